@@ -0,0 +1,809 @@
+/*
+ * DJI Tello Drone Controller Library
+ *
+ * Copyright (c) 2025 aleskin
+ *
+ * This file is part of dji_tello_lib.
+ *
+ * dji_tello_lib is free software: you can redistribute it and/or modify
+ * it under the terms of the MIT License as published.
+ *
+ * Created: March 30, 2025
+ */
+
+// Typed commands of the command line, parsed without touching the drone
+use std::error::Error;
+use std::fmt;
+use std::io;
+use std::str::FromStr;
+use std::time::Duration;
+use crate::error::TelloError;
+use crate::telemetry::{LogFormat, TelemetryState};
+use crate::tello::{CameraSelect, Position, VideoFps, VideoResolution};
+use crate::tello_movement::FlipDirection;
+
+/// A command line command with its arguments parsed
+///
+/// Speeds left out are `None` and default to the last speed set on the
+/// drone when the command is executed.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Command {
+    // System commands
+    Help,
+    Version,
+    Info { json: bool },
+    WifiSignal,
+    Identity,
+    SetWifi { ssid: String, password: String },
+    ConnectToAp { ssid: String, password: String },
+    Reconnect,
+    Reboot { confirmed: bool },
+    ConfigReload,
+    Raw(String),
+    Exit,
+    Wait(Duration),
+    Repeat(String),
+    Sequence(String),
+    If { condition: Condition, then: String },
+    Hover(Duration),
+    Script { path: String, continue_on_error: bool },
+    
+    // Flight control commands
+    Takeoff(Option<f32>),
+    ThrowTakeoff,
+    Land,
+    Arm,
+    Disarm,
+    Emergency,
+    Battery,
+    Motor(bool),
+    Temperature,
+    Height,
+    Velocity,
+    TelemetryLog(TelemetryLogCommand),
+    FlightTime,
+    ShowCeiling,
+    SetCeiling(i32),
+    ClearCeiling,
+    ShowGeofence,
+    SetGeofence(Position, Position),
+    ClearGeofence,
+    Status,
+    State { json: bool },
+    
+    // Movement commands, distances in cm and angles in degrees
+    Forward(i32),
+    Back(i32),
+    Left(i32),
+    Right(i32),
+    Up(i32),
+    Down(i32),
+    RotateCw(i32),
+    RotateCcw(i32),
+    Flip(FlipDirection),
+    GetSpeed,
+    SetSpeed(i32),
+    Go { x: i32, y: i32, z: i32, speed: i32 },
+    Curve { p1: (i32, i32, i32), p2: (i32, i32, i32), speed: i32 },
+    Move { forward: i32, right: i32, up: i32, speed: Option<i32> },
+    Square { side: i32, speed: Option<i32> },
+    Circle { radius: i32, speed: Option<i32> },
+    Polygon { sides: u32, side: i32, speed: Option<i32> },
+    FigureEight { radius: i32, speed: Option<i32> },
+    GoToPad { x: i32, y: i32, z: i32, speed: i32, pad: u8 },
+    Jump { target: (i32, i32, i32), speed: i32, yaw: i32, from_pad: u8, to_pad: u8 },
+    Clamp(Option<bool>),
+    Rc { roll: i32, pitch: i32, throttle: i32, yaw: i32 },
+    Stop,
+    
+    // Camera commands
+    Photo,
+    SavePhoto(Option<String>),
+    Video(VideoCommand),
+    Resolution(VideoResolution),
+    Fps(VideoFps),
+    Camera(CameraSelect),
+    SetBitrate(u8),
+    GetBitrate,
+    
+    // Media commands
+    Media(MediaCommand),
+    
+    // Positioning commands, in meters
+    SetPosition(Position),
+    GetPosition,
+    FlyTo { target: Position, speed: Option<i32> },
+    Home { speed: Option<i32> },
+    LandAt { x: f32, y: f32 },
+    Direction(Option<f32>),
+    Path(PathCommand),
+    MissionPad(MissionPadCommand),
+    CameraToCenter { x: f32, y: f32, z: Option<f32> },
+    CameraFromCenter { x: f32, y: f32 },
+    
+    // Maintenance commands
+    Calibrate,
+}
+
+/// The "video" subcommands
+#[derive(Debug, Clone, PartialEq)]
+pub enum VideoCommand {
+    Start,
+    Record(String),
+    Stop,
+}
+
+/// The "media" subcommands
+#[derive(Debug, Clone, PartialEq)]
+pub enum MediaCommand {
+    List,
+    Download(String),
+    DownloadAll,
+    Direct(String),
+    Delete(String),
+    DeleteAll,
+    Path(String),
+}
+
+/// The "path" subcommands
+#[derive(Debug, Clone, PartialEq)]
+pub enum PathCommand {
+    Show,
+    Save(String),
+    Fly { file: String, speed: Option<i32> },
+}
+
+/// The "telemetry log" subcommands
+#[derive(Debug, Clone, PartialEq)]
+pub enum TelemetryLogCommand {
+    Start { file: String, format: LogFormat },
+    Stop,
+}
+
+/// The "mpad" subcommands
+#[derive(Debug, Clone, PartialEq)]
+pub enum MissionPadCommand {
+    Show,
+    On,
+    Off,
+    Direction(u8),
+}
+
+/// The telemetry value an "if" command looks at
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConditionField {
+    /// Battery in percent
+    Battery,
+    /// Height in cm
+    Height,
+    /// Highest temperature in degrees Celsius
+    Temperature,
+}
+
+impl fmt::Display for ConditionField {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConditionField::Battery => write!(f, "battery"),
+            ConditionField::Height => write!(f, "height"),
+            ConditionField::Temperature => write!(f, "temp"),
+        }
+    }
+}
+
+/// How an "if" command compares the telemetry value
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Comparison {
+    Less,
+    Greater,
+    Equal,
+}
+
+impl fmt::Display for Comparison {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Comparison::Less => write!(f, "<"),
+            Comparison::Greater => write!(f, ">"),
+            Comparison::Equal => write!(f, "=="),
+        }
+    }
+}
+
+/// The "<field> <op> <value>" test of an "if" command, e.g. "battery < 20"
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Condition {
+    pub field: ConditionField,
+    pub comparison: Comparison,
+    pub value: i32,
+}
+
+impl Condition {
+    /// Whether the condition holds for a state packet
+    ///
+    /// `None` if the packet doesn't have the field.
+    pub fn holds(&self, state: &TelemetryState) -> Option<bool> {
+        let actual = match self.field {
+            ConditionField::Battery => state.bat?,
+            ConditionField::Height => state.h?,
+            ConditionField::Temperature => state.temph?,
+        };
+        Some(match self.comparison {
+            Comparison::Less => actual < self.value,
+            Comparison::Greater => actual > self.value,
+            Comparison::Equal => actual == self.value,
+        })
+    }
+}
+
+impl fmt::Display for Condition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {} {}", self.field, self.comparison, self.value)
+    }
+}
+
+/// A command that is unknown or has missing or malformed arguments
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseError(pub String);
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl Error for ParseError {}
+
+// Parse errors surface like the other usage errors of the command line
+impl From<ParseError> for io::Error {
+    fn from(e: ParseError) -> Self {
+        io::Error::new(io::ErrorKind::InvalidInput, e.0)
+    }
+}
+
+impl Command {
+    /// Check argument ranges before anything is sent to the drone
+    ///
+    /// Movement distances must be 1-500 cm unless `clamp_movements` is on,
+    /// rotations 1-360 degrees if `strict_rotation` is on (otherwise they
+    /// are normalized), and takeoff heights above 0 and at most 8 m.
+    pub fn check_ranges(&self, clamp_movements: bool, strict_rotation: bool) -> Result<(), ParseError> {
+        match *self {
+            Command::Forward(distance) | Command::Back(distance) | Command::Left(distance)
+            | Command::Right(distance) | Command::Up(distance) | Command::Down(distance)
+                if !clamp_movements && !(1..=500).contains(&distance) =>
+                Err(ParseError(format!("Distance must be 1-500 cm, got {}", distance))),
+            Command::RotateCw(degrees) | Command::RotateCcw(degrees)
+                if strict_rotation && !(1..=360).contains(&degrees) =>
+                Err(ParseError(format!("Rotation must be 1-360 degrees, got {}", degrees))),
+            Command::Takeoff(Some(height)) if !(height > 0.0 && height <= 8.0) =>
+                Err(ParseError(format!("Takeoff height must be above 0 and at most 8 m, got {}", height))),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Parse a command split into words, e.g. ["forward", "50"]
+pub fn parse_command(parts: &[&str]) -> Result<Command, ParseError> {
+    let Some(&name) = parts.first() else {
+        return Err(ParseError(String::from("Empty command")));
+    };
+    let json = parts[1..].contains(&"--json");
+    
+    let command = match name {
+        // === SYSTEM COMMANDS ===
+        "help" => Command::Help,
+        "version" => Command::Version,
+        "info" => Command::Info { json },
+        "wifi" => match parts.get(1..) {
+            Some([]) | None => Command::WifiSignal,
+            Some(["set", ssid, password, ..]) => Command::SetWifi { ssid: ssid.to_string(), password: password.to_string() },
+            Some(_) => return Err(ParseError(String::from("Usage: wifi set <ssid> <password>"))),
+        },
+        "ap" => match parts.get(1..3) {
+            Some([ssid, password]) => Command::ConnectToAp { ssid: ssid.to_string(), password: password.to_string() },
+            _ => return Err(ParseError(String::from("Please specify the network: ap <ssid> <password>"))),
+        },
+        "reconnect" => Command::Reconnect,
+        "reboot" => match parts.get(1) {
+            None => Command::Reboot { confirmed: false },
+            Some(&"--yes") => Command::Reboot { confirmed: true },
+            Some(_) => return Err(ParseError(String::from("Usage: reboot [--yes]"))),
+        },
+        "id" => Command::Identity,
+        "raw" if parts.len() > 1 => Command::Raw(parts[1..].join(" ")),
+        "raw" => return Err(ParseError(String::from("Usage: raw <sdk command>"))),
+        "config" => match parts.get(1) {
+            Some(&"reload") => Command::ConfigReload,
+            _ => return Err(ParseError(String::from("Usage: config reload"))),
+        },
+        "exit" => Command::Exit,
+        "wait" => {
+            let arg = required(parts, 1, "Please specify wait time in seconds")?;
+            match arg.parse::<f64>() {
+                Ok(seconds) if seconds >= 0.0 => Command::Wait(Duration::from_millis((seconds * 1000.0) as u64)),
+                _ => return Err(ParseError(format!("Invalid wait time: {}. Please specify a number of seconds.", arg))),
+            }
+        },
+        "repeat" => Command::Repeat(parts[1..].join(" ")),
+        "sequence" => Command::Sequence(parts[1..].join(" ")),
+        "if" => parse_if(parts)?,
+        "alias" | "unalias" => return Err(ParseError(String::from("Aliases can only be changed in interactive mode"))),
+        "history" => return Err(ParseError(String::from("History is only kept in interactive mode"))),
+        "record" => return Err(ParseError(String::from("Flights can only be recorded in interactive mode"))),
+        "prompt" => return Err(ParseError(String::from("The prompt can only be changed in interactive mode"))),
+        "hover" => match parts.get(1).map(|arg| arg.parse::<f64>()) {
+            Some(Ok(seconds)) => Command::Hover(Duration::try_from_secs_f64(seconds)
+                .map_err(|_| ParseError(format!("Invalid hover time: {}", seconds)))?),
+            _ => return Err(ParseError(String::from("Please specify how long to hover: hover <seconds>"))),
+        },
+        "script" => Command::Script {
+            path: required(parts, 1, "Please specify a script file: script <path> [--continue]")?.to_string(),
+            continue_on_error: parts.get(2..).is_some_and(|rest| rest.contains(&"--continue")),
+        },
+        
+        // === FLIGHT CONTROL COMMANDS ===
+        "takeoff" => Command::Takeoff(match parts.get(1) {
+            Some(arg) => Some(value(arg, "Invalid height value")?),
+            None => None,
+        }),
+        "throwfly" => Command::ThrowTakeoff,
+        "land" => Command::Land,
+        "arm" => Command::Arm,
+        "disarm" => Command::Disarm,
+        "emergency" | "kill" => Command::Emergency,
+        "battery" => Command::Battery,
+        "motor" => match parts.get(1) {
+            Some(&"on") => Command::Motor(true),
+            Some(&"off") => Command::Motor(false),
+            _ => return Err(ParseError(String::from("Usage: motor on|off"))),
+        },
+        "calibrate" => Command::Calibrate,
+        "temp" => Command::Temperature,
+        "height" => Command::Height,
+        "velocity" => Command::Velocity,
+        "telemetry" => Command::TelemetryLog(match (parts.get(1), parts.get(2)) {
+            (Some(&"log"), Some(&"start")) => TelemetryLogCommand::Start {
+                file: required(parts, 3, "Please specify a file: telemetry log start <file> [csv|json]")?.to_string(),
+                format: match parts.get(4) {
+                    Some(format) => format.parse().map_err(|e: TelloError| ParseError(e.to_string()))?,
+                    None => LogFormat::Csv,
+                },
+            },
+            (Some(&"log"), Some(&"stop")) => TelemetryLogCommand::Stop,
+            _ => return Err(ParseError(String::from("Usage: telemetry log start <file> [csv|json], or telemetry log stop"))),
+        }),
+        "flighttime" => Command::FlightTime,
+        "ceiling" => match parts.get(1) {
+            None => Command::ShowCeiling,
+            Some(&"off") => Command::ClearCeiling,
+            Some(arg) => match arg.parse::<i32>() {
+                Ok(cm) if cm > 0 => Command::SetCeiling(cm),
+                _ => return Err(ParseError(format!("Invalid ceiling: {}. Specify a height in cm or 'off'.", arg))),
+            },
+        },
+        "geofence" => match &parts[1..] {
+            [] => Command::ShowGeofence,
+            ["off"] => Command::ClearGeofence,
+            args if args.len() == 6 => {
+                let values = args.iter()
+                    .map(|arg| value::<f32>(arg, "Invalid geofence value"))
+                    .collect::<Result<Vec<f32>, ParseError>>()?;
+                Command::SetGeofence(Position { x: values[0], y: values[1], z: values[2] },
+                    Position { x: values[3], y: values[4], z: values[5] })
+            },
+            _ => return Err(ParseError(String::from(
+                "Usage: geofence <minx> <miny> <minz> <maxx> <maxy> <maxz>, or geofence off"))),
+        },
+        "status" => Command::Status,
+        "state" => Command::State { json },
+        
+        // === MOVEMENT COMMANDS ===
+        "forward" | "back" | "left" | "right" | "up" | "down" => {
+            let arg = required(parts, 1, &format!("Please specify distance for {} movement", name))?;
+            let distance = value(arg, "Invalid distance value")?;
+            match name {
+                "forward" => Command::Forward(distance),
+                "back" => Command::Back(distance),
+                "left" => Command::Left(distance),
+                "right" => Command::Right(distance),
+                "up" => Command::Up(distance),
+                _ => Command::Down(distance),
+            }
+        },
+        "rotate_cw" => Command::RotateCw(value(
+            required(parts, 1, "Please specify degrees for clockwise rotation")?, "Invalid degrees value")?),
+        "rotate_ccw" => Command::RotateCcw(value(
+            required(parts, 1, "Please specify degrees for counter-clockwise rotation")?, "Invalid degrees value")?),
+        "flip" => {
+            let arg = required(parts, 1, "Please specify flip direction: l, r, f or b")?;
+            Command::Flip(arg.parse::<FlipDirection>().map_err(|e| ParseError(e.to_string()))?)
+        },
+        "speed" => match parts.get(1) {
+            Some(arg) => Command::SetSpeed(value(arg, "Invalid speed value")?),
+            None => Command::GetSpeed,
+        },
+        "go" => {
+            let values = int_args(parts, 4, "go", "Please specify all values: go <x> <y> <z> <speed>")?;
+            Command::Go { x: values[0], y: values[1], z: values[2], speed: values[3] }
+        },
+        "curve" => {
+            let values = int_args(parts, 7, "curve",
+                "Please specify all values: curve <x1> <y1> <z1> <x2> <y2> <z2> <speed>")?;
+            Command::Curve { p1: (values[0], values[1], values[2]), p2: (values[3], values[4], values[5]), speed: values[6] }
+        },
+        "move" => {
+            let values = int_args(parts, 3, "distance", "Please specify all distances: move <forward> <right> <up> [speed]")?;
+            Command::Move { forward: values[0], right: values[1], up: values[2], speed: optional_speed(parts, 4)? }
+        },
+        "square" => {
+            let side = value(required(parts, 1, "Please specify the side length: square <cm> [speed]")?, "Invalid side length")?;
+            Command::Square { side, speed: optional_speed(parts, 2)? }
+        },
+        "circle" => {
+            let radius = value(required(parts, 1, "Please specify the radius: circle <cm> [speed]")?, "Invalid radius")?;
+            Command::Circle { radius, speed: optional_speed(parts, 2)? }
+        },
+        "polygon" => {
+            let usage = "Please specify the sides and side length: polygon <sides> <cm> [speed]";
+            let sides = value(required(parts, 1, usage)?, "Invalid number of sides")?;
+            let side = value(required(parts, 2, usage)?, "Invalid side length")?;
+            Command::Polygon { sides, side, speed: optional_speed(parts, 3)? }
+        },
+        "eight" => {
+            let radius = value(required(parts, 1, "Please specify the radius: eight <cm> [speed]")?, "Invalid radius")?;
+            Command::FigureEight { radius, speed: optional_speed(parts, 2)? }
+        },
+        "go_to_pad" => {
+            let usage = "Please specify all values: go_to_pad <x> <y> <z> <speed> <pad>";
+            let values = int_args(parts, 4, "go_to_pad", usage)?;
+            let pad = value(required(parts, 5, usage)?, "Invalid mission pad id")?;
+            Command::GoToPad { x: values[0], y: values[1], z: values[2], speed: values[3], pad }
+        },
+        "jump" => {
+            let usage = "Please specify all values: jump <x> <y> <z> <speed> <yaw> <pad1> <pad2>";
+            let values = int_args(parts, 5, "jump", usage)?;
+            let from_pad = value(required(parts, 6, usage)?, "Invalid mission pad id")?;
+            let to_pad = value(required(parts, 7, usage)?, "Invalid mission pad id")?;
+            Command::Jump { target: (values[0], values[1], values[2]), speed: values[3], yaw: values[4], from_pad, to_pad }
+        },
+        "clamp" => match parts.get(1) {
+            Some(&"on") => Command::Clamp(Some(true)),
+            Some(&"off") => Command::Clamp(Some(false)),
+            Some(other) => return Err(ParseError(format!("Invalid clamp setting: {}. Use 'on' or 'off'.", other))),
+            None => Command::Clamp(None),
+        },
+        "rc" => {
+            let values = int_args(parts, 4, "RC", "Please specify all channels: rc <a> <b> <c> <d>")?;
+            Command::Rc { roll: values[0], pitch: values[1], throttle: values[2], yaw: values[3] }
+        },
+        "stop" => Command::Stop,
+        
+        // === CAMERA COMMANDS ===
+        "photo" if parts.get(1) == Some(&"save") => Command::SavePhoto(parts.get(2).map(|path| path.to_string())),
+        "photo" => Command::Photo,
+        "video" => match parts.get(1) {
+            Some(&"start") => Command::Video(VideoCommand::Start),
+            Some(&"record") => Command::Video(VideoCommand::Record(
+                required(parts, 2, "Please specify a file: video record <file>")?.to_string())),
+            Some(&"stop") => Command::Video(VideoCommand::Stop),
+            Some(other) => return Err(ParseError(format!("Unknown video command: {}", other))),
+            None => return Err(ParseError(String::from("Please specify 'start', 'record' or 'stop' after 'video'"))),
+        },
+        "resolution" => Command::Resolution(required(parts, 1, "Usage: resolution high|low")?
+            .parse().map_err(|e: TelloError| ParseError(e.to_string()))?),
+        "fps" => Command::Fps(required(parts, 1, "Usage: fps high|middle|low")?
+            .parse().map_err(|e: TelloError| ParseError(e.to_string()))?),
+        "camera" => Command::Camera(required(parts, 1, "Usage: camera forward|down")?
+            .parse().map_err(|e: TelloError| ParseError(e.to_string()))?),
+        "bitrate" => {
+            let arg = required(parts, 1, "Usage: bitrate <0-5>, or bitrate? to show it")?;
+            Command::SetBitrate(arg.parse::<u8>()
+                .map_err(|_| ParseError(format!("Invalid bitrate: {}. Specify 0 (auto) to 5.", arg)))?)
+        },
+        "bitrate?" => Command::GetBitrate,
+        
+        // === MEDIA COMMANDS ===
+        "media" => Command::Media(match parts.get(1) {
+            Some(&"list") => MediaCommand::List,
+            Some(&"download") => MediaCommand::Download(
+                required(parts, 2, "Please specify a filename to download")?.to_string()),
+            Some(&"downloadall") => MediaCommand::DownloadAll,
+            Some(&"direct") => MediaCommand::Direct(
+                required(parts, 2, "Please specify a filename for direct transfer")?.to_string()),
+            Some(&"delete") => MediaCommand::Delete(
+                required(parts, 2, "Please specify a filename to delete")?.to_string()),
+            Some(&"deleteall") => MediaCommand::DeleteAll,
+            Some(&"path") => MediaCommand::Path(
+                required(parts, 2, "Please specify a path for media downloads")?.to_string()),
+            Some(other) => return Err(ParseError(format!("Unknown media command: {}", other))),
+            None => return Err(ParseError(String::from(
+                "Please specify a media command: list, download, downloadall, delete, deleteall, path"))),
+        }),
+        
+        // === POSITIONING COMMANDS ===
+        "position" => {
+            if parts.len() < 4 {
+                return Err(ParseError(String::from("Please specify all coordinates: position <x> <y> <z>")));
+            }
+            Command::SetPosition(Position {
+                x: value(parts[1], "Invalid x-coordinate")?,
+                y: value(parts[2], "Invalid y-coordinate")?,
+                z: value(parts[3], "Invalid z-coordinate")?,
+            })
+        },
+        "get_position" => Command::GetPosition,
+        "fly_to" => {
+            if parts.len() < 4 {
+                return Err(ParseError(String::from("Please specify a target: fly_to <x> <y> <z> [speed]")));
+            }
+            Command::FlyTo {
+                target: Position {
+                    x: value(parts[1], "Invalid coordinate")?,
+                    y: value(parts[2], "Invalid coordinate")?,
+                    z: value(parts[3], "Invalid coordinate")?,
+                },
+                speed: optional_speed(parts, 4)?,
+            }
+        },
+        "home" => Command::Home { speed: optional_speed(parts, 1)? },
+        "land_at" => {
+            if parts.len() < 3 {
+                return Err(ParseError(String::from("Please specify a target: land_at <x> <y>")));
+            }
+            Command::LandAt {
+                x: value(parts[1], "Invalid coordinate")?,
+                y: value(parts[2], "Invalid coordinate")?,
+            }
+        },
+        "direction" => Command::Direction(match parts.get(1) {
+            Some(arg) => Some(value(arg, "Invalid direction")?),
+            None => None,
+        }),
+        "path" => Command::Path(match parts.get(1) {
+            None => PathCommand::Show,
+            Some(&"save") => PathCommand::Save(
+                required(parts, 2, "Please specify a file: path save <file>")?.to_string()),
+            Some(&"fly") => PathCommand::Fly {
+                file: required(parts, 2, "Please specify a file: path fly <file> [speed]")?.to_string(),
+                speed: optional_speed(parts, 3)?,
+            },
+            Some(other) => return Err(ParseError(
+                format!("Unknown path command: {}. Use: path [save <file> | fly <file> [speed]]", other))),
+        }),
+        "mpad" => Command::MissionPad(match parts.get(1) {
+            None => MissionPadCommand::Show,
+            Some(&"on") => MissionPadCommand::On,
+            Some(&"off") => MissionPadCommand::Off,
+            Some(&"dir") => match parts.get(2).and_then(|arg| arg.parse::<u8>().ok()) {
+                Some(direction) => MissionPadCommand::Direction(direction),
+                None => return Err(ParseError(String::from("Please specify a direction: mpad dir <0-2>"))),
+            },
+            Some(other) => return Err(ParseError(format!("Unknown mpad command: {}", other))),
+        }),
+        "camera_to_center" => {
+            if parts.len() < 3 {
+                return Err(ParseError(String::from("Please specify center coordinates: camera_to_center <x> <y>")));
+            }
+            Command::CameraToCenter {
+                x: value(parts[1], "Invalid x-coordinate")?,
+                y: value(parts[2], "Invalid y-coordinate")?,
+                z: match parts.get(3) {
+                    Some(arg) => Some(value(arg, "Invalid z-coordinate")?),
+                    None => None,
+                },
+            }
+        },
+        "camera_from_center" => {
+            if parts.len() < 3 {
+                return Err(ParseError(String::from("Please specify center coordinates: camera_from_center <x> <y>")));
+            }
+            Command::CameraFromCenter {
+                x: value(parts[1], "Invalid x-coordinate")?,
+                y: value(parts[2], "Invalid y-coordinate")?,
+            }
+        },
+        _ => return Err(ParseError(format!("Unknown command: {}. Type 'help' for available commands.", name))),
+    };
+    
+    Ok(command)
+}
+
+/// The argument at `index`, or `usage` as the error if it is missing
+fn required<'a>(parts: &[&'a str], index: usize, usage: &str) -> Result<&'a str, ParseError> {
+    parts.get(index).copied().ok_or_else(|| ParseError(usage.to_string()))
+}
+
+/// Parse one argument, e.g. "Invalid distance value: abc" on failure
+fn value<T: FromStr>(arg: &str, what: &str) -> Result<T, ParseError> {
+    arg.parse::<T>().map_err(|_| ParseError(format!("{}: {}", what, arg)))
+}
+
+/// Parse the `count` integer arguments after the command name
+fn int_args(parts: &[&str], count: usize, what: &str, usage: &str) -> Result<Vec<i32>, ParseError> {
+    let args = parts.get(1..=count).ok_or_else(|| ParseError(usage.to_string()))?;
+    args.iter()
+        .map(|arg| value(arg, &format!("Invalid {} value", what)))
+        .collect()
+}
+
+/// An optional speed argument in cm/s
+fn optional_speed(parts: &[&str], index: usize) -> Result<Option<i32>, ParseError> {
+    parts.get(index).map(|arg| value(arg, "Invalid speed value")).transpose()
+}
+
+/// Parse "if <field> <op> <value> then <command>"
+fn parse_if(parts: &[&str]) -> Result<Command, ParseError> {
+    let usage = || ParseError(String::from("Usage: if <battery|height|temp> <<|>|==> <value> then <command>"));
+    let &[_, field, comparison, value, "then", ref then @ ..] = parts else {
+        return Err(usage());
+    };
+    if then.is_empty() {
+        return Err(usage());
+    }
+    
+    let field = match field {
+        "battery" => ConditionField::Battery,
+        "height" => ConditionField::Height,
+        "temp" => ConditionField::Temperature,
+        _ => return Err(ParseError(format!("Unknown field: {}. Use battery, height or temp", field))),
+    };
+    let comparison = match comparison {
+        "<" => Comparison::Less,
+        ">" => Comparison::Greater,
+        "==" => Comparison::Equal,
+        _ => return Err(ParseError(format!("Unknown comparison: {}. Use <, > or ==", comparison))),
+    };
+    let value = value.parse::<i32>().map_err(|_| ParseError(format!("Invalid value: {}", value)))?;
+    
+    if then[0] == "if" {
+        return Err(ParseError(String::from("An if can't contain another if")));
+    }
+    Ok(Command::If { condition: Condition { field, comparison, value }, then: then.join(" ") })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn parse(line: &str) -> Result<Command, ParseError> {
+        parse_command(&line.split_whitespace().collect::<Vec<&str>>())
+    }
+    
+    #[test]
+    fn test_parse_movement() {
+        assert_eq!(parse("forward 50"), Ok(Command::Forward(50)));
+        assert_eq!(parse("down 20"), Ok(Command::Down(20)));
+        assert_eq!(parse("forward abc"), Err(ParseError(String::from("Invalid distance value: abc"))));
+        assert!(parse("forward").is_err());
+        assert_eq!(parse("rotate_cw 90"), Ok(Command::RotateCw(90)));
+        assert_eq!(parse("flip f"), Ok(Command::Flip(FlipDirection::Forward)));
+        assert_eq!(parse("go 100 0 50 30"), Ok(Command::Go { x: 100, y: 0, z: 50, speed: 30 }));
+        assert!(parse("go 100 0 50").is_err());
+        assert_eq!(parse("move 50 -30 20"), Ok(Command::Move { forward: 50, right: -30, up: 20, speed: None }));
+        assert_eq!(parse("move 50 -30 20 40"), Ok(Command::Move { forward: 50, right: -30, up: 20, speed: Some(40) }));
+        assert_eq!(parse("square 100"), Ok(Command::Square { side: 100, speed: None }));
+        assert_eq!(parse("circle 80 30"), Ok(Command::Circle { radius: 80, speed: Some(30) }));
+        assert!(parse("square").is_err());
+        assert_eq!(parse("polygon 6 50"), Ok(Command::Polygon { sides: 6, side: 50, speed: None }));
+        assert_eq!(parse("polygon 5 80 30"), Ok(Command::Polygon { sides: 5, side: 80, speed: Some(30) }));
+        assert!(parse("polygon 6").is_err());
+        assert!(parse("polygon -3 50").is_err());
+        assert_eq!(parse("eight 100"), Ok(Command::FigureEight { radius: 100, speed: None }));
+        assert!(parse("circle big").is_err());
+        assert!(parse("move 50 30").is_err());
+        assert_eq!(parse("go_to_pad 0 0 80 50 1"), Ok(Command::GoToPad { x: 0, y: 0, z: 80, speed: 50, pad: 1 }));
+        assert!(parse("go_to_pad 0 0 80 50").is_err());
+        assert_eq!(parse("jump 100 0 80 50 90 1 2"),
+            Ok(Command::Jump { target: (100, 0, 80), speed: 50, yaw: 90, from_pad: 1, to_pad: 2 }));
+        assert_eq!(parse("jump 100 0 80 50 90 1 x"), Err(ParseError(String::from("Invalid mission pad id: x"))));
+        assert_eq!(parse("rc 0 10 0 -10"), Ok(Command::Rc { roll: 0, pitch: 10, throttle: 0, yaw: -10 }));
+        assert_eq!(parse("speed"), Ok(Command::GetSpeed));
+        assert_eq!(parse("clamp on"), Ok(Command::Clamp(Some(true))));
+    }
+    
+    #[test]
+    fn test_check_ranges() {
+        let check = |line: &str| parse(line).unwrap().check_ranges(false, false);
+        
+        assert_eq!(check("forward 9999"), Err(ParseError(String::from("Distance must be 1-500 cm, got 9999"))));
+        assert_eq!(check("down 0"), Err(ParseError(String::from("Distance must be 1-500 cm, got 0"))));
+        assert_eq!(check("takeoff 9"), Err(ParseError(String::from("Takeoff height must be above 0 and at most 8 m, got 9"))));
+        assert_eq!(check("takeoff -1"), Err(ParseError(String::from("Takeoff height must be above 0 and at most 8 m, got -1"))));
+        assert_eq!(check("forward 500"), Ok(()));
+        assert_eq!(check("takeoff 8"), Ok(()));
+        assert_eq!(check("takeoff"), Ok(()));
+        
+        // Rotations are normalized unless strict, long moves split when clamping
+        assert_eq!(check("rotate_cw 450"), Ok(()));
+        assert_eq!(parse("rotate_ccw 0").unwrap().check_ranges(false, true),
+            Err(ParseError(String::from("Rotation must be 1-360 degrees, got 0"))));
+        assert_eq!(parse("forward 9999").unwrap().check_ranges(true, false), Ok(()));
+    }
+    
+    #[test]
+    fn test_parse_flight_control() {
+        assert_eq!(parse("takeoff"), Ok(Command::Takeoff(None)));
+        assert_eq!(parse("takeoff 2"), Ok(Command::Takeoff(Some(2.0))));
+        assert!(parse("takeoff high").is_err());
+        assert_eq!(parse("kill"), Ok(Command::Emergency));
+        assert_eq!(parse("motor off"), Ok(Command::Motor(false)));
+        assert_eq!(parse("ceiling off"), Ok(Command::ClearCeiling));
+        assert!(parse("ceiling -5").is_err());
+        assert_eq!(parse("geofence -1 -1 0 1 1 2"), Ok(Command::SetGeofence(
+            Position { x: -1.0, y: -1.0, z: 0.0 }, Position { x: 1.0, y: 1.0, z: 2.0 })));
+        assert!(parse("geofence 1 2").is_err());
+        assert_eq!(parse("state --json"), Ok(Command::State { json: true }));
+    }
+    
+    #[test]
+    fn test_parse_subcommands() {
+        assert_eq!(parse("media download photo_01.jpg"),
+            Ok(Command::Media(MediaCommand::Download(String::from("photo_01.jpg")))));
+        assert_eq!(parse("media downloadall"), Ok(Command::Media(MediaCommand::DownloadAll)));
+        assert!(parse("media").is_err());
+        assert!(parse("media bogus").is_err());
+        assert_eq!(parse("video record flight.h264"), Ok(Command::Video(VideoCommand::Record(String::from("flight.h264")))));
+        assert_eq!(parse("photo save"), Ok(Command::SavePhoto(None)));
+        assert_eq!(parse("path fly square.csv 40"),
+            Ok(Command::Path(PathCommand::Fly { file: String::from("square.csv"), speed: Some(40) })));
+        assert_eq!(parse("mpad dir 2"), Ok(Command::MissionPad(MissionPadCommand::Direction(2))));
+        assert_eq!(parse("resolution low"), Ok(Command::Resolution(VideoResolution::Low)));
+        assert_eq!(parse("camera down"), Ok(Command::Camera(CameraSelect::Downward)));
+        assert_eq!(parse("camera forward"), Ok(Command::Camera(CameraSelect::Forward)));
+        assert!(parse("camera").is_err());
+        assert!(parse("camera up").is_err());
+        assert!(parse("fps fast").is_err());
+    }
+    
+    #[test]
+    fn test_parse_positioning() {
+        assert_eq!(parse("fly_to 1 2 0.5"), Ok(Command::FlyTo { target: Position { x: 1.0, y: 2.0, z: 0.5 }, speed: None }));
+        assert!(parse("fly_to 1 2 0.5 fast").is_err());
+        assert_eq!(parse("home 30"), Ok(Command::Home { speed: Some(30) }));
+        assert_eq!(parse("land_at 1.5 -2"), Ok(Command::LandAt { x: 1.5, y: -2.0 }));
+        assert!(parse("land_at 1.5").is_err());
+        assert_eq!(parse("camera_to_center 1 1"), Ok(Command::CameraToCenter { x: 1.0, y: 1.0, z: None }));
+        assert_eq!(parse("direction"), Ok(Command::Direction(None)));
+    }
+    
+    #[test]
+    fn test_parse_system() {
+        assert_eq!(parse("wait 1.5"), Ok(Command::Wait(Duration::from_millis(1500))));
+        assert!(parse("wait -1").is_err());
+        assert_eq!(parse("script flight.txt --continue"),
+            Ok(Command::Script { path: String::from("flight.txt"), continue_on_error: true }));
+        assert_eq!(parse("repeat 2 forward 50; land"), Ok(Command::Repeat(String::from("2 forward 50; land"))));
+        assert_eq!(parse("if battery < 20 then land"), Ok(Command::If {
+            condition: Condition { field: ConditionField::Battery, comparison: Comparison::Less, value: 20 },
+            then: String::from("land"),
+        }));
+        assert_eq!(parse("if height > 150 then down 50"), Ok(Command::If {
+            condition: Condition { field: ConditionField::Height, comparison: Comparison::Greater, value: 150 },
+            then: String::from("down 50"),
+        }));
+        assert!(parse("if battery < 20 land").is_err());
+        assert!(parse("if battery < 20 then").is_err());
+        assert!(parse("if speed < 20 then land").is_err());
+        assert!(parse("if battery <= 20 then land").is_err());
+        assert!(parse("if battery < 20 then if height > 100 then land").is_err());
+        assert!(parse("alias square = forward 50").is_err());
+        assert_eq!(parse("config reload"), Ok(Command::ConfigReload));
+        assert_eq!(parse("reboot"), Ok(Command::Reboot { confirmed: false }));
+        assert_eq!(parse("reboot --yes"), Ok(Command::Reboot { confirmed: true }));
+        assert!(parse("reboot now").is_err());
+        assert_eq!(parse("id"), Ok(Command::Identity));
+        assert_eq!(parse("velocity"), Ok(Command::Velocity));
+        assert_eq!(parse("telemetry log start flight.csv"), Ok(Command::TelemetryLog(
+            TelemetryLogCommand::Start { file: String::from("flight.csv"), format: LogFormat::Csv })));
+        assert_eq!(parse("telemetry log start flight.jsonl json"), Ok(Command::TelemetryLog(
+            TelemetryLogCommand::Start { file: String::from("flight.jsonl"), format: LogFormat::Json })));
+        assert_eq!(parse("telemetry log stop"), Ok(Command::TelemetryLog(TelemetryLogCommand::Stop)));
+        assert!(parse("telemetry log start").is_err());
+        assert!(parse("telemetry log start flight.csv xml").is_err());
+        assert!(parse("telemetry").is_err());
+        assert!(parse("config").is_err());
+        assert_eq!(parse("raw EXT led 255 0 0"), Ok(Command::Raw(String::from("EXT led 255 0 0"))));
+        assert!(parse("raw").is_err());
+        assert!(parse("bogus").unwrap_err().to_string().contains("Unknown command: bogus"));
+        assert!(parse_command(&[]).is_err());
+    }
+}
@@ -11,37 +11,79 @@
  * Created: March 30, 2025
  */
 
-use std::io::{self};
+use std::fmt;
+use std::io::{self, Write};
 use std::thread;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use std::collections::HashMap;
-use std::path::PathBuf;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
 use rustyline::error::ReadlineError;
 use rustyline::{Editor, Config, CompletionType};
+use rustyline::{Cmd, ConditionalEventHandler, Event, EventContext, EventHandler, KeyCode, KeyEvent, Modifiers, RepeatCount};
 use rustyline::completion::{Completer, Pair};
 use rustyline::highlight::Highlighter;
 use rustyline::hint::Hinter;
 use rustyline::validate::Validator;
 use rustyline::Helper;
-use crate::tello::Tello;
+use crate::logging::Level;
+use crate::command::{parse_command, Command, Condition, MediaCommand, MissionPadCommand, PathCommand, TelemetryLogCommand, VideoCommand};
+use crate::config::TelloConfig;
+use crate::tello::{response_timeout, CommandCategory, Tello, COMMAND_TIMEOUT};
+use crate::telemetry::TelemetryState;
+use crate::tello_movement::estimate_move_time;
+use serde_json::json;
 
 // Version of the application defined in Makefile.version
 // and injected via build.rs during compilation
 const VERSION: &str = env!("TELLO_LIB_VERSION");
 const BUILD_DATE: &str = env!("TELLO_BUILD_DATE");
+const DEFAULT_FLY_TO_SPEED: i32 = 50; // cm/s, used by fly_to when no speed was set
+const LANDING_TIMEOUT: Duration = Duration::from_secs(10); // How long "land" waits for telemetry to show the drone down
+const DEFAULT_PROMPT: &str = "> ";
+const ESC_TIMEOUT_MS: i32 = 100; // Wait for the rest of an escape sequence before taking Esc alone
+const MAX_SCRIPT_DEPTH: u32 = 8; // How deep scripts may run other scripts, so a script running itself stops
 
-// Enumeration for command categories
-#[derive(Debug, PartialEq, Eq, Clone, Copy)]
-enum CommandCategory {
-    System,      // System commands (help, version, exit)
-    FlightControl, // Flight control commands (takeoff, land, etc.)
-    Movement,    // Movement commands (forward, back, left, right, etc.)
-    Camera,      // Camera control commands (photo, video)
-    Media,       // Media management commands (download, delete, etc.)
-    Positioning  // Positioning commands (position, get_position)
+/// How executed commands print their output, from the command line options
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ExecOptions {
+    /// "state" and "info" print JSON, and echoes and progress go to stderr
+    /// so stdout only holds the JSON
+    pub json: bool,
+    /// Running a script or "--exec", so there is no one to answer questions
+    pub batch: bool,
+    /// How many scripts are running inside each other
+    pub script_depth: u32,
 }
 
+impl ExecOptions {
+    /// Print an echo or progress line, to stderr in JSON mode to keep stdout parseable
+    pub fn progress(&self, message: fmt::Arguments) {
+        if self.json {
+            eprintln!("{}", message);
+        } else {
+            println!("{}", message);
+        }
+    }
+}
+
+static EXPERT_MODE: AtomicBool = AtomicBool::new(false);
+
+/// Allow "raw", which sends any text to the drone unchecked
+pub fn set_expert_mode(enabled: bool) {
+    EXPERT_MODE.store(enabled, Ordering::Relaxed);
+}
+
+/// Whether a command should print JSON, either globally or via a "--json" argument
+fn wants_json(options: &ExecOptions, json: bool) -> bool {
+    options.json || json
+}
+
+
 // Structure for command information
+#[allow(dead_code)]
 struct CommandInfo {
     name: &'static str,
     category: CommandCategory,
@@ -63,14 +105,78 @@ fn get_commands_registry() -> Vec<CommandInfo> {
                      description: "Exit the application", delay: 0 },
         CommandInfo { name: "wait", category: CommandCategory::System, 
                      description: "Wait specified number of seconds between commands", delay: 0 },
+        CommandInfo { name: "hover", category: CommandCategory::FlightControl, 
+                     description: "Hover in place for a number of seconds without auto-landing", delay: 0 },
+        CommandInfo { name: "script", category: CommandCategory::System, 
+                     description: "Run commands from a script file", delay: 0 },
+        CommandInfo { name: "repeat", category: CommandCategory::System, 
+                     description: "Run commands a number of times", delay: 0 },
+        CommandInfo { name: "sequence", category: CommandCategory::System, 
+                     description: "Run comma separated commands, each followed by its own wait (@seconds)", delay: 0 },
+        CommandInfo { name: "reconnect", category: CommandCategory::System, 
+                     description: "Re-establish the connection after the link was lost", delay: 0 },
+        CommandInfo { name: "reboot", category: CommandCategory::System, 
+                     description: "Reboot the drone after asking, '--yes' skips the question", delay: 0 },
+        CommandInfo { name: "config", category: CommandCategory::System, 
+                     description: "Read the settings from ~/.tello.toml again (reload)", delay: 0 },
+        CommandInfo { name: "raw", category: CommandCategory::System, 
+                     description: "Send an SDK command as typed and print the response (needs --expert)", delay: 0 },
+        CommandInfo { name: "alias", category: CommandCategory::System, 
+                     description: "Define a name for a sequence of commands, or list aliases", delay: 0 },
+        CommandInfo { name: "unalias", category: CommandCategory::System, 
+                     description: "Remove an alias", delay: 0 },
+        CommandInfo { name: "history", category: CommandCategory::System, 
+                     description: "Save this session's commands to a file, or clear the history", delay: 0 },
+        CommandInfo { name: "record", category: CommandCategory::System, 
+                     description: "Record the commands that succeed with their timing, and save them as a script", delay: 0 },
+        CommandInfo { name: "prompt", category: CommandCategory::System, 
+                     description: "Set the prompt, with {battery}, {height} and {id} filled in (reset)", delay: 0 },
+        CommandInfo { name: "if", category: CommandCategory::System, 
+                     description: "Run a command only if battery, height or temp is <, > or == a value", delay: 0 },
         
         // Flight control commands
+        CommandInfo { name: "arm", category: CommandCategory::FlightControl, 
+                     description: "Allow takeoff and moves when arming is required", delay: 0 },
+        CommandInfo { name: "disarm", category: CommandCategory::FlightControl, 
+                     description: "Refuse takeoff and moves until the next arm", delay: 0 },
         CommandInfo { name: "takeoff", category: CommandCategory::FlightControl, 
                      description: "Take off (optional height in meters, default 1m, max 8m)", delay: 3000 },
+        CommandInfo { name: "throwfly", category: CommandCategory::FlightControl, 
+                     description: "Take off by throwing the drone into the air", delay: 3000 },
         CommandInfo { name: "land", category: CommandCategory::FlightControl, 
                      description: "Land the drone", delay: 3000 },
+        CommandInfo { name: "emergency", category: CommandCategory::FlightControl, 
+                     description: "Stop all motors immediately", delay: 0 },
+        CommandInfo { name: "kill", category: CommandCategory::FlightControl, 
+                     description: "Alias for emergency", delay: 0 },
         CommandInfo { name: "state", category: CommandCategory::FlightControl, 
                      description: "Get current drone state/telemetry", delay: 100 },
+        CommandInfo { name: "status", category: CommandCategory::FlightControl, 
+                     description: "Summarize connection, state packets, video, position and battery", delay: 0 },
+        CommandInfo { name: "battery", category: CommandCategory::FlightControl, 
+                     description: "Show battery level", delay: 100 },
+        CommandInfo { name: "motor", category: CommandCategory::FlightControl, 
+                     description: "Spin the motors at idle on the ground (on, off)", delay: 500 },
+        CommandInfo { name: "temp", category: CommandCategory::FlightControl, 
+                     description: "Show the drone temperature", delay: 0 },
+        CommandInfo { name: "height", category: CommandCategory::FlightControl, 
+                     description: "Show the height and ground distance the drone reports", delay: 0 },
+        CommandInfo { name: "velocity", category: CommandCategory::FlightControl, 
+                     description: "Show the speed along x, y and z (cm/s) the drone reports", delay: 0 },
+        CommandInfo { name: "telemetry", category: CommandCategory::FlightControl, 
+                     description: "Record every state packet to a CSV or JSON file ('log start', 'log stop')", delay: 0 },
+        CommandInfo { name: "flighttime", category: CommandCategory::FlightControl, 
+                     description: "Show how long the drone has been in the air", delay: 100 },
+        CommandInfo { name: "id", category: CommandCategory::System, 
+                     description: "Show the serial number and SDK version that identify the drone", delay: 0 },
+        CommandInfo { name: "wifi", category: CommandCategory::System, 
+                     description: "Show WiFi signal strength, or set the hotspot credentials", delay: 100 },
+        CommandInfo { name: "ap", category: CommandCategory::System, 
+                     description: "Join an existing WiFi network (station mode, Tello EDU)", delay: 0 },
+        CommandInfo { name: "ceiling", category: CommandCategory::FlightControl, 
+                     description: "Set the altitude ceiling in cm, 'off' to remove it, or show it", delay: 0 },
+        CommandInfo { name: "geofence", category: CommandCategory::FlightControl, 
+                     description: "Set a box in meters that moves must stay in, 'off' to remove it, or show it", delay: 0 },
         
         // Movement commands
         CommandInfo { name: "forward", category: CommandCategory::Movement, 
@@ -89,12 +195,48 @@ fn get_commands_registry() -> Vec<CommandInfo> {
                      description: "Rotate clockwise by specified degrees", delay: 1000 },
         CommandInfo { name: "rotate_ccw", category: CommandCategory::Movement, 
                      description: "Rotate counter-clockwise by specified degrees", delay: 1000 },
+        CommandInfo { name: "flip", category: CommandCategory::Movement, 
+                     description: "Flip in the specified direction (l, r, f, b)", delay: 2000 },
+        CommandInfo { name: "speed", category: CommandCategory::Movement, 
+                     description: "Set flight speed in cm/s (10-100), or show it without a value", delay: 100 },
+        CommandInfo { name: "clamp", category: CommandCategory::Movement, 
+                     description: "Clamp and split out-of-range distances instead of failing (on, off)", delay: 0 },
+        CommandInfo { name: "go", category: CommandCategory::Movement, 
+                     description: "Fly to x y z (cm, relative) at speed (cm/s)", delay: 2000 },
+        CommandInfo { name: "curve", category: CommandCategory::Movement, 
+                     description: "Fly a curve through two points at speed (cm/s)", delay: 3000 },
+        CommandInfo { name: "move", category: CommandCategory::Movement, 
+                     description: "Move forward, right and up (cm) at once with one go command", delay: 2000 },
+        CommandInfo { name: "square", category: CommandCategory::Movement, 
+                     description: "Fly a square with the given side (cm), turning right at each corner", delay: 1000 },
+        CommandInfo { name: "circle", category: CommandCategory::Movement, 
+                     description: "Fly a circle with the given radius (cm, 50-250) to the right", delay: 1000 },
+        CommandInfo { name: "polygon", category: CommandCategory::Movement, 
+                     description: "Fly a regular polygon with the given sides and side length (cm)", delay: 1000 },
+        CommandInfo { name: "eight", category: CommandCategory::Movement, 
+                     description: "Fly a figure eight of two circles with the given radius (cm, 50-250)", delay: 1000 },
+        CommandInfo { name: "go_to_pad", category: CommandCategory::Movement, 
+                     description: "Fly to x y z (cm) relative to a mission pad (1-8)", delay: 2000 },
+        CommandInfo { name: "jump", category: CommandCategory::Movement, 
+                     description: "Fly to x y z relative to one mission pad and turn to yaw over another", delay: 3000 },
+        CommandInfo { name: "rc", category: CommandCategory::Movement, 
+                     description: "Send RC control values (-100..100 each)", delay: 0 },
+        CommandInfo { name: "stop", category: CommandCategory::Movement, 
+                     description: "Stop and hover in place", delay: 0 },
         
         // Camera commands
         CommandInfo { name: "photo", category: CommandCategory::Camera, 
-                     description: "Take a photo", delay: 500 },
+                     description: "Take a photo, or save a frame of the video stream with 'save'", delay: 500 },
         CommandInfo { name: "video", category: CommandCategory::Camera, 
                      description: "Start or stop video recording", delay: 500 },
+        CommandInfo { name: "resolution", category: CommandCategory::Camera, 
+                     description: "Set the video resolution before starting video (high, low)", delay: 100 },
+        CommandInfo { name: "fps", category: CommandCategory::Camera, 
+                     description: "Set the video frame rate before starting video (high, middle, low)", delay: 100 },
+        CommandInfo { name: "camera", category: CommandCategory::Camera, 
+                     description: "Stream the forward or downward camera (forward, down)", delay: 100 },
+        CommandInfo { name: "bitrate", category: CommandCategory::Camera, 
+                     description: "Set the video bitrate (0 = auto, 1-5 Mbps), 'bitrate?' shows it", delay: 100 },
         
         // Media commands
         CommandInfo { name: "media", category: CommandCategory::Media, 
@@ -105,16 +247,33 @@ fn get_commands_registry() -> Vec<CommandInfo> {
                      description: "Set current drone position for camera positioning", delay: 100 },
         CommandInfo { name: "get_position", category: CommandCategory::Positioning, 
                      description: "Display current drone position", delay: 100 },
+        CommandInfo { name: "fly_to", category: CommandCategory::Positioning, 
+                     description: "Fly to a tracked position in meters, optionally at speed (cm/s)", delay: 3000 },
+        CommandInfo { name: "direction", category: CommandCategory::Positioning, 
+                     description: "Display the tracked heading, or set it in degrees", delay: 0 },
         CommandInfo { name: "camera_to_center", category: CommandCategory::Positioning, 
                      description: "Point camera towards the specified center point", delay: 1000 },
         CommandInfo { name: "camera_from_center", category: CommandCategory::Positioning, 
                      description: "Point camera away from the specified center point", delay: 1000 },
+        CommandInfo { name: "home", category: CommandCategory::Positioning, 
+                     description: "Fly back to the tracked origin and face the start heading", delay: 1000 },
+        CommandInfo { name: "land_at", category: CommandCategory::Positioning, 
+                     description: "Fly to a tracked x y in meters at the current height, then land", delay: 5000 },
+        CommandInfo { name: "path", category: CommandCategory::Positioning, 
+                     description: "Show the recorded flight path, save it as CSV, or fly a saved one", delay: 0 },
+        CommandInfo { name: "mpad", category: CommandCategory::Positioning, 
+                     description: "Mission pad detection (on, off, dir <0-2>), or show the detected pad", delay: 100 },
+        
+        // Maintenance commands
+        CommandInfo { name: "calibrate", category: CommandCategory::Maintenance, 
+                     description: "Calibrate the IMU, with the drone resting on a flat surface", delay: 500 },
     ]
 }
 
 /// Structure for command auto-completion
 pub struct CommandCompleter {
     commands: Vec<String>,
+    aliases: Vec<String>,
 }
 
 impl CommandCompleter {
@@ -124,7 +283,12 @@ impl CommandCompleter {
             .map(|info| info.name.to_string())
             .collect();
         
-        CommandCompleter { commands }
+        CommandCompleter { commands, aliases: Vec::new() }
+    }
+    
+    /// Offer these alias names alongside the built-in commands
+    fn set_aliases(&mut self, aliases: Vec<String>) {
+        self.aliases = aliases;
     }
 }
 
@@ -148,6 +312,7 @@ impl Completer for CommandCompleter {
         let matches: Vec<Pair> = self
             .commands
             .iter()
+            .chain(self.aliases.iter())
             .filter(|cmd| cmd.starts_with(current_part))
             .map(|cmd| Pair {
                 display: cmd.clone(),
@@ -202,6 +367,12 @@ pub struct CommandDelay {
     delays: HashMap<&'static str, u64>,
 }
 
+impl Default for CommandDelay {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl CommandDelay {
     pub fn new() -> Self {
         let registry = get_commands_registry();
@@ -218,10 +389,153 @@ impl CommandDelay {
     pub fn get_delay(&self, command: &str) -> u64 {
         *self.delays.get(command).unwrap_or(&500)
     }
+    
+    /// Delay in ms after a command, estimated from distance and speed for moves
+    ///
+    /// Moves by a distance wait for their estimated flight time if the speed
+    /// is known (`speed` is the last one set), "go" uses its own speed. Other
+    /// commands get the fixed delay of the registry.
+    pub fn delay_for(&self, parts: &[&str], speed: Option<i32>) -> u64 {
+        let estimate = match parts {
+            [name, distance] if matches!(*name, "forward" | "back" | "left" | "right" | "up" | "down") =>
+                speed.zip(distance.parse().ok()).map(|(speed, distance)| estimate_move_time(distance, speed)),
+            ["go", x, y, z, speed] => {
+                let values: Option<Vec<f64>> = [x, y, z, speed].iter().map(|arg| arg.parse().ok()).collect();
+                values.map(|v| estimate_move_time((v[0] * v[0] + v[1] * v[1] + v[2] * v[2]).sqrt().round() as i32, v[3] as i32))
+            },
+            _ => None,
+        };
+        
+        match estimate {
+            Some(time) => time.as_millis() as u64,
+            None => parts.first().map_or(0, |name| self.get_delay(name)),
+        }
+    }
+}
+
+/// User-defined command aliases, e.g. "side = forward 50; rotate_cw 90"
+#[derive(Debug, Default)]
+struct Aliases {
+    aliases: HashMap<String, String>,
+}
+
+impl Aliases {
+    /// Parse alias definitions, one "name = commands" per line
+    ///
+    /// Invalid definitions are skipped with a warning.
+    fn parse(contents: &str) -> Self {
+        let mut aliases = Aliases::default();
+        
+        for line in contents.lines().map(|line| line.trim()).filter(|line| !line.is_empty()) {
+            let result = match line.split_once('=') {
+                Some((name, commands)) => aliases.define(name.trim(), commands.trim()),
+                None => Err(usage_error(format!("Missing '=' in '{}'", line))),
+            };
+            if let Err(e) = result {
+                eprintln!("Warning: Skipping alias: {}", e);
+            }
+        }
+        
+        aliases
+    }
+    
+    /// Load aliases saved by a previous session
+    fn load(path: &Path) -> Self {
+        match fs::read_to_string(path) {
+            Ok(contents) => Aliases::parse(&contents),
+            Err(_) => Aliases::default(),
+        }
+    }
+    
+    /// Save all aliases so the next session can load them
+    fn save(&self, path: &Path) -> io::Result<()> {
+        let contents: String = self.names()
+            .iter()
+            .map(|name| format!("{} = {}\n", name, self.aliases[name]))
+            .collect();
+        fs::write(path, contents)
+    }
+    
+    /// Define or replace an alias
+    ///
+    /// Built-in commands can't be shadowed, and a definition that would
+    /// make an alias expand into itself is rejected.
+    fn define(&mut self, name: &str, commands: &str) -> io::Result<()> {
+        if name.is_empty() || name.contains(|c: char| c.is_whitespace() || c == ';' || c == '=') {
+            return Err(usage_error(format!("Invalid alias name: '{}'", name)));
+        }
+        if get_commands_registry().iter().any(|info| info.name == name) {
+            return Err(usage_error(format!("'{}' is a built-in command and can't be an alias", name)));
+        }
+        if parse_script(commands).is_empty() {
+            return Err(usage_error(format!("Alias '{}' has no commands", name)));
+        }
+        
+        let previous = self.aliases.insert(name.to_string(), commands.to_string());
+        if let Err(e) = self.expand(name) {
+            // Put back whatever was there before
+            match previous {
+                Some(previous) => self.aliases.insert(name.to_string(), previous),
+                None => self.aliases.remove(name),
+            };
+            return Err(e);
+        }
+        
+        Ok(())
+    }
+    
+    /// Remove an alias, returning whether it existed
+    fn remove(&mut self, name: &str) -> bool {
+        self.aliases.remove(name).is_some()
+    }
+    
+    /// Alias names in alphabetical order
+    fn names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.aliases.keys().cloned().collect();
+        names.sort();
+        names
+    }
+    
+    /// Expand a command into the commands to run
+    ///
+    /// Aliases used inside an alias are expanded too. Anything that is not
+    /// an alias is returned unchanged.
+    fn expand(&self, command: &str) -> io::Result<Vec<String>> {
+        let mut commands = Vec::new();
+        self.expand_into(command, &mut Vec::new(), &mut commands)?;
+        Ok(commands)
+    }
+    
+    fn expand_into(&self, command: &str, active: &mut Vec<String>, commands: &mut Vec<String>) -> io::Result<()> {
+        let mut parts = command.split_whitespace();
+        let name = parts.next().unwrap_or("");
+        
+        let Some(body) = self.aliases.get(name) else {
+            commands.push(command.to_string());
+            return Ok(());
+        };
+        
+        if parts.next().is_some() {
+            return Err(usage_error(format!("Alias '{}' takes no arguments", name)));
+        }
+        
+        // An alias that is already being expanded means a cycle
+        active.push(name.to_string());
+        if active[..active.len() - 1].contains(&active[active.len() - 1]) {
+            return Err(usage_error(format!("Recursive alias: {}", active.join(" -> "))));
+        }
+        
+        for sub_command in parse_script(body) {
+            self.expand_into(&sub_command, active, commands)?;
+        }
+        
+        active.pop();
+        Ok(())
+    }
 }
 
 /// Run the interactive command-line interface with enhanced editing capabilities
-pub fn run_command_line(mut drone: Tello) -> io::Result<()> {
+pub fn run_command_line(mut drone: Tello, abort_key: Option<KeyEvent>, options: &ExecOptions) -> io::Result<()> {
     // Create command delay settings
     let command_delays = CommandDelay::new();
     
@@ -229,6 +543,8 @@ pub fn run_command_line(mut drone: Tello) -> io::Result<()> {
     let config = Config::builder()
         .history_ignore_space(true)
         .completion_type(CompletionType::List)
+        // A lone Esc would otherwise wait for the rest of an escape sequence
+        .keyseq_timeout(ESC_TIMEOUT_MS)
         .build();
     
     // Create editor with history and command completion
@@ -236,15 +552,18 @@ pub fn run_command_line(mut drone: Tello) -> io::Result<()> {
     let mut rl = match Editor::with_config(config) {
         Ok(editor) => editor,
         Err(err) => {
-            return Err(io::Error::new(io::ErrorKind::Other, 
+            return Err(io::Error::other(
                 format!("Failed to initialize command line editor: {}", err)))
         }
     };
     
     rl.set_helper(Some(helper));
     
-    // Tab completion is enabled by default with the helper
-    // No need to explicitly bind keys
+    // The abort key interrupts the prompt like CTRL-C, the flag tells the two apart
+    let abort_requested = Arc::new(AtomicBool::new(false));
+    if let Some(key) = abort_key {
+        rl.bind_sequence(key, EventHandler::Conditional(Box::new(AbortKeyHandler(Arc::clone(&abort_requested)))));
+    }
     
     // Try to load history from previous sessions
     let history_path = get_history_file_path();
@@ -252,22 +571,107 @@ pub fn run_command_line(mut drone: Tello) -> io::Result<()> {
         println!("No previous history found.");
     }
     
+    // Aliases are kept next to the history
+    let aliases_path = get_aliases_file_path();
+    let mut aliases = Aliases::load(&aliases_path);
+    if let Some(helper) = rl.helper_mut() {
+        helper.completer.set_aliases(aliases.names());
+    }
+    
+    // Lines entered in this session, for "history save"
+    let mut session_history: Vec<String> = Vec::new();
+    
+    // Set by "record start", collects the commands that succeed
+    let mut recorder: Option<Recorder> = None;
+    
+    // Rebuilt before every line, so it shows the latest telemetry
+    let mut prompt = Prompt::default();
+    
+    // Set when landing on CTRL-C failed, so the next CTRL-C quits without landing
+    let mut landing_failed = false;
+    
     println!("Tello Control - Interactive Mode");
     println!("Type commands to control the drone. Separate multiple commands with semicolons (;)");
     println!("Available commands:");
     print_available_commands();
     println!("Use arrow keys to navigate, Tab for completion, Ctrl+R to search history");
+    if let Some(key) = abort_key {
+        println!("Press {} at the prompt to land immediately", key_name(key));
+    }
     
     // Main command loop
     loop {
         // Read line with editing capabilities
-        let readline = rl.readline("> ");
+        let readline = rl.readline(&prompt.render(drone.get_telemetry().as_ref()));
         
         match readline {
             Ok(line) => {
+                landing_failed = false;
+                
                 // Add non-empty entries to history
-                if !line.trim().is_empty() {
-                    rl.add_history_entry(&line);
+                if !line.trim().is_empty() && rl.add_history_entry(&line) {
+                    // Keep "history" itself out so a saved session replays as a script
+                    if line.split_whitespace().next() != Some("history") {
+                        session_history.push(line.clone());
+                    }
+                }
+                
+                // Alias and repeat commands take the whole line, their commands contain semicolons
+                match line.split_whitespace().next() {
+                    Some("alias" | "unalias") => {
+                        if let Err(e) = execute_alias_command(&mut aliases, line.trim(), &aliases_path) {
+                            eprintln!("Error executing command: {}", e);
+                        }
+                        if let Some(helper) = rl.helper_mut() {
+                            helper.completer.set_aliases(aliases.names());
+                        }
+                        continue;
+                    },
+                    Some("history") => {
+                        let args: Vec<&str> = line.split_whitespace().skip(1).collect();
+                        let result = match args.as_slice() {
+                            ["save", path] => save_session_history(Path::new(path), &session_history)
+                                .map(|_| println!("Saved {} commands to {}", session_history.len(), path)),
+                            ["clear"] => {
+                                rl.clear_history();
+                                session_history.clear();
+                                rl.save_history(&history_path)
+                                    .map(|_| println!("Command history cleared"))
+                                    .map_err(|e| command_failed(format!("Failed to clear {}: {}", history_path.display(), e)))
+                            },
+                            _ => Err(usage_error(String::from("Usage: history save <file> | history clear"))),
+                        };
+                        if let Err(e) = result {
+                            eprintln!("Error executing command: {}", e);
+                        }
+                        continue;
+                    },
+                    Some("record") => {
+                        let args: Vec<&str> = line.split_whitespace().skip(1).collect();
+                        if let Err(e) = execute_record_command(&mut recorder, &args) {
+                            eprintln!("Error executing command: {}", e);
+                        }
+                        continue;
+                    },
+                    Some("prompt") => {
+                        // Spaces at the end of the template are part of the prompt
+                        let args = line.trim_start()["prompt".len()..].trim_start();
+                        if let Err(e) = execute_prompt_command(&mut prompt, &mut drone, args) {
+                            eprintln!("Error executing command: {}", e);
+                        }
+                        continue;
+                    },
+                    Some("repeat") => {
+                        let args = line.trim()["repeat".len()..].trim();
+                        if let Err(e) = run_repeat(&mut drone, args, &aliases, &command_delays, options) {
+                            if is_exit_request(&e) {
+                                break;
+                            }
+                            eprintln!("Error executing command: {}", e);
+                        }
+                        continue;
+                    },
+                    _ => {}
                 }
                 
                 // Split input by semicolons to handle multiple commands
@@ -278,51 +682,58 @@ pub fn run_command_line(mut drone: Tello) -> io::Result<()> {
                         continue;
                     }
                     
-                    let parts: Vec<&str> = cmd.split_whitespace().collect();
-                    
-                    if parts.is_empty() {
-                        continue;
-                    }
-                    
-                    // Check if it's a wait command
-                    if parts[0] == "wait" && parts.len() > 1 {
-                        if let Ok(seconds) = parts[1].parse::<f64>() {
-                            let millis = (seconds * 1000.0) as u64;
-                            println!("Waiting for {} seconds...", seconds);
-                            thread::sleep(Duration::from_millis(millis));
-                            println!("Wait completed");
-                            continue;
-                        } else {
-                            println!("Invalid wait time: {}. Please specify a number of seconds.", parts[1]);
+                    // Replace an alias by the commands it stands for
+                    let expanded = match aliases.expand(cmd) {
+                        Ok(expanded) => expanded,
+                        Err(e) => {
+                            eprintln!("Error executing command: {}", e);
                             continue;
                         }
-                    }
+                    };
                     
-                    // Execute the command
-                    if let Err(e) = execute_command(&mut drone, &parts) {
-                        if let Some(message) = e.get_ref() {
-                            if message.to_string() == "Exit requested" {
+                    for cmd in &expanded {
+                        let parts: Vec<&str> = cmd.split_whitespace().collect();
+                        
+                        if parts.is_empty() {
+                            continue;
+                        }
+                        
+                        // Execute the command followed by its command-specific delay
+                        if let Err(e) = execute_recorded(&mut drone, &parts, &command_delays, &mut recorder, options) {
+                            if is_exit_request(&e) {
                                 // Save command history before exiting
                                 if let Err(history_err) = rl.save_history(&history_path) {
                                     eprintln!("Warning: Failed to save command history: {}", history_err);
                                 }
-                                return Err(e);
+                                return Ok(());
                             }
+                            eprintln!("Error executing command: {}", e);
                         }
-                        eprintln!("Error executing command: {}", e);
-                    }
-                    
-                    // Add a delay between commands based on the command type
-                    let delay = command_delays.get_delay(parts[0]);
-                    
-                    if delay > 0 {
-                        println!("Waiting for command completion ({} ms)...", delay);
-                        thread::sleep(Duration::from_millis(delay));
                     }
                 }
             },
+            Err(ReadlineError::Interrupted) if abort_requested.swap(false, Ordering::Relaxed) => {
+                // Sent even if the drone doesn't seem to fly, the tracked state may be wrong
+                println!("Abort key pressed, landing...");
+                match drone.land() {
+                    Ok(_) => println!("Landed"),
+                    Err(e) => eprintln!("Landing failed: {}", e),
+                }
+            },
             Err(ReadlineError::Interrupted) => {
-                println!("CTRL-C pressed, exiting...");
+                if drone.is_flying() && !landing_failed {
+                    // The prompt is not reading while landing, so a second CTRL-C
+                    // ends the program at once without waiting for the drone
+                    println!("CTRL-C pressed while flying, landing before exit (CTRL-C again quits without landing)...");
+                    if let Err(e) = drone.land() {
+                        eprintln!("Landing failed: {}. Press CTRL-C again to quit without landing", e);
+                        landing_failed = true;
+                        continue;
+                    }
+                    println!("Landed, exiting...");
+                } else {
+                    println!("CTRL-C pressed, exiting...");
+                }
                 break;
             },
             Err(ReadlineError::Eof) => {
@@ -344,525 +755,2001 @@ pub fn run_command_line(mut drone: Tello) -> io::Result<()> {
     Ok(())
 }
 
-/// Get the path to the history file
-fn get_history_file_path() -> PathBuf {
-    let mut home_dir = dirs::home_dir().unwrap_or_else(|| PathBuf::from("."));
-    home_dir.push(".tello_history");
-    home_dir
+/// Interrupts the prompt and flags a landing when the abort key is pressed
+struct AbortKeyHandler(Arc<AtomicBool>);
+
+impl ConditionalEventHandler for AbortKeyHandler {
+    fn handle(&self, _: &Event, _: RepeatCount, _: bool, _: &EventContext) -> Option<Cmd> {
+        self.0.store(true, Ordering::Relaxed);
+        Some(Cmd::Interrupt)
+    }
 }
 
-/// Print available commands
-fn print_available_commands() {
-    println!("\n=== SYSTEM COMMANDS ===");
-    println!("  help           - Show available commands");
-    println!("  version        - Show application version");
-    println!("  info           - Show detailed information about application and connected drone");
-    println!("  exit           - Exit the application");
-    println!("  wait <seconds> - Wait specified number of seconds between commands");
+/// Parse the key given with --abort-key: "esc", "f1"-"f12" or "ctrl-<letter>"
+///
+/// "none" turns the abort key off.
+pub fn parse_abort_key(name: &str) -> io::Result<Option<KeyEvent>> {
+    let name = name.to_lowercase();
+    let function_key = name.strip_prefix('f')
+        .and_then(|number| number.parse::<u8>().ok())
+        .filter(|number| (1..=12).contains(number));
+    let ctrl_letter = name.strip_prefix("ctrl-")
+        .and_then(|letter| match letter.as_bytes() {
+            [c] if c.is_ascii_lowercase() => Some(c.to_ascii_uppercase() as char),
+            _ => None,
+        });
     
-    println!("\n=== FLIGHT CONTROL COMMANDS ===");
-    println!("  takeoff [height] - Take off (optional height in meters, default 1m, max 8m)");
-    println!("  land           - Land the drone");
-    println!("  state          - Get current drone state/telemetry");
+    match (name.as_str(), function_key, ctrl_letter) {
+        ("none", _, _) => Ok(None),
+        ("esc", _, _) => Ok(Some(KeyEvent(KeyCode::Esc, Modifiers::NONE))),
+        (_, Some(number), _) => Ok(Some(KeyEvent(KeyCode::F(number), Modifiers::NONE))),
+        // CTRL-C and CTRL-D already quit the prompt
+        (_, _, Some('C' | 'D')) => Err(usage_error(format!("{} can't be the abort key, it already quits", name))),
+        (_, _, Some(letter)) => Ok(Some(KeyEvent::ctrl(letter))),
+        _ => Err(usage_error(format!("Invalid abort key: {}. Use esc, f1-f12, ctrl-<letter> or none", name))),
+    }
+}
+
+/// The name of an abort key as shown in the prompt hint
+fn key_name(key: KeyEvent) -> String {
+    match key {
+        KeyEvent(KeyCode::Esc, _) => String::from("Esc"),
+        KeyEvent(KeyCode::F(number), _) => format!("F{}", number),
+        KeyEvent(KeyCode::Char(c), _) => format!("Ctrl-{}", c),
+        other => format!("{:?}", other),
+    }
+}
+
+/// Handle "alias" and "unalias", saving the aliases after a change
+///
+/// "alias" lists all aliases, "alias <name>" shows one and
+/// "alias <name> = <commands>" defines one.
+fn execute_alias_command(aliases: &mut Aliases, line: &str, path: &Path) -> io::Result<()> {
+    let (command, rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+    let rest = rest.trim();
     
-    println!("\n=== MOVEMENT COMMANDS ===");
-    println!("  forward <distance> - Move forward by specified distance in cm (1-500)");
-    println!("  back <distance>    - Move backward by specified distance in cm (1-500)");
-    println!("  left <distance>    - Move left by specified distance in cm (1-500)");
-    println!("  right <distance>   - Move right by specified distance in cm (1-500)");
-    println!("  up <distance>      - Move up by specified distance in cm (1-500)");
-    println!("  down <distance>    - Move down by specified distance in cm (1-500)");
-    println!("  rotate_cw <degrees> - Rotate clockwise by specified degrees");
-    println!("  rotate_ccw <degrees> - Rotate counter-clockwise by specified degrees");
+    if command == "unalias" {
+        if rest.is_empty() {
+            return Err(usage_error(String::from("Please specify an alias: unalias <name>")));
+        }
+        if !aliases.remove(rest) {
+            return Err(usage_error(format!("No alias named '{}'", rest)));
+        }
+        println!("Alias '{}' removed", rest);
+    } else if let Some((name, commands)) = rest.split_once('=') {
+        let name = name.trim();
+        aliases.define(name, commands.trim())?;
+        println!("Alias '{}' defined", name);
+    } else if rest.is_empty() {
+        if aliases.names().is_empty() {
+            println!("No aliases defined. Use: alias <name> = <commands>");
+        }
+        for name in aliases.names() {
+            println!("  {} = {}", name, aliases.aliases[&name]);
+        }
+        return Ok(());
+    } else {
+        match aliases.aliases.get(rest) {
+            Some(commands) => println!("  {} = {}", rest, commands),
+            None => return Err(usage_error(format!("No alias named '{}'", rest))),
+        }
+        return Ok(());
+    }
     
-    println!("\n=== CAMERA COMMANDS ===");
-    println!("  photo          - Take a photo");
-    println!("  video start    - Start recording video");
-    println!("  video stop     - Stop recording video");
+    aliases.save(path)
+        .map_err(|e| command_failed(format!("Failed to save aliases to {}: {}", path.display(), e)))
+}
+
+/// Write the commands entered in this session to a file, one per line
+fn save_session_history(path: &Path, commands: &[String]) -> io::Result<()> {
+    let mut contents = String::new();
+    for command in commands {
+        contents.push_str(command.trim());
+        contents.push('\n');
+    }
     
-    println!("\n=== MEDIA MANAGEMENT ===");
-    println!("  media list     - List media files on the drone");
-    println!("  media download <filename> - Download media file from drone");
-    println!("  media direct <filename>   - Download media using direct TCP connection");
-    println!("  media delete <filename>   - Delete media file from drone");
-    println!("  media deleteall - Delete all media files from drone");
-    println!("  media path <path> - Set download path for media files");
+    fs::write(path, contents)
+        .map_err(|e| command_failed(format!("Failed to save history to {}: {}", path.display(), e)))
+}
+
+/// Commands that succeeded since "record start", with when they ran
+struct Recorder {
+    path: PathBuf,
+    commands: Vec<(String, Instant, Instant)>, // Command, started, finished
+}
+
+impl Recorder {
+    fn new(path: &str) -> Self {
+        Recorder { path: PathBuf::from(path), commands: Vec::new() }
+    }
     
-    println!("\n=== POSITIONING COMMANDS ===");
-    println!("  position <x> <y> <z> - Set current drone position for camera positioning");
-    println!("  get_position         - Display current drone position");
-    println!("  camera_to_center <x> <y> - Point camera towards the specified center point");
-    println!("  camera_from_center <x> <y> - Point camera away from the specified center point");
-    println!("");
+    fn push(&mut self, command: String, started: Instant, finished: Instant) {
+        self.commands.push((command, started, finished));
+    }
+    
+    /// One command per line, followed by a wait for the pause before the next
+    ///
+    /// Pauses under 0.1 s are left out, since replaying keeps the usual
+    /// delays after each command anyway.
+    fn to_script(&self) -> String {
+        let mut script = String::new();
+        for (i, (command, _, finished)) in self.commands.iter().enumerate() {
+            script.push_str(command);
+            if let Some((_, next_started, _)) = self.commands.get(i + 1) {
+                let pause = next_started.saturating_duration_since(*finished);
+                if pause >= Duration::from_millis(100) {
+                    script.push_str(&format!("; wait {:.1}", pause.as_secs_f64()));
+                }
+            }
+            script.push('\n');
+        }
+        script
+    }
 }
 
-/// Execute a single command
-fn execute_command(drone: &mut Tello, parts: &[&str]) -> io::Result<()> {
-    match parts[0] {
-        // === SYSTEM COMMANDS ===
-        "help" => {
-            println!("Available commands:");
-            print_available_commands();
+/// Execute a command with its delay, recording it if it succeeds
+fn execute_recorded(drone: &mut Tello, parts: &[&str], command_delays: &CommandDelay,
+                    recorder: &mut Option<Recorder>, options: &ExecOptions) -> io::Result<()> {
+    let started = Instant::now();
+    execute_with_delay(drone, parts, command_delays, options)?;
+    
+    if let Some(recorder) = recorder {
+        recorder.push(parts.join(" "), started, Instant::now());
+    }
+    Ok(())
+}
+
+/// Handle "record start <file>" and "record stop"
+fn execute_record_command(recorder: &mut Option<Recorder>, args: &[&str]) -> io::Result<()> {
+    match args {
+        ["start", path] => {
+            if let Some(current) = recorder {
+                return Err(usage_error(format!("Already recording to {}, use 'record stop' first", current.path.display())));
+            }
+            *recorder = Some(Recorder::new(path));
+            println!("Recording to {}, 'record stop' saves it", path);
         },
-        "version" => {
-            println!("DJI Tello Controller Library");
-            println!("Version: {}", VERSION);
-            println!("Build date: {}", BUILD_DATE);
-            println!("Copyright (c) 2025 aleskin");
+        ["stop"] => {
+            let current = recorder.take()
+                .ok_or_else(|| usage_error(String::from("Not recording, use 'record start <file>'")))?;
+            fs::write(&current.path, current.to_script())
+                .map_err(|e| command_failed(format!("Failed to save the recording to {}: {}", current.path.display(), e)))?;
+            println!("Saved {} commands to {} (replay with 'script {}')",
+                current.commands.len(), current.path.display(), current.path.display());
         },
-        "info" => {
-            // Application information
-            println!("=== APPLICATION INFORMATION ===");
-            println!("DJI Tello Controller Library");
-            println!("Version: {}", VERSION);
-            println!("Build date: {}", BUILD_DATE);
-            println!("Copyright (c) 2025 aleskin");
-            println!("");
-            
-            // Network information
-            println!("=== NETWORK CONFIGURATION ===");
-            println!("Command port: 8889 (UDP)");
-            println!("Response port: 8890 (UDP)");
-            println!("State port: 8891 (UDP)");
-            println!("Media port: 8888 (TCP/Direct)");
-            println!("");
-            
-            // Drone information
-            println!("=== DRONE INFORMATION ===");
-            
-            // Try to get drone SDK version
-            match drone.send_command_with_response("sdk?") {
-                Ok(sdk_version) => println!("SDK version: {}", sdk_version),
-                Err(_) => println!("SDK version: Unable to retrieve"),
-            }
-            
-            // Try to get drone serial number
-            match drone.send_command_with_response("sn?") {
-                Ok(serial) => println!("Serial number: {}", serial),
-                Err(_) => println!("Serial number: Unable to retrieve"),
-            }
-            
-            // Try to get drone hardware version
-            match drone.send_command_with_response("hardware?") {
-                Ok(hardware) => println!("Hardware version: {}", hardware),
-                Err(_) => println!("Hardware version: Unable to retrieve"),
-            }
+        _ => return Err(usage_error(String::from("Usage: record start <file> | record stop"))),
+    }
+    Ok(())
+}
 
-            // Try to get drone firmware version
-            match drone.send_command_with_response("version?") {
-                Ok(firmware) => println!("Firmware version: {}", firmware),
-                Err(_) => println!("Firmware version: Unable to retrieve"),
-            }
-            
-            // Try to get battery level
-            match drone.send_command_with_response("battery?") {
-                Ok(battery) => println!("Battery level: {}%", battery),
-                Err(_) => println!("Battery level: Unable to retrieve"),
-            }
-            
-            // Try to get WiFi signal-to-noise ratio
-            match drone.send_command_with_response("wifi?") {
-                Ok(wifi) => println!("WiFi SNR: {}", wifi),
-                Err(_) => println!("WiFi SNR: Unable to retrieve"),
-            }
-            
-            // Display full state information if available
-            if let Some(state) = drone.get_state() {
-                println!("\n=== DETAILED STATE INFORMATION ===");
-                
-                // Parse and display the state in a more readable format
-                let state_pairs: Vec<&str> = state.split(';').collect();
-                
-                // Sort and display by categories
-                let mut battery_info = Vec::new();
-                let mut flight_info = Vec::new();
-                let mut position_info = Vec::new();
-                let mut other_info = Vec::new();
+/// Run `then` if the condition holds for the given state packet
+///
+/// Without a state packet, or one lacking the field, nothing is run and an
+/// error is returned, so a script doesn't go on as if the check passed.
+fn run_conditional(drone: &mut Tello, condition: &Condition, then: &str, telemetry: Option<&TelemetryState>,
+    options: &ExecOptions) -> io::Result<()> {
+    let state = telemetry
+        .ok_or_else(|| command_failed(format!("No state received from the drone, can't check '{}'", condition)))?;
+    let holds = condition.holds(state)
+        .ok_or_else(|| command_failed(format!("The state packet has no {} value, can't check '{}'", condition.field, condition)))?;
+    
+    if !holds {
+        options.progress(format_args!("'{}' is false, skipping '{}'", condition, then));
+        return Ok(());
+    }
+    options.progress(format_args!("'{}' holds, running '{}'", condition, then));
+    let parts: Vec<&str> = then.split_whitespace().collect();
+    execute_with_delay(drone, &parts, &CommandDelay::new(), options)
+}
+
+/// The placeholders a prompt template can contain
+const PROMPT_PLACEHOLDERS: [&str; 3] = ["{battery}", "{height}", "{id}"];
+
+/// The interactive prompt, a template filled in from the latest telemetry
+struct Prompt {
+    template: String,
+    id: Option<String>, // Serial number for {id}, looked up when the template is set
+}
+
+impl Default for Prompt {
+    fn default() -> Self {
+        Prompt { template: String::from(DEFAULT_PROMPT), id: None }
+    }
+}
+
+impl Prompt {
+    /// Fill in the placeholders, "?" for values that aren't known
+    fn render(&self, telemetry: Option<&TelemetryState>) -> String {
+        let known = |value: Option<String>| value.unwrap_or_else(|| String::from("?"));
+        self.template
+            .replace("{battery}", &known(telemetry.and_then(|state| state.bat).map(|bat| bat.to_string())))
+            .replace("{height}", &known(telemetry.and_then(|state| state.h).map(|h| h.to_string())))
+            .replace("{id}", &known(self.id.clone()))
+    }
+}
+
+/// Handle "prompt", "prompt reset" and "prompt <template>"
+fn execute_prompt_command(prompt: &mut Prompt, drone: &mut Tello, args: &str) -> io::Result<()> {
+    match args.trim() {
+        "" => {
+            println!("Prompt template: '{}'", prompt.template);
+            return Ok(());
+        },
+        "reset" => {
+            *prompt = Prompt::default();
+            return Ok(());
+        },
+        _ => {}
+    }
+    
+    // Anything in braces must be a known placeholder, so a typo doesn't end up in the prompt
+    let mut rest = args.to_string();
+    for placeholder in PROMPT_PLACEHOLDERS {
+        rest = rest.replace(placeholder, "");
+    }
+    if let Some(start) = rest.find('{') {
+        if let Some(length) = rest[start..].find('}') {
+            return Err(usage_error(format!("Unknown placeholder {}, use {}",
+                &rest[start..=start + length], PROMPT_PLACEHOLDERS.join(", "))));
+        }
+    }
+    
+    // The serial number doesn't change, so it is looked up once
+    let id = if args.contains("{id}") {
+        match drone.identity() {
+            Ok(identity) => Some(identity.serial),
+            Err(e) => {
+                eprintln!("Warning: Failed to get the drone id, showing '?': {}", e);
+                None
+            },
+        }
+    } else {
+        None
+    };
+    
+    // Keep the typed text apart from the prompt
+    let mut template = args.to_string();
+    if !template.ends_with(char::is_whitespace) {
+        template.push(' ');
+    }
+    *prompt = Prompt { template, id };
+    Ok(())
+}
+
+/// Parse "<n> <commands>" into the repeat count and the semicolon separated commands
+fn parse_repeat(args: &str) -> io::Result<(u32, Vec<String>)> {
+    let (count, commands) = args.trim().split_once(char::is_whitespace).unwrap_or((args.trim(), ""));
+    
+    let count = match count.parse::<i64>() {
+        Ok(count) if count < 0 => {
+            return Err(usage_error(format!("Invalid repeat count: {}. It must not be negative.", count)));
+        },
+        Ok(count) => u32::try_from(count)
+            .map_err(|_| usage_error(format!("Repeat count {} is too large", count)))?,
+        Err(_) => {
+            return Err(usage_error(String::from("Usage: repeat <n> <commands>")));
+        },
+    };
+    
+    let commands = parse_script(commands);
+    if commands.is_empty() && count > 0 {
+        return Err(usage_error(String::from("Please specify the commands to repeat: repeat <n> <commands>")));
+    }
+    
+    Ok((count, commands))
+}
+
+/// Run "repeat <n> <commands>": the commands n times with their usual delays
+///
+/// Stops at the first failing command. An "exit" is passed on immediately.
+fn run_repeat(drone: &mut Tello, args: &str, aliases: &Aliases, command_delays: &CommandDelay,
+    options: &ExecOptions) -> io::Result<()> {
+    let (count, commands) = parse_repeat(args)?;
+    
+    for iteration in 1..=count {
+        options.progress(format_args!("Repeat {} of {}", iteration, count));
+        
+        for cmd in &commands {
+            for cmd in aliases.expand(cmd)? {
+                let parts: Vec<&str> = cmd.split_whitespace().collect();
                 
-                for pair in state_pairs {
-                    if pair.is_empty() {
-                        continue;
-                    }
-                    
-                    if pair.contains("bat") || pair.contains("temp") {
-                        battery_info.push(pair);
-                    } else if pair.contains("pitch") || pair.contains("roll") || pair.contains("yaw") || 
-                              pair.contains("vel") || pair.contains("spd") {
-                        flight_info.push(pair);
-                    } else if pair.contains("x") || pair.contains("y") || pair.contains("z") || 
-                              pair.contains("h") || pair.contains("tof") {
-                        position_info.push(pair);
-                    } else {
-                        other_info.push(pair);
+                if let Err(e) = execute_with_delay(drone, &parts, command_delays, options) {
+                    if is_exit_request(&e) {
+                        return Err(e);
                     }
+                    return Err(command_failed(format!("Repeat stopped at '{}' ({} of {}): {}",
+                        cmd, iteration, count, e)));
                 }
-                
-                if !battery_info.is_empty() {
-                    println!("Battery & Temperature:");
-                    for info in battery_info {
-                        println!("  {}", info);
-                    }
-                }
-                
-                if !flight_info.is_empty() {
-                    println!("Flight Parameters:");
-                    for info in flight_info {
-                        println!("  {}", info);
-                    }
-                }
-                
-                if !position_info.is_empty() {
-                    println!("Position Information:");
-                    for info in position_info {
-                        println!("  {}", info);
-                    }
-                }
-                
-                if !other_info.is_empty() {
-                    println!("Other Information:");
-                    for info in other_info {
-                        println!("  {}", info);
-                    }
-                }
-            } else {
-                println!("\nReal-time state information unavailable. Make sure the drone is connected.");
             }
+        }
+    }
+    
+    Ok(())
+}
+
+/// Parse "forward 50 @2, rotate_cw 90 @1.5, land @0" into commands and waits
+///
+/// A step without "@" keeps the command's usual delay.
+fn parse_sequence(args: &str) -> io::Result<Vec<(String, Option<Duration>)>> {
+    let mut steps = Vec::new();
+    
+    for step in args.split(',') {
+        let (command, wait) = match step.rsplit_once('@') {
+            Some((command, seconds)) => {
+                let wait = seconds.trim().parse::<f64>().ok()
+                    .and_then(|seconds| Duration::try_from_secs_f64(seconds).ok())
+                    .ok_or_else(|| usage_error(format!("Invalid wait in sequence step '{}'", step.trim())))?;
+                (command.trim(), Some(wait))
+            },
+            None => (step.trim(), None),
+        };
+        
+        if command.is_empty() {
+            return Err(usage_error(format!("Missing command in sequence step '{}'", step.trim())));
+        }
+        steps.push((command.to_string(), wait));
+    }
+    
+    Ok(steps)
+}
+
+/// Run "sequence <steps>": each command followed by its own wait
+///
+/// All steps are parsed before anything runs. Stops at the first failing
+/// command. An "exit" is passed on immediately.
+fn run_sequence(drone: &mut Tello, args: &str, command_delays: &CommandDelay, options: &ExecOptions) -> io::Result<()> {
+    if args.trim().is_empty() {
+        return Err(usage_error(String::from("Usage: sequence <command> @<seconds>, <command> @<seconds>, ...")));
+    }
+    let steps = parse_sequence(args)?;
+    
+    for (cmd, wait) in &steps {
+        options.progress(format_args!("> {}", cmd));
+        let parts: Vec<&str> = cmd.split_whitespace().collect();
+        
+        if let Err(e) = execute_command(drone, &parts, options) {
+            if is_exit_request(&e) {
+                return Err(e);
+            }
+            return Err(command_failed(format!("Sequence stopped at '{}': {}", cmd, e)));
+        }
+        
+        let wait = wait.unwrap_or_else(|| Duration::from_millis(command_delays.get_delay(parts[0])));
+        if !wait.is_zero() {
+            thread::sleep(wait);
+        }
+    }
+    
+    Ok(())
+}
+
+/// Execute a single command and wait for its command-specific delay afterwards
+fn execute_with_delay(drone: &mut Tello, parts: &[&str], command_delays: &CommandDelay,
+    options: &ExecOptions) -> io::Result<()> {
+    let result = execute_command(drone, parts, options);
+    
+    if let Err(e) = &result {
+        if is_exit_request(e) {
+            return result;
+        }
+    }
+    
+    // Wait for moves to finish at the set speed, other commands by their type
+    let delay = command_delays.delay_for(parts, drone.last_set_speed());
+    
+    if delay > 0 {
+        options.progress(format_args!("Waiting for command completion ({} ms)...", delay));
+        thread::sleep(Duration::from_millis(delay));
+    }
+    
+    result
+}
+
+/// Check whether an error returned by execute_command is a request to exit
+fn is_exit_request(e: &io::Error) -> bool {
+    e.get_ref().map(|message| message.to_string() == "Exit requested").unwrap_or(false)
+}
+
+/// Split a flight script into individual commands
+///
+/// Commands are separated by newlines or semicolons.
+/// Lines starting with '#' are comments and skipped.
+fn parse_script(contents: &str) -> Vec<String> {
+    contents
+        .lines()
+        .map(|line| line.trim())
+        .filter(|line| !line.starts_with('#'))
+        .flat_map(|line| line.split(';'))
+        .map(|cmd| cmd.trim())
+        .filter(|cmd| !cmd.is_empty())
+        .map(String::from)
+        .collect()
+}
+
+/// Run the commands of a script file as if they were typed interactively
+///
+/// Stops at the first failing command unless `continue_on_error` is set,
+/// in which case all commands run and the number of failures is reported.
+pub fn run_script(drone: &mut Tello, path: &str, continue_on_error: bool, options: &ExecOptions) -> io::Result<()> {
+    if options.script_depth >= MAX_SCRIPT_DEPTH {
+        return Err(usage_error(format!("Scripts nested more than {} deep, does {} run itself?", MAX_SCRIPT_DEPTH, path)));
+    }
+    
+    let contents = std::fs::read_to_string(path)
+        .map_err(|e| command_failed(format!("Failed to read script {}: {}", path, e)))?;
+    let commands = parse_script(&contents);
+    
+    options.progress(format_args!("Running script {} ({} commands)", path, commands.len()));
+    let nested = ExecOptions { script_depth: options.script_depth + 1, ..*options };
+    run_command_list(drone, &commands, "Script", continue_on_error, &nested)?;
+    options.progress(format_args!("Script {} completed", path));
+    Ok(())
+}
+
+/// Run semicolon separated commands, e.g. "takeoff; forward 50; land"
+///
+/// Stops at the first failing command. An "exit" ends the run successfully.
+pub fn run_commands(drone: &mut Tello, commands: &str, options: &ExecOptions) -> io::Result<()> {
+    let commands = parse_script(commands);
+    match run_command_list(drone, &commands, "Batch", false, options) {
+        Err(e) if is_exit_request(&e) => Ok(()),
+        result => result,
+    }
+}
+
+/// Execute a list of commands with their delays, echoing each one
+///
+/// `name` ("Script", "Batch") is used in the error messages.
+fn run_command_list(drone: &mut Tello, commands: &[String], name: &str, continue_on_error: bool,
+    options: &ExecOptions) -> io::Result<()> {
+    let options = &ExecOptions { batch: true, ..*options };
+    let command_delays = CommandDelay::new();
+    let mut failures = 0;
+    
+    for cmd in commands {
+        options.progress(format_args!("> {}", cmd));
+        let parts: Vec<&str> = cmd.split_whitespace().collect();
+        
+        if let Err(e) = execute_with_delay(drone, &parts, &command_delays, options) {
+            if is_exit_request(&e) {
+                return Err(e);
+            }
+            if !continue_on_error {
+                return Err(command_failed(format!("{} stopped at '{}': {}", name, cmd, e)));
+            }
+            eprintln!("Error executing command: {}", e);
+            failures += 1;
+        }
+    }
+    
+    if failures > 0 {
+        return Err(command_failed(format!("{} of {} {} commands failed",
+            failures, commands.len(), name.to_lowercase())));
+    }
+    
+    Ok(())
+}
+
+/// How the application was asked to run
+#[derive(Debug, PartialEq)]
+pub enum RunMode {
+    /// Interactive prompt (no arguments)
+    Interactive,
+    /// Run the commands given with --exec and exit
+    Exec(String),
+    /// Run a script file given with --script and exit
+    Script { path: String, continue_on_error: bool },
+    /// Print usage and exit
+    Help,
+}
+
+/// Options given on the command line
+#[derive(Debug, PartialEq)]
+pub struct CliOptions {
+    pub mode: RunMode,
+    /// Print "state" and "info" as JSON
+    pub json: bool,
+    /// Print commands instead of sending them to a drone
+    pub dry_run: bool,
+    /// Start disarmed and refuse takeoff and moves until "arm"
+    pub require_arming: bool,
+    /// Allow "raw" SDK commands
+    pub expert: bool,
+    /// How much the library traces to stderr
+    pub verbosity: Level,
+    /// Lands the drone when pressed at the interactive prompt, `None` for no key
+    pub abort_key: Option<KeyEvent>,
+}
+
+/// Parse the command line arguments (without the program name)
+pub fn parse_args(args: &[String]) -> io::Result<CliOptions> {
+    let mut mode = RunMode::Interactive;
+    let mut continue_on_error = false;
+    let mut json = false;
+    let mut dry_run = false;
+    let mut require_arming = false;
+    let mut expert = false;
+    let mut verbosity = Level::default();
+    let mut abort_key = Some(KeyEvent(KeyCode::Esc, Modifiers::NONE));
+    let mut iter = args.iter();
+    
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--exec" | "-e" => {
+                let commands = iter.next()
+                    .ok_or_else(|| usage_error(String::from("--exec needs a command list")))?;
+                mode = RunMode::Exec(commands.clone());
+            },
+            "--script" | "-s" => {
+                let path = iter.next()
+                    .ok_or_else(|| usage_error(String::from("--script needs a file path")))?;
+                mode = RunMode::Script { path: path.clone(), continue_on_error: false };
+            },
+            "--continue" => continue_on_error = true,
+            "--json" => json = true,
+            "--dry-run" => dry_run = true,
+            "--require-arming" => require_arming = true,
+            "--expert" => expert = true,
+            "--verbose" | "-v" => verbosity = Level::Debug,
+            "--verbosity" => {
+                let level = iter.next()
+                    .ok_or_else(|| usage_error(String::from("--verbosity needs a level")))?;
+                verbosity = level.parse().map_err(usage_error)?;
+            },
+            "--abort-key" => {
+                let key = iter.next()
+                    .ok_or_else(|| usage_error(String::from("--abort-key needs a key")))?;
+                abort_key = parse_abort_key(key)?;
+            },
+            "--help" | "-h" => return Ok(CliOptions { mode: RunMode::Help, json, dry_run, require_arming, expert, verbosity, abort_key }),
+            _ => return Err(usage_error(format!("Unknown argument: {}", arg))),
+        }
+    }
+    
+    if let RunMode::Script { continue_on_error: flag, .. } = &mut mode {
+        *flag = continue_on_error;
+    } else if continue_on_error {
+        return Err(usage_error(String::from("--continue can only be used with --script")));
+    }
+    
+    Ok(CliOptions { mode, json, dry_run, require_arming, expert, verbosity, abort_key })
+}
+
+/// Collect application, network and drone information as a JSON object
+///
+/// Drone queries that fail are reported as null.
+fn info_json(drone: &Tello) -> serde_json::Value {
+    let queries = drone.query_all();
+    
+    json!({
+        "version": VERSION,
+        "build_date": BUILD_DATE,
+        "network": {
+            "drone_address": drone.tello_addr().to_string(),
+            "command_port": drone.tello_addr().port(),
+            "response_port": drone.local_port(),
+            "state_port": drone.state_port(),
+            "media_port": drone.file_transfer_port(),
+            "video_port": drone.video_port(),
         },
-        "exit" => {
-            println!("Exiting Tello Control...");
-            return Err(io::Error::new(io::ErrorKind::Other, "Exit requested"));
+        "drone": {
+            "model": drone.model().map(|model| model.to_string()),
+            "sdk_version": queries.get("sdk"),
+            "serial_number": queries.get("sn"),
+            "hardware_version": queries.get("hardware"),
+            "firmware_version": queries.get("version"),
+            "battery": queries.get("battery").and_then(|battery| battery.parse::<u8>().ok()),
+            "wifi_snr": queries.get("wifi").and_then(|snr| snr.parse::<i32>().ok()),
         },
-        "wait" => {
-            if parts.len() < 2 {
-                println!("Please specify wait time in seconds");
+        "state": drone.get_telemetry(),
+    })
+}
+
+/// Print command line usage
+pub fn print_usage(program: &str) {
+    println!("Usage: {} [--json] [--dry-run] [--require-arming] [--expert] [-v | --verbosity <level>] [--abort-key <key>] [--exec <commands> | --script <file> [--continue]]", program);
+    println!();
+    println!("Without arguments the interactive prompt is started.");
+    println!("  -e, --exec <commands> - Run semicolon separated commands and exit");
+    println!("  -s, --script <file>   - Run commands from a script file and exit");
+    println!("      --continue        - Keep running the script after a failing command");
+    println!("      --json            - Print \"state\" and \"info\" as JSON");
+    println!("      --dry-run         - Print commands instead of sending them to a drone");
+    println!("      --require-arming  - Start disarmed; takeoff and moves need 'arm' first");
+    println!("      --expert          - Allow 'raw' to send any SDK command");
+    println!("  -v, --verbose         - Trace commands and responses to stderr");
+    println!("      --verbosity <level> - Trace level: quiet (default), warn, info or debug");
+    println!("      --abort-key <key> - Key that lands at once at the prompt: esc (default), f1-f12, ctrl-<letter> or none");
+    println!("  -h, --help            - Show this help");
+}
+
+/// Get the path to the history file
+fn get_history_file_path() -> PathBuf {
+    let mut home_dir = dirs::home_dir().unwrap_or_else(|| PathBuf::from("."));
+    home_dir.push(".tello_history");
+    home_dir
+}
+
+/// Get the path to the file aliases are saved in
+fn get_aliases_file_path() -> PathBuf {
+    let mut home_dir = dirs::home_dir().unwrap_or_else(|| PathBuf::from("."));
+    home_dir.push(".tello_aliases");
+    home_dir
+}
+
+/// Print available commands
+fn print_available_commands() {
+    println!("\n=== SYSTEM COMMANDS ===");
+    println!("  help           - Show available commands");
+    println!("  version        - Show application version");
+    println!("  info [--json]  - Show detailed information about application and connected drone");
+    println!("  exit           - Exit the application");
+    println!("  wait <seconds> - Wait specified number of seconds between commands");
+    println!("  script <path> [--continue] - Run commands from a script file");
+    println!("  repeat <n> <commands> - Run semicolon separated commands n times");
+    println!("  sequence <command @seconds>, ... - Run commands, each followed by its own wait");
+    println!("  reconnect      - Re-establish the connection after the link was lost");
+    println!("  reboot [--yes] - Reboot the drone to recover from a stuck state, asks first unless --yes");
+    println!("  config reload  - Read the settings from ~/.tello.toml again");
+    println!("  raw <command>  - Send an SDK command as typed and print the response (needs --expert)");
+    println!("  alias [name]   - List aliases, or show one");
+    println!("  alias <name> = <commands> - Define a name for semicolon separated commands");
+    println!("  unalias <name> - Remove an alias");
+    println!("  history save <file> - Save the commands entered in this session to a file");
+    println!("  history clear  - Clear the command history, including the history file");
+    println!("  record start <file> - Record the commands that succeed, with the pauses between them");
+    println!("  record stop    - Save the recorded commands as a script to replay with 'script'");
+    println!("  prompt <template> - Set the prompt, e.g. 'prompt [bat {{battery}}%] > ' ({{battery}}, {{height}}, {{id}})");
+    println!("  prompt reset   - Go back to the plain '> ' prompt");
+    println!("  if <battery|height|temp> <<|>|==> <value> then <command> - Run the command only if the telemetry matches");
+    println!("  id             - Show the drone's serial number and SDK version (cached until reconnect)");
+    println!("  wifi           - Show WiFi signal-to-noise ratio");
+    println!("  wifi set <ssid> <password> - Change the drone's hotspot name and password");
+    println!("  ap <ssid> <password> - Make the drone join an existing WiFi network (Tello EDU)");
+    
+    println!("\n=== FLIGHT CONTROL COMMANDS ===");
+    println!("  arm            - Allow takeoff and moves (needed with --require-arming)");
+    println!("  disarm         - Refuse takeoff and moves until the next arm");
+    println!("  takeoff [height] - Take off (optional height in meters, default 1m, max 8m)");
+    println!("  throwfly       - Take off by throwing the drone within 5 seconds");
+    println!("  land           - Land the drone");
+    println!("  emergency      - Stop all motors immediately (alias: kill)");
+    println!("  state [--json] - Get current drone state/telemetry");
+    println!("  status         - Summarize connection, state packets, video, position and battery");
+    println!("  hover <seconds> - Stay in the air for a while, unlike 'wait' which lets the drone auto-land");
+    println!("  battery        - Show battery level");
+    println!("  motor on|off   - Spin the motors at idle without flying, e.g. for cooling");
+    println!("  temp           - Show the lowest and highest drone temperature");
+    println!("  height         - Show the drone's height and time-of-flight distance next to the tracked z");
+    println!("  velocity       - Show the drone's speed along x, y and z in cm/s");
+    println!("  telemetry log start <file> [csv|json] - Record every state packet to a file (default csv)");
+    println!("  telemetry log stop - Stop recording state packets");
+    println!("  flighttime     - Show the seconds in the air since takeoff and the drone's motor time");
+    println!("  ceiling [cm|off] - Set the altitude ceiling for takeoff, up and go, or show it");
+    println!("  geofence [<minx> <miny> <minz> <maxx> <maxy> <maxz>|off] - Keep moves inside a box in meters, or show it");
+    
+    println!("\n=== MOVEMENT COMMANDS ===");
+    println!("  forward <distance> - Move forward by specified distance in cm (1-500)");
+    println!("  back <distance>    - Move backward by specified distance in cm (1-500)");
+    println!("  left <distance>    - Move left by specified distance in cm (1-500)");
+    println!("  right <distance>   - Move right by specified distance in cm (1-500)");
+    println!("  up <distance>      - Move up by specified distance in cm (1-500)");
+    println!("  down <distance>    - Move down by specified distance in cm (1-500)");
+    println!("  rotate_cw <degrees> - Rotate clockwise by specified degrees");
+    println!("  rotate_ccw <degrees> - Rotate counter-clockwise by specified degrees");
+    println!("  flip <direction> - Flip in the specified direction (l, r, f, b)");
+    println!("  speed [value]  - Set flight speed in cm/s (10-100), or show it without a value");
+    println!("  clamp [on|off] - Clamp distances into 1-500 cm and split longer moves, or show the setting");
+    println!("  go <x> <y> <z> <speed> - Fly to x y z (cm, relative) at speed (10-100 cm/s)");
+    println!("  curve <x1> <y1> <z1> <x2> <y2> <z2> <speed> - Fly a curve through two points (10-60 cm/s)");
+    println!("  move <fwd> <right> <up> [speed] - Move on all three axes at once (cm, negative for back/left/down)");
+    println!("  square <side> [speed] - Fly a square, turning right at each corner (side in cm)");
+    println!("  circle <radius> [speed] - Fly a circle to the right with two curves (50-250 cm, 10-60 cm/s)");
+    println!("  polygon <sides> <side> [speed] - Fly a regular polygon, turning right at each corner (side in cm)");
+    println!("  eight <radius> [speed] - Fly a figure eight, a circle to the right and one to the left");
+    println!("  go_to_pad <x> <y> <z> <speed> <pad> - Fly to x y z (cm) relative to mission pad 1-8");
+    println!("  jump <x> <y> <z> <speed> <yaw> <pad1> <pad2> - Fly from pad1 to pad2 and turn to yaw");
+    println!("  rc <a> <b> <c> <d> - Send RC control: left/right, forward/back, up/down, yaw (-100..100)");
+    println!("  stop               - Stop the current movement and hover in place");
+    
+    println!("\n=== CAMERA COMMANDS ===");
+    println!("  photo          - Take a photo");
+    println!("  photo save [path] - Save a frame of the video stream as a JPEG");
+    println!("  video start    - Start recording video");
+    println!("  video stop     - Stop recording video");
+    println!("  video record <file> - Save the H.264 video stream to a file until 'video stop'");
+    println!("  resolution high|low - Set the video resolution (720p or 480p) before 'video start'");
+    println!("  fps high|middle|low - Set the video frame rate (30, 15 or 5) before 'video start'");
+    println!("  camera forward|down - Stream the forward or downward camera (Tello EDU)");
+    println!("  bitrate <level> - Set the video bitrate: 0 = auto, 1-5 = 1-5 Mbps");
+    println!("  bitrate?       - Show the video bitrate");
+    
+    println!("\n=== MEDIA MANAGEMENT ===");
+    println!("  media list     - List media files on the drone");
+    println!("  media download <filename> - Download media file from drone");
+    println!("  media downloadall - Download every media file on the drone");
+    println!("  media direct <filename>   - Download media using direct TCP connection");
+    println!("  media delete <filename>   - Delete media file from drone");
+    println!("  media deleteall - Delete all media files from drone");
+    println!("  media path <path> - Set download path for media files");
+    
+    println!("\n=== POSITIONING COMMANDS ===");
+    println!("  position <x> <y> <z> - Set current drone position for camera positioning");
+    println!("  get_position         - Display current drone position");
+    println!("  fly_to <x> <y> <z> [speed] - Fly to a tracked position in meters (speed 10-100 cm/s)");
+    println!("  home [speed]         - Fly back to the tracked origin at the current height and face heading 0");
+    println!("  land_at <x> <y>      - Fly to a tracked position in meters at the current height and land there");
+    println!("  direction [degrees]  - Display the tracked heading, or set it to match the drone");
+    println!("  camera_to_center <x> <y> [z] - Point camera towards the specified center point, with z also show the tilt to it");
+    println!("  camera_from_center <x> <y> - Point camera away from the specified center point");
+    println!("  path                 - Show how many positions of the flight path were recorded");
+    println!("  path save <file>     - Save the recorded flight path as CSV (timestamp,x,y,z,heading)");
+    println!("  path fly <file> [speed] - Fly through the positions of a saved flight path (speed 10-100 cm/s)");
+    println!("  mpad [on|off]        - Enable/disable mission pad detection, or show the detected pad");
+    println!("  mpad dir <0-2>       - Detect pads with the downward (0), forward (1) or both (2) cameras");
+    
+    println!("\n=== MAINTENANCE ===");
+    println!("  calibrate      - Calibrate the IMU; place the drone on a flat surface first, takes 10+ seconds");
+    println!();
+}
+
+/// Sleep for `duration` while counting down the seconds on one line
+fn countdown_sleep(duration: Duration) -> io::Result<()> {
+    countdown("Waiting", duration, |slice| {
+        thread::sleep(slice);
+        Ok(())
+    })
+}
+
+/// Spend `duration` in one-second steps, showing the seconds left
+///
+/// `step` is called with the length of each step and has to take that
+/// long. The display is rounded to whole seconds and rewritten in place
+/// with a carriage return.
+fn countdown<F: FnMut(Duration) -> io::Result<()>>(label: &str, duration: Duration, mut step: F) -> io::Result<()> {
+    let mut remaining = duration;
+    
+    while !remaining.is_zero() {
+        print!("\r{}... {}s   ", label, remaining.as_secs_f64().round());
+        io::stdout().flush()?;
+        
+        let slice = remaining.min(Duration::from_secs(1));
+        step(slice)?;
+        remaining -= slice;
+    }
+    
+    println!("\r{}... 0s   ", label);
+    Ok(())
+}
+
+/// Describe a video bitrate level, e.g. "auto" or "3 Mbps"
+fn bitrate_label(level: u8) -> String {
+    if level == 0 {
+        String::from("auto")
+    } else {
+        format!("{} Mbps", level)
+    }
+}
+
+/// Error for a command entered with missing or malformed arguments
+fn usage_error(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, message)
+}
+
+/// Error for a command the drone failed to execute
+fn command_failed(message: String) -> io::Error {
+    io::Error::other(message)
+}
+
+/// Parse and execute a single command
+fn execute_command(drone: &mut Tello, parts: &[&str], options: &ExecOptions) -> io::Result<()> {
+    let command = parse_command(parts)?;
+    execute(drone, command, options)
+}
+
+/// Execute a parsed command
+///
+/// Out-of-range arguments are refused here, before a round trip to the drone.
+pub fn execute(drone: &mut Tello, command: Command, options: &ExecOptions) -> io::Result<()> {
+    command.check_ranges(drone.clamp_movements(), drone.strict_rotation())?;
+    
+    match command {
+        // === SYSTEM COMMANDS ===
+        Command::Help => {
+            println!("Available commands:");
+            print_available_commands();
+        },
+        Command::Version => {
+            println!("DJI Tello Controller Library");
+            println!("Version: {}", VERSION);
+            println!("Build date: {}", BUILD_DATE);
+            println!("Copyright (c) 2025 aleskin");
+        },
+        Command::Info { json } => {
+            if wants_json(options, json) {
+                println!("{}", info_json(drone));
+                return Ok(());
+            }
+            print_info(drone);
+        },
+        Command::Identity => {
+            match drone.identity() {
+                Ok(identity) => {
+                    println!("Serial number: {}", identity.serial);
+                    println!("SDK version: {}", identity.sdk_version);
+                },
+                Err(e) => return Err(command_failed(format!("Failed to identify the drone: {}", e))),
+            }
+        },
+        Command::WifiSignal => {
+            match drone.get_wifi_snr() {
+                Ok(snr) => println!("WiFi SNR: {}", snr),
+                Err(e) => return Err(command_failed(format!("Failed to get WiFi signal: {}", e))),
+            }
+        },
+        Command::SetWifi { ssid, password } => {
+            match drone.set_wifi(&ssid, &password) {
+                Ok(_) => println!("Hotspot changed to {}. Join the new network to reconnect.", ssid),
+                Err(e) => return Err(command_failed(format!("Failed to change WiFi settings: {}", e))),
+            }
+        },
+        Command::ConnectToAp { ssid, password } => {
+            match drone.connect_to_ap(&ssid, &password) {
+                Ok(_) => {
+                    println!("The drone is rebooting to join {}.", ssid);
+                    println!("Restart the application with the drone's new IP address to control it.");
+                },
+                Err(e) => return Err(command_failed(format!("Failed to switch to station mode: {}", e))),
+            }
+        },
+        Command::Reconnect => {
+            match drone.reconnect() {
+                Ok(_) => println!("Reconnected to drone"),
+                Err(e) => return Err(command_failed(format!("Failed to reconnect: {}", e))),
+            }
+        },
+        Command::Reboot { confirmed } => {
+            if !confirmed && options.batch {
+                return Err(usage_error(String::from("reboot asks for confirmation, use 'reboot --yes' in scripts and --exec")));
+            }
+            if !confirmed && !confirm("Reboot the drone? The connection is lost until it restarts")? {
+                println!("Reboot cancelled");
                 return Ok(());
             }
+            drone.reboot()
+                .map_err(|e| command_failed(format!("Failed to reboot: {}", e)))?;
+            println!("The drone is rebooting. Use 'reconnect' once it is back up, after about 10 seconds.");
+        },
+        Command::ConfigReload => {
+            let path = TelloConfig::default_path();
+            let config = TelloConfig::load(&path)
+                .map_err(|e| command_failed(format!("Failed to read the settings: {}", e)))?;
+            config.apply(drone)
+                .map_err(|e| command_failed(format!("Failed to apply the settings: {}", e)))?;
+            println!("Settings reloaded from {}", path.display());
             
-            if let Ok(seconds) = parts[1].parse::<f64>() {
-                let millis = (seconds * 1000.0) as u64;
-                println!("Waiting for {} seconds...", seconds);
-                thread::sleep(Duration::from_millis(millis));
-                println!("Wait completed");
-            } else {
-                println!("Invalid wait time: {}. Please specify a number of seconds.", parts[1]);
+            // The address is only used when the drone is created
+            if config.drone_ip.is_some_and(|ip| ip != drone.tello_addr().ip().to_string()) {
+                println!("The new drone_ip takes effect after a restart");
+            }
+        },
+        Command::Raw(command) => {
+            // Nothing is checked, so a stray "motoron" or "emergency" goes straight to the drone
+            if !EXPERT_MODE.load(Ordering::Relaxed) {
+                return Err(usage_error(String::from("raw sends commands unchecked, start with --expert to allow it")));
             }
+            // The reply is printed as the drone sent it, "error" replies included
+            let timeout = response_timeout(&command).unwrap_or(COMMAND_TIMEOUT);
+            let response = drone.send_command_with_response(&command, timeout)
+                .map_err(|e| command_failed(format!("Failed to send '{}': {}", command, e)))?;
+            println!("{}", response);
+        },
+        Command::Exit => {
+            println!("Exiting Tello Control...");
+            return Err(io::Error::other("Exit requested"));
+        },
+        Command::Wait(duration) => {
+            countdown_sleep(duration)?;
+            println!("Wait completed");
+        },
+        Command::Repeat(args) => {
+            run_repeat(drone, &args, &Aliases::default(), &CommandDelay::new(), options)?;
+        },
+        Command::Sequence(args) => {
+            run_sequence(drone, &args, &CommandDelay::new(), options)?;
+        },
+        Command::If { condition, then } => {
+            let telemetry = drone.get_telemetry();
+            run_conditional(drone, &condition, &then, telemetry.as_ref(), options)?;
+        },
+        Command::Hover(duration) => {
+            // The drone gets a keepalive with every step of the countdown
+            countdown("Hovering", duration, |slice| {
+                drone.hover(slice).map_err(|e| command_failed(format!("Hover failed: {}", e)))
+            })?;
+            println!("Hover completed");
+        },
+        Command::Script { path, continue_on_error } => {
+            run_script(drone, &path, continue_on_error, options)?;
         },
         
         // === FLIGHT CONTROL COMMANDS ===
-        "takeoff" => {
-            let height = if parts.len() > 1 {
-                match parts[1].parse::<f32>() {
-                    Ok(h) => Some(h),
-                    Err(_) => {
-                        println!("Warning: Invalid height value. Using default height (1m).");
-                        None
+        Command::Takeoff(height) => {
+            if let Err(e) = drone.takeoff(height) {
+                return Err(command_failed(format!("Takeoff failed: {}", e)));
+            }
+            println!("Takeoff command executed successfully");
+        },
+        Command::ThrowTakeoff => {
+            println!("Throw the drone gently into the air within 5 seconds...");
+            if let Err(e) = drone.throw_takeoff() {
+                return Err(command_failed(format!("Throw takeoff failed: {}", e)));
+            }
+        },
+        Command::Land => {
+            if let Err(e) = drone.land() {
+                return Err(command_failed(format!("Landing failed: {}", e)));
+            }
+            println!("Landing command executed successfully");
+            
+            // The drone answers before it is down, so wait for the telemetry to show it
+            if drone.is_link_alive(Duration::from_secs(1)) {
+                match drone.wait_until_landed(LANDING_TIMEOUT) {
+                    Ok(_) => println!("Landed"),
+                    Err(e) => eprintln!("Warning: Landing not confirmed by telemetry: {}", e),
+                }
+            }
+        },
+        Command::Arm => {
+            drone.arm();
+            if drone.requires_arming() {
+                println!("Armed, takeoff and moves are allowed");
+            } else {
+                println!("Armed (arming is not required, start with --require-arming to enforce it)");
+            }
+        },
+        Command::Disarm => {
+            drone.disarm();
+            if drone.requires_arming() {
+                println!("Disarmed, takeoff and moves are refused until 'arm'");
+            } else {
+                println!("Disarmed (arming is not required, start with --require-arming to enforce it)");
+            }
+        },
+        Command::Emergency => {
+            match drone.emergency() {
+                Ok(_) => println!("Emergency stop executed, motors cut"),
+                Err(e) => return Err(command_failed(format!("Emergency stop failed: {}", e))),
+            }
+        },
+        Command::Battery => {
+            match drone.get_battery() {
+                Ok(battery) => println!("Battery: {}%", battery),
+                Err(e) => return Err(command_failed(format!("Failed to get battery level: {}", e))),
+            }
+        },
+        Command::Motor(true) => {
+            drone.motor_on().map_err(|e| command_failed(format!("Failed to start the motors: {}", e)))?;
+            println!("Motors spinning at idle");
+        },
+        Command::Motor(false) => {
+            drone.motor_off().map_err(|e| command_failed(format!("Failed to stop the motors: {}", e)))?;
+            println!("Motors stopped");
+        },
+        Command::Calibrate => {
+            println!("Calibrating the IMU, keep the drone still...");
+            drone.calibrate_imu().map_err(|e| command_failed(format!("Calibration failed: {}", e)))?;
+            println!("IMU calibrated");
+        },
+        Command::Temperature => {
+            match drone.get_temperature() {
+                Ok((low, high)) => println!("Temperature: {}°C - {}°C", low, high),
+                Err(e) => return Err(command_failed(format!("Failed to get temperature: {}", e))),
+            }
+        },
+        Command::Height => {
+            let format_cm = |cm: Option<i32>| cm.map_or_else(|| String::from("unknown"), |cm| format!("{} cm", cm));
+            println!("Height: {}", format_cm(drone.get_height_cm()));
+            println!("Time-of-flight distance: {}", format_cm(drone.get_tof_cm()));
+            println!("Tracked z: {:.0} cm", drone.get_position().z * 100.0);
+        },
+        Command::TelemetryLog(TelemetryLogCommand::Start { file, format }) => {
+            drone.start_telemetry_log(&file, format)
+                .map_err(|e| command_failed(format!("Failed to open telemetry log {}: {}", file, e)))?;
+            println!("Recording telemetry to {}", file);
+        },
+        Command::TelemetryLog(TelemetryLogCommand::Stop) => {
+            drone.stop_telemetry_log();
+            println!("Telemetry recording stopped");
+        },
+        Command::Velocity => {
+            match drone.get_velocity() {
+                Some((x, y, z)) => println!("Velocity: x={} y={} z={} cm/s", x, y, z),
+                None => println!("Velocity: unknown (no state received)"),
+            }
+        },
+        Command::FlightTime => {
+            match drone.flight_time() {
+                Some(elapsed) => println!("Flight time: {:.1} s", elapsed.as_secs_f32()),
+                None => println!("Flight time: not flying"),
+            }
+            
+            // The drone's own count of motor seconds, to compare against
+            match drone.get_motor_time() {
+                Ok(seconds) => println!("Motor time: {} s", seconds),
+                Err(e) => println!("Motor time: Unable to retrieve ({})", e),
+            }
+        },
+        Command::ShowCeiling | Command::SetCeiling(_) | Command::ClearCeiling => {
+            match command {
+                Command::SetCeiling(cm) => drone.set_max_altitude(cm),
+                Command::ClearCeiling => drone.clear_max_altitude(),
+                _ => {},
+            }
+            
+            match drone.max_altitude() {
+                Some(cm) => println!("Altitude ceiling: {} cm", cm),
+                None => println!("Altitude ceiling: off"),
+            }
+        },
+        Command::ShowGeofence | Command::SetGeofence(..) | Command::ClearGeofence => {
+            match command {
+                Command::SetGeofence(min, max) => drone.set_geofence(min, max),
+                Command::ClearGeofence => drone.clear_geofence(),
+                _ => {},
+            }
+            
+            match drone.geofence() {
+                Some((min, max)) => println!("Geofence: ({:.2}, {:.2}, {:.2}) to ({:.2}, {:.2}, {:.2})",
+                    min.x, min.y, min.z, max.x, max.y, max.z),
+                None => println!("Geofence: off"),
+            }
+        },
+        Command::Status => {
+            let status = drone.status();
+            println!("Connected: {}", if status.connected { "yes" } else { "no" });
+            match status.state_age {
+                Some(age) if status.receiving_state => println!("State packets: arriving (last {:.1} s ago)", age.as_secs_f32()),
+                Some(age) => println!("State packets: stopped (last {:.1} s ago)", age.as_secs_f32()),
+                None => println!("State packets: none received"),
+            }
+            println!("Video recording: {}", if status.video_recording { "on" } else { "off" });
+            println!("Position: ({:.2}, {:.2}, {:.2})", status.position.x, status.position.y, status.position.z);
+            println!("Heading: {:.1}°", status.heading);
+            match status.battery {
+                Some(battery) => println!("Battery: {}%", battery),
+                None => println!("Battery: unknown"),
+            }
+        },
+        Command::State { json } => {
+            if wants_json(options, json) {
+                // null when no state has been received yet
+                println!("{}", json!(drone.get_telemetry()));
+                return Ok(());
+            }
+            
+            if let Some(state) = drone.get_state() {
+                println!("Drone state: {}", state);
+                
+                // Parse and display the state in a more readable format
+                let state_pairs: Vec<&str> = state.split(';').collect();
+                println!("Parsed state:");
+                for pair in state_pairs {
+                    if !pair.is_empty() {
+                        println!("  {}", pair);
                     }
                 }
             } else {
-                None
+                println!("No state information available. Make sure the drone is connected.");
+            }
+        },
+        
+        // === MOVEMENT COMMANDS ===
+        Command::Forward(distance) | Command::Back(distance) | Command::Left(distance)
+        | Command::Right(distance) | Command::Up(distance) | Command::Down(distance) => {
+            let (name, result) = match command {
+                Command::Forward(_) => ("forward", drone.forward(distance)),
+                Command::Back(_) => ("back", drone.back(distance)),
+                Command::Left(_) => ("left", drone.left(distance)),
+                Command::Right(_) => ("right", drone.right(distance)),
+                Command::Up(_) => ("up", drone.up(distance)),
+                _ => ("down", drone.down(distance)),
             };
             
-            if let Err(e) = drone.takeoff(height) {
-                eprintln!("Takeoff failed: {}", e);
-            } else {
-                println!("Takeoff command executed successfully");
+            match result {
+                Ok(_) => println!("Moved {} by {} cm", name, distance),
+                Err(e) => return Err(command_failed(format!("Failed to move {}: {}", name, e))),
+            }
+        },
+        Command::RotateCw(degrees) => {
+            match drone.rotate_cw(degrees) {
+                Ok(_) => println!("Rotated clockwise by {} degrees", degrees),
+                Err(e) => return Err(command_failed(format!("Failed to rotate: {}", e))),
+            }
+        },
+        Command::RotateCcw(degrees) => {
+            match drone.rotate_ccw(degrees) {
+                Ok(_) => println!("Rotated counter-clockwise by {} degrees", degrees),
+                Err(e) => return Err(command_failed(format!("Failed to rotate: {}", e))),
+            }
+        },
+        Command::Flip(direction) => {
+            match drone.flip(direction) {
+                Ok(_) => println!("Flipped {:?}", direction),
+                Err(e) => return Err(command_failed(format!("Failed to flip: {}", e))),
+            }
+        },
+        Command::GetSpeed => {
+            match drone.get_speed() {
+                Ok(speed) => println!("Speed: {} cm/s", speed),
+                Err(e) => return Err(command_failed(format!("Failed to get speed: {}", e))),
+            }
+        },
+        Command::SetSpeed(speed) => {
+            match drone.set_speed(speed) {
+                Ok(_) => println!("Speed set to {} cm/s", speed),
+                Err(e) => return Err(command_failed(format!("Failed to set speed: {}", e))),
+            }
+        },
+        Command::Go { x, y, z, speed } => {
+            match drone.go(x, y, z, speed) {
+                Ok(_) => println!("Moved to ({}, {}, {}) at {} cm/s", x, y, z, speed),
+                Err(e) => return Err(command_failed(format!("Failed to go: {}", e))),
+            }
+        },
+        Command::Curve { p1, p2, speed } => {
+            match drone.curve(p1, p2, speed) {
+                Ok(_) => println!("Flew curve through {:?} to {:?} at {} cm/s", p1, p2, speed),
+                Err(e) => return Err(command_failed(format!("Failed to fly curve: {}", e))),
+            }
+        },
+        Command::Move { forward, right, up, speed } => {
+            // Default to the last speed set with "speed", like fly_to
+            let speed = speed.unwrap_or_else(|| drone.last_set_speed().unwrap_or(DEFAULT_FLY_TO_SPEED));
+            match drone.move_by(forward, right, up, speed) {
+                Ok(_) => println!("Moved {} cm forward, {} cm right, {} cm up", forward, right, up),
+                Err(e) => return Err(command_failed(format!("Failed to move: {}", e))),
+            }
+        },
+        Command::Square { side, speed } => {
+            let speed = speed.unwrap_or_else(|| drone.last_set_speed().unwrap_or(DEFAULT_FLY_TO_SPEED));
+            match drone.fly_square(side, speed) {
+                Ok(_) => println!("Flew a {} cm square at {} cm/s", side, speed),
+                Err(e) => return Err(command_failed(format!("Failed to fly square: {}", e))),
+            }
+        },
+        Command::Circle { radius, speed } => {
+            // Curves are limited to 60 cm/s
+            let speed = speed.unwrap_or_else(|| drone.last_set_speed().unwrap_or(DEFAULT_FLY_TO_SPEED).min(60));
+            match drone.fly_circle(radius, speed) {
+                Ok(_) => println!("Flew a circle with a {} cm radius at {} cm/s", radius, speed),
+                Err(e) => return Err(command_failed(format!("Failed to fly circle: {}", e))),
+            }
+        },
+        Command::Polygon { sides, side, speed } => {
+            let speed = speed.unwrap_or_else(|| drone.last_set_speed().unwrap_or(DEFAULT_FLY_TO_SPEED));
+            match drone.fly_polygon(sides, side, speed) {
+                Ok(_) => println!("Flew a {}-sided polygon with {} cm sides at {} cm/s", sides, side, speed),
+                Err(e) => return Err(command_failed(format!("Failed to fly polygon: {}", e))),
+            }
+        },
+        Command::FigureEight { radius, speed } => {
+            // Curves are limited to 60 cm/s
+            let speed = speed.unwrap_or_else(|| drone.last_set_speed().unwrap_or(DEFAULT_FLY_TO_SPEED).min(60));
+            match drone.fly_figure_eight(radius, speed) {
+                Ok(_) => println!("Flew a figure eight with a {} cm radius at {} cm/s", radius, speed),
+                Err(e) => return Err(command_failed(format!("Failed to fly figure eight: {}", e))),
+            }
+        },
+        Command::GoToPad { x, y, z, speed, pad } => {
+            match drone.go_to_pad(x, y, z, speed, pad) {
+                Ok(_) => println!("Moved to ({}, {}, {}) over pad m{} at {} cm/s", x, y, z, pad, speed),
+                Err(e) => return Err(command_failed(format!("Failed to go to pad: {}", e))),
+            }
+        },
+        Command::Jump { target, speed, yaw, from_pad, to_pad } => {
+            match drone.jump(target, speed, yaw, from_pad, to_pad) {
+                Ok(_) => println!("Jumped from pad m{} to pad m{} at {} cm/s", from_pad, to_pad, speed),
+                Err(e) => return Err(command_failed(format!("Failed to jump: {}", e))),
+            }
+        },
+        Command::Clamp(clamp) => {
+            if let Some(clamp) = clamp {
+                drone.set_clamp_movements(clamp);
+            }
+            println!("Distance clamping: {}", if drone.clamp_movements() { "on" } else { "off" });
+        },
+        Command::Rc { roll, pitch, throttle, yaw } => {
+            if let Err(e) = drone.send_rc_control(roll, pitch, throttle, yaw) {
+                return Err(command_failed(format!("Failed to send RC control: {}", e)));
+            }
+        },
+        Command::Stop => {
+            match drone.stop() {
+                Ok(_) => println!("Drone stopped and hovering"),
+                Err(e) => return Err(command_failed(format!("Failed to stop: {}", e))),
+            }
+        },
+        
+        // === CAMERA COMMANDS ===
+        Command::SavePhoto(path) => {
+            match drone.capture_photo_to(path.as_deref()) {
+                Ok(path) => println!("Photo saved to {}", path.display()),
+                Err(e) => return Err(command_failed(format!("Failed to capture photo: {}", e))),
+            }
+        },
+        Command::Photo => {
+            match drone.take_photo() {
+                Ok(_) => println!("Photo taken successfully"),
+                Err(e) => return Err(command_failed(format!("Failed to take photo: {}", e))),
+            }
+        },
+        Command::Video(VideoCommand::Start) => {
+            match drone.start_video() {
+                Ok(_) => println!("Video recording started"),
+                Err(e) => return Err(command_failed(format!("Failed to start video: {}", e))),
+            }
+        },
+        Command::Video(VideoCommand::Record(file)) => {
+            match drone.record_video_to(&file) {
+                Ok(_) => println!("Recording video to {}", file),
+                Err(e) => return Err(command_failed(format!("Failed to record video: {}", e))),
+            }
+        },
+        Command::Video(VideoCommand::Stop) => {
+            match drone.stop_video() {
+                Ok(_) => println!("Video recording stopped"),
+                Err(e) => return Err(command_failed(format!("Failed to stop video: {}", e))),
+            }
+        },
+        Command::Resolution(resolution) => {
+            drone.set_video_resolution(resolution)
+                .map_err(|e| command_failed(format!("Failed to set resolution: {}", e)))?;
+            println!("Video resolution set to {}", resolution.as_str());
+        },
+        Command::Fps(fps) => {
+            drone.set_video_fps(fps)
+                .map_err(|e| command_failed(format!("Failed to set frame rate: {}", e)))?;
+            println!("Video frame rate set to {}", fps.as_str());
+        },
+        Command::Camera(camera) => {
+            drone.set_camera(camera)
+                .map_err(|e| command_failed(format!("Failed to switch camera: {}", e)))?;
+            println!("Camera set to {}", camera.as_str());
+        },
+        Command::SetBitrate(level) => {
+            drone.set_video_bitrate(level)
+                .map_err(|e| command_failed(format!("Failed to set bitrate: {}", e)))?;
+            println!("Video bitrate set to {}", bitrate_label(level));
+        },
+        Command::GetBitrate => {
+            match drone.get_video_bitrate() {
+                Ok(level) => println!("Video bitrate: {}", bitrate_label(level)),
+                Err(e) => return Err(command_failed(format!("Failed to get bitrate: {}", e))),
+            }
+        },
+        
+        // === MEDIA COMMANDS ===
+        Command::Media(media) => execute_media(drone, media)?,
+        
+        // === POSITIONING COMMANDS ===
+        Command::SetPosition(position) => {
+            drone.set_position(position.x, position.y, position.z);
+            println!("Drone position set to ({}, {}, {})", position.x, position.y, position.z);
+        },
+        Command::GetPosition => {
+            let pos = drone.get_position();
+            println!("Current drone position: ({:.2}, {:.2}, {:.2})", pos.x, pos.y, pos.z);
+        },
+        Command::FlyTo { target, speed } => {
+            // Default to the last speed set with "speed"
+            let speed = speed.unwrap_or_else(|| drone.last_set_speed().unwrap_or(DEFAULT_FLY_TO_SPEED));
+            match drone.fly_to(target, speed) {
+                Ok(_) => println!("Arrived at ({:.2}, {:.2}, {:.2})", target.x, target.y, target.z),
+                Err(e) => return Err(command_failed(format!("Failed to fly to target: {}", e))),
             }
         },
-        "land" => {
-            if let Err(e) = drone.land() {
-                eprintln!("Landing failed: {}", e);
-            } else {
-                println!("Landing command executed successfully");
+        Command::Home { speed } => {
+            // Default to the last speed set with "speed", like fly_to
+            let speed = speed.unwrap_or_else(|| drone.last_set_speed().unwrap_or(DEFAULT_FLY_TO_SPEED));
+            match drone.return_to_home(speed) {
+                Ok(_) => println!("Back home"),
+                Err(e) => return Err(command_failed(format!("Failed to return home: {}", e))),
             }
         },
-        "state" => {
-            if let Some(state) = drone.get_state() {
-                println!("Drone state: {}", state);
-                
-                // Parse and display the state in a more readable format
-                let state_pairs: Vec<&str> = state.split(';').collect();
-                println!("Parsed state:");
-                for pair in state_pairs {
-                    if !pair.is_empty() {
-                        println!("  {}", pair);
-                    }
-                }
-            } else {
-                println!("No state information available. Make sure the drone is connected.");
+        Command::LandAt { x, y } => {
+            match drone.land_at(x, y) {
+                Ok(_) => println!("Landed at ({:.2}, {:.2})", x, y),
+                Err(e) => return Err(command_failed(format!("Failed to land at target: {}", e))),
             }
         },
-        
-        // === MOVEMENT COMMANDS ===
-        "forward" | "back" | "left" | "right" | "up" | "down" => {
-            if parts.len() < 2 {
-                println!("Please specify distance for {} movement", parts[0]);
-                return Ok(());
+        Command::Direction(degrees) => {
+            if let Some(degrees) = degrees {
+                drone.set_direction(degrees);
             }
-            
-            match parts[1].parse::<i32>() {
-                Ok(distance) => {
-                    // Handle specific movement direction
-                    let result = match parts[0] {
-                        "forward" => drone.forward(distance),
-                        "back" => drone.back(distance),
-                        "left" => drone.left(distance),
-                        "right" => drone.right(distance),
-                        "up" => drone.up(distance),
-                        "down" => drone.down(distance),
-                        _ => unreachable!()
-                    };
-                    
-                    // Output result
-                    match result {
-                        Ok(_) => println!("Moved {} by {} cm", parts[0], distance),
-                        Err(e) => eprintln!("Failed to move {}: {}", parts[0], e),
-                    }
+            println!("Current drone direction: {:.1} degrees", drone.get_direction());
+        },
+        Command::Path(PathCommand::Show) => println!("Recorded positions: {}", drone.position_history().len()),
+        Command::Path(PathCommand::Save(file)) => {
+            drone.export_path_csv(&file)
+                .map_err(|e| command_failed(format!("Failed to save the flight path: {}", e)))?;
+            println!("Flight path with {} positions saved to {}", drone.position_history().len(), file);
+        },
+        Command::Path(PathCommand::Fly { file, speed }) => {
+            // Default to the last speed set with "speed", like fly_to
+            let speed = speed.unwrap_or_else(|| drone.last_set_speed().unwrap_or(DEFAULT_FLY_TO_SPEED));
+            drone.fly_path_from_csv(&file, speed)
+                .map_err(|e| command_failed(format!("Failed to fly the path: {}", e)))?;
+            let pos = drone.get_position();
+            println!("Flew the path from {}, now at ({:.2}, {:.2}, {:.2})", file, pos.x, pos.y, pos.z);
+        },
+        Command::MissionPad(MissionPadCommand::Show) => {
+            // Show the pad reported by the latest telemetry
+            match drone.get_telemetry() {
+                Some(telemetry) => match telemetry.mission_pad() {
+                    Some(mid) => println!("Mission pad {} detected at ({}, {}, {}) cm",
+                        mid, telemetry.x.unwrap_or(0), telemetry.y.unwrap_or(0), telemetry.z.unwrap_or(0)),
+                    None => println!("No mission pad detected"),
                 },
-                Err(_) => {
-                    eprintln!("Invalid distance value: {}", parts[1]);
-                }
+                None => println!("No state information available. Make sure the drone is connected."),
             }
         },
-        "rotate_cw" => {
-            if parts.len() < 2 {
-                println!("Please specify degrees for clockwise rotation");
-                return Ok(());
+        Command::MissionPad(MissionPadCommand::On) => {
+            match drone.enable_mission_pads() {
+                Ok(_) => println!("Mission pad detection enabled"),
+                Err(e) => return Err(command_failed(format!("Failed to enable mission pads: {}", e))),
             }
-            
-            match parts[1].parse::<i32>() {
-                Ok(degrees) => {
-                    match drone.rotate_cw(degrees) {
-                        Ok(_) => println!("Rotated clockwise by {} degrees", degrees),
-                        Err(e) => eprintln!("Failed to rotate: {}", e),
-                    }
-                },
-                Err(_) => {
-                    eprintln!("Invalid degrees value: {}", parts[1]);
-                }
+        },
+        Command::MissionPad(MissionPadCommand::Off) => {
+            match drone.disable_mission_pads() {
+                Ok(_) => println!("Mission pad detection disabled"),
+                Err(e) => return Err(command_failed(format!("Failed to disable mission pads: {}", e))),
             }
         },
-        "rotate_ccw" => {
-            if parts.len() < 2 {
-                println!("Please specify degrees for counter-clockwise rotation");
-                return Ok(());
+        Command::MissionPad(MissionPadCommand::Direction(direction)) => {
+            match drone.set_mission_pad_detection_direction(direction) {
+                Ok(_) => println!("Mission pad detection direction set to {}", direction),
+                Err(e) => return Err(command_failed(format!("Failed to set mission pad direction: {}", e))),
             }
-            
-            match parts[1].parse::<i32>() {
-                Ok(degrees) => {
-                    match drone.rotate_ccw(degrees) {
-                        Ok(_) => println!("Rotated counter-clockwise by {} degrees", degrees),
-                        Err(e) => eprintln!("Failed to rotate: {}", e),
-                    }
+        },
+        Command::CameraToCenter { x, y, z: Some(z) } => {
+            // With a height the tilt towards the center is reported as well
+            match drone.point_camera_to_center_3d(x, y, z) {
+                Ok(pitch) => {
+                    println!("Camera pointed towards center point ({}, {}, {})", x, y, z);
+                    println!("The center is {:.1}° {} the horizon", pitch.abs(), if pitch < 0.0 { "below" } else { "above" });
                 },
-                Err(_) => {
-                    eprintln!("Invalid degrees value: {}", parts[1]);
-                }
+                Err(e) => return Err(command_failed(format!("Failed to point camera: {}", e))),
             }
         },
-        
-        // === CAMERA COMMANDS ===
-        "photo" => {
-            match drone.take_photo() {
-                Ok(_) => println!("Photo taken successfully"),
-                Err(e) => eprintln!("Failed to take photo: {}", e),
+        Command::CameraToCenter { x, y, z: None } => {
+            match drone.point_camera_to_center(x, y) {
+                Ok(_) => println!("Camera pointed towards center point ({}, {})", x, y),
+                Err(e) => return Err(command_failed(format!("Failed to point camera: {}", e))),
             }
         },
-        "video" => {
-            if parts.len() < 2 {
-                println!("Please specify 'start' or 'stop' after 'video'");
-                return Ok(());
+        Command::CameraFromCenter { x, y } => {
+            match drone.point_camera_from_center(x, y) {
+                Ok(_) => println!("Camera pointed away from center point ({}, {})", x, y),
+                Err(e) => return Err(command_failed(format!("Failed to point camera: {}", e))),
             }
-            
-            match parts[1] {
-                "start" => {
-                    match drone.start_video() {
-                        Ok(_) => println!("Video recording started"),
-                        Err(e) => eprintln!("Failed to start video: {}", e),
-                    }
-                },
-                "stop" => {
-                    match drone.stop_video() {
-                        Ok(_) => println!("Video recording stopped"),
-                        Err(e) => eprintln!("Failed to stop video: {}", e),
+        },
+    }
+    
+    Ok(())
+}
+
+/// Ask a yes/no question on stdin, anything but "y" or "yes" is a no
+fn confirm(question: &str) -> io::Result<bool> {
+    print!("{} [y/N] ", question);
+    io::stdout().flush()?;
+    
+    let mut answer = String::new();
+    io::stdin().read_line(&mut answer)?;
+    Ok(matches!(answer.trim().to_lowercase().as_str(), "y" | "yes"))
+}
+
+/// Execute one of the "media" subcommands
+fn execute_media(drone: &mut Tello, command: MediaCommand) -> io::Result<()> {
+    match command {
+        MediaCommand::List => {
+            match drone.list_media_detailed() {
+                Ok(files) => {
+                    println!("Media files on drone:");
+                    for file in files {
+                        match file.size {
+                            Some(size) => println!("  {} ({}, {} bytes)", file.name, file.kind, size),
+                            None => println!("  {} ({})", file.name, file.kind),
+                        }
                     }
                 },
-                _ => println!("Unknown video command: {}", parts[1]),
+                Err(e) => return Err(command_failed(format!("Failed to list media: {}", e))),
             }
         },
-        
-        // === MEDIA COMMANDS ===
-        "media" => {
-            if parts.len() < 2 {
-                println!("Please specify a media command: list, download, delete, deleteall, path");
-                return Ok(());
-            }
-            
-            match parts[1] {
-                "list" => {
-                    match drone.list_media() {
-                        Ok(files) => {
-                            println!("Media files on drone:");
-                            for file in files {
-                                println!("  {}", file);
-                            }
-                        },
-                        Err(e) => eprintln!("Failed to list media: {}", e),
-                    }
-                },
-                "download" => {
-                    if parts.len() < 3 {
-                        println!("Please specify a filename to download");
-                        return Ok(());
-                    }
-                    
-                    let filename = parts[2];
-                    match drone.download_media(filename) {
-                        Ok(result) => println!("{}", result),
-                        Err(e) => eprintln!("Failed to download media: {}", e),
-                    }
-                },
-                "direct" => {
-                    if parts.len() < 3 {
-                        println!("Please specify a filename for direct transfer");
-                        return Ok(());
-                    }
-                    
-                    let filename = parts[2];
-                    match drone.transfer_file_via_direct_connection(filename) {
-                        Ok(result) => println!("{}", result),
-                        Err(e) => eprintln!("Failed to transfer file: {}", e),
-                    }
-                },
-                "delete" => {
-                    if parts.len() < 3 {
-                        println!("Please specify a filename to delete");
-                        return Ok(());
-                    }
-                    
-                    let filename = parts[2];
-                    match drone.delete_media(filename) {
-                        Ok(result) => println!("{}", result),
-                        Err(e) => eprintln!("Failed to delete media: {}", e),
-                    }
-                },
-                "deleteall" => {
-                    match drone.delete_all_media() {
-                        Ok(result) => println!("{}", result),
-                        Err(e) => eprintln!("Failed to delete all media: {}", e),
-                    }
-                },
-                "path" => {
-                    if parts.len() < 3 {
-                        println!("Please specify a path for media downloads");
-                        return Ok(());
-                    }
-                    
-                    let path = parts[2];
-                    match drone.set_download_path(path) {
-                        Ok(_) => println!("Download path set to: {}", path),
-                        Err(e) => eprintln!("Failed to set download path: {}", e),
-                    }
-                },
-                _ => println!("Unknown media command: {}", parts[1]),
+        MediaCommand::Download(filename) => {
+            match drone.download_media(&filename) {
+                Ok(result) => println!("{}", result),
+                Err(e) => return Err(command_failed(format!("Failed to download media: {}", e))),
             }
         },
-        
-        // === POSITIONING COMMANDS ===
-        "position" => {
-            if parts.len() < 4 {
-                println!("Please specify all coordinates: position <x> <y> <z>");
-                return Ok(());
+        MediaCommand::DownloadAll => {
+            let downloads = drone.download_all_media()
+                .map_err(|e| command_failed(format!("Failed to list media: {}", e)))?;
+            for path in &downloads.saved {
+                println!("Downloaded to {}", path.display());
             }
-            
-            let x = match parts[1].parse::<f32>() {
-                Ok(val) => val,
-                Err(_) => {
-                    eprintln!("Invalid x-coordinate: {}", parts[1]);
-                    return Ok(());
-                }
-            };
-            
-            let y = match parts[2].parse::<f32>() {
-                Ok(val) => val,
-                Err(_) => {
-                    eprintln!("Invalid y-coordinate: {}", parts[2]);
-                    return Ok(());
-                }
-            };
-            
-            let z = match parts[3].parse::<f32>() {
-                Ok(val) => val,
-                Err(_) => {
-                    eprintln!("Invalid z-coordinate: {}", parts[3]);
-                    return Ok(());
+            for (filename, e) in &downloads.failed {
+                println!("Failed to download {}: {}", filename, e);
+            }
+            if !downloads.failed.is_empty() {
+                return Err(command_failed(format!("{} of {} files failed to download",
+                    downloads.failed.len(), downloads.saved.len() + downloads.failed.len())));
+            }
+        },
+        MediaCommand::Direct(filename) => {
+            let result = drone.download_media_with_progress(&filename, |copied, total| {
+                match total {
+                    Some(total) if total > 0 => print!("\rReceived {} of {} bytes ({}%)", copied, total, copied * 100 / total),
+                    _ => print!("\rReceived {} bytes", copied),
                 }
-            };
-            
-            drone.set_position(x, y, z);
-            println!("Drone position set to ({}, {}, {})", x, y, z);
+                let _ = io::stdout().flush();
+            });
+            println!();
+            match result {
+                Ok(bytes) => println!("Transferred {} bytes of {}", bytes, filename),
+                Err(e) => return Err(command_failed(format!("Failed to transfer file: {}", e))),
+            }
         },
-        "get_position" => {
-            let pos = drone.get_position();
-            println!("Current drone position: ({:.2}, {:.2}, {:.2})", pos.x, pos.y, pos.z);
+        MediaCommand::Delete(filename) => {
+            match drone.delete_media(&filename) {
+                Ok(result) => println!("{}", result),
+                Err(e) => return Err(command_failed(format!("Failed to delete media: {}", e))),
+            }
         },
-        "camera_to_center" => {
-            if parts.len() < 3 {
-                println!("Please specify center coordinates: camera_to_center <x> <y>");
-                return Ok(());
+        MediaCommand::DeleteAll => {
+            match drone.delete_all_media() {
+                Ok(result) => println!("{}", result),
+                Err(e) => return Err(command_failed(format!("Failed to delete all media: {}", e))),
             }
-            
-            let x = match parts[1].parse::<f32>() {
-                Ok(val) => val,
-                Err(_) => {
-                    eprintln!("Invalid x-coordinate: {}", parts[1]);
-                    return Ok(());
-                }
-            };
-            
-            let y = match parts[2].parse::<f32>() {
-                Ok(val) => val,
-                Err(_) => {
-                    eprintln!("Invalid y-coordinate: {}", parts[2]);
-                    return Ok(());
-                }
-            };
-            
-            match drone.point_camera_to_center(x, y) {
-                Ok(_) => println!("Camera pointed towards center point ({}, {})", x, y),
-                Err(e) => eprintln!("Failed to point camera: {}", e),
+        },
+        MediaCommand::Path(path) => {
+            match drone.set_download_path(&path) {
+                Ok(_) => println!("Download path set to: {}", path),
+                Err(e) => return Err(command_failed(format!("Failed to set download path: {}", e))),
             }
         },
-        "camera_from_center" => {
-            if parts.len() < 3 {
-                println!("Please specify center coordinates: camera_from_center <x> <y>");
-                return Ok(());
+    }
+    
+    Ok(())
+}
+
+/// Print application, network and drone information
+fn print_info(drone: &Tello) {
+    // Application information
+    println!("=== APPLICATION INFORMATION ===");
+    println!("DJI Tello Controller Library");
+    println!("Version: {}", VERSION);
+    println!("Build date: {}", BUILD_DATE);
+    println!("Copyright (c) 2025 aleskin");
+    println!();
+    
+    // Network information
+    println!("=== NETWORK CONFIGURATION ===");
+    println!("Drone address: {}", drone.tello_addr());
+    println!("Command port: {} (UDP, on the drone)", drone.tello_addr().port());
+    println!("Response port: {} (UDP, local, commands are sent from it)", drone.local_port());
+    println!("State port: {} (UDP, local)", drone.state_port());
+    println!("Media port: {} (TCP/Direct)", drone.file_transfer_port());
+    println!("Video port: {} (UDP)", drone.video_port());
+    println!();
+    
+    // Drone information
+    println!("=== DRONE INFORMATION ===");
+    
+    // Queries the drone didn't answer are shown as unavailable
+    let queries = drone.query_all();
+    match drone.model() {
+        Some(model) => println!("Model: {}", model),
+        None => println!("Model: Not detected"),
+    }
+    for (query, label, unit) in [
+        ("sdk", "SDK version", ""),
+        ("sn", "Serial number", ""),
+        ("hardware", "Hardware version", ""),
+        ("version", "Firmware version", ""),
+        ("battery", "Battery level", "%"),
+        ("wifi", "WiFi SNR", ""),
+    ] {
+        match queries.get(query) {
+            Some(answer) => println!("{}: {}{}", label, answer, unit),
+            None => println!("{}: Unable to retrieve", label),
+        }
+    }
+    
+    // Display full state information if available
+    if let Some(state) = drone.get_state() {
+        println!("\n=== DETAILED STATE INFORMATION ===");
+        
+        // Parse and display the state in a more readable format
+        let state_pairs: Vec<&str> = state.split(';').collect();
+        
+        // Sort and display by categories
+        let mut battery_info = Vec::new();
+        let mut flight_info = Vec::new();
+        let mut position_info = Vec::new();
+        let mut other_info = Vec::new();
+        
+        for pair in state_pairs {
+            if pair.is_empty() {
+                continue;
             }
             
-            let x = match parts[1].parse::<f32>() {
-                Ok(val) => val,
-                Err(_) => {
-                    eprintln!("Invalid x-coordinate: {}", parts[1]);
-                    return Ok(());
-                }
-            };
-            
-            let y = match parts[2].parse::<f32>() {
-                Ok(val) => val,
-                Err(_) => {
-                    eprintln!("Invalid y-coordinate: {}", parts[2]);
-                    return Ok(());
-                }
-            };
-            
-            match drone.point_camera_from_center(x, y) {
-                Ok(_) => println!("Camera pointed away from center point ({}, {})", x, y),
-                Err(e) => eprintln!("Failed to point camera: {}", e),
+            if pair.contains("bat") || pair.contains("temp") {
+                battery_info.push(pair);
+            } else if pair.contains("pitch") || pair.contains("roll") || pair.contains("yaw") || 
+                      pair.contains("vel") || pair.contains("spd") {
+                flight_info.push(pair);
+            } else if pair.contains("x") || pair.contains("y") || pair.contains("z") || 
+                      pair.contains("h") || pair.contains("tof") {
+                position_info.push(pair);
+            } else {
+                other_info.push(pair);
             }
-        },
-        _ => {
-            println!("Unknown command: {}. Type 'help' for available commands.", parts[0]);
         }
+        
+        if !battery_info.is_empty() {
+            println!("Battery & Temperature:");
+            for info in battery_info {
+                println!("  {}", info);
+            }
+        }
+        
+        if !flight_info.is_empty() {
+            println!("Flight Parameters:");
+            for info in flight_info {
+                println!("  {}", info);
+            }
+        }
+        
+        if !position_info.is_empty() {
+            println!("Position Information:");
+            for info in position_info {
+                println!("  {}", info);
+            }
+        }
+        
+        if !other_info.is_empty() {
+            println!("Other Information:");
+            for info in other_info {
+                println!("  {}", info);
+            }
+        }
+    } else {
+        println!("\nReal-time state information unavailable. Make sure the drone is connected.");
     }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tello::mock::mock_tello;
     
-    Ok(())
-}
\ No newline at end of file
+    #[test]
+    fn test_parse_script() {
+        let script = "# Simple flight\ntakeoff; wait 1; land\n\n  # indented comment\nphoto\n";
+        
+        assert_eq!(parse_script(script), vec!["takeoff", "wait 1", "land", "photo"]);
+    }
+    
+    #[test]
+    fn test_run_script_stops_on_error() {
+        let path = std::env::temp_dir().join(format!("tello_script_stop_{}.txt", std::process::id()));
+        std::fs::write(&path, "# comment\nforward abc; land\n").unwrap();
+        
+        // The invalid distance must stop the script before "land" is sent
+        let mut drone = Tello::new().unwrap();
+        let result = run_script(&mut drone, path.to_str().unwrap(), false, &ExecOptions::default());
+        std::fs::remove_file(&path).unwrap();
+        
+        let message = result.unwrap_err().to_string();
+        assert!(message.contains("Script stopped at 'forward abc'"), "{}", message);
+    }
+    
+    #[test]
+    fn test_run_script_continue() {
+        let path = std::env::temp_dir().join(format!("tello_script_continue_{}.txt", std::process::id()));
+        std::fs::write(&path, "forward abc\nposition 1 2 3\n").unwrap();
+        
+        // With --continue the remaining commands still run
+        let mut drone = Tello::new().unwrap();
+        let result = run_script(&mut drone, path.to_str().unwrap(), true, &ExecOptions::default());
+        std::fs::remove_file(&path).unwrap();
+        
+        assert_eq!(result.unwrap_err().to_string(), "1 of 2 script commands failed");
+        assert_eq!(drone.get_position().x, 1.0);
+    }
+    
+    #[test]
+    fn test_run_script_nesting() {
+        let path = std::env::temp_dir().join(format!("tello_script_nested_{}.txt", std::process::id()));
+        std::fs::write(&path, format!("script {}\n", path.display())).unwrap();
+        
+        // A script running itself stops at the depth limit instead of overflowing the stack
+        let mut drone = Tello::new().unwrap();
+        let result = run_script(&mut drone, path.to_str().unwrap(), false, &ExecOptions::default());
+        std::fs::remove_file(&path).unwrap();
+        
+        let message = result.unwrap_err().to_string();
+        assert!(message.contains("Scripts nested more than 8 deep"), "{}", message);
+    }
+    
+    #[test]
+    fn test_run_commands() {
+        // Commands after "exit" are not run and the batch still succeeds
+        let mut drone = Tello::new().unwrap();
+        run_commands(&mut drone, "position 1 2 3; exit; position 4 5 6", &ExecOptions::default()).unwrap();
+        assert_eq!(drone.get_position().x, 1.0);
+        
+        // A failing command fails the batch
+        let result = run_commands(&mut drone, "forward abc; position 4 5 6", &ExecOptions::default());
+        assert!(result.unwrap_err().to_string().contains("Batch stopped at 'forward abc'"));
+        assert_eq!(drone.get_position().x, 1.0);
+    }
+    
+    #[test]
+    fn test_aliases() {
+        let mut aliases = Aliases::default();
+        aliases.define("side", "forward 50; rotate_cw 90").unwrap();
+        aliases.define("lap", "side; side; side; side").unwrap();
+        
+        // Nested aliases expand fully, other commands pass through
+        let expanded = aliases.expand("lap").unwrap();
+        assert_eq!(expanded.len(), 8);
+        assert_eq!(expanded[..2], ["forward 50", "rotate_cw 90"]);
+        assert_eq!(aliases.expand("land").unwrap(), vec!["land"]);
+        assert!(aliases.expand("side 2").is_err());
+        
+        // Built-in commands can't be redefined
+        assert!(aliases.define("land", "emergency").is_err());
+        assert!(aliases.define("bad name", "land").is_err());
+        
+        // A cycle is rejected and the previous definition kept
+        let message = aliases.define("side", "lap").unwrap_err().to_string();
+        assert_eq!(message, "Recursive alias: side -> lap -> side");
+        assert_eq!(aliases.expand("side").unwrap(), vec!["forward 50", "rotate_cw 90"]);
+        
+        // Saved aliases load again
+        let path = std::env::temp_dir().join(format!("tello_aliases_{}", std::process::id()));
+        aliases.save(&path).unwrap();
+        let loaded = Aliases::load(&path);
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(loaded.names(), vec!["lap", "side"]);
+        assert_eq!(loaded.expand("lap").unwrap(), expanded);
+    }
+    
+    #[test]
+    fn test_repeat() {
+        let (mut drone, mock) = mock_tello();
+        mock.set_response("forward 20", "ok");
+        
+        run_commands(&mut drone, "repeat 3 forward 20", &ExecOptions::default()).unwrap();
+        assert_eq!(mock.get_commands(), vec!["forward 20"; 3]);
+        
+        // Nothing to do, and invalid counts
+        run_commands(&mut drone, "repeat 0 forward 20", &ExecOptions::default()).unwrap();
+        assert!(run_commands(&mut drone, "repeat -1 forward 20", &ExecOptions::default()).is_err());
+        assert!(run_commands(&mut drone, "repeat many forward 20", &ExecOptions::default()).is_err());
+        assert_eq!(mock.get_commands().len(), 3);
+        
+        // Semicolons split the repeated commands, and exit leaves at once
+        let (count, commands) = parse_repeat("2 position 1 2 3; exit").unwrap();
+        assert_eq!(count, 2);
+        assert_eq!(commands, vec!["position 1 2 3", "exit"]);
+        let result = run_repeat(&mut drone, "2 position 1 2 3; exit", &Aliases::default(), &CommandDelay::new(), &ExecOptions::default());
+        assert!(is_exit_request(&result.unwrap_err()));
+    }
+    
+    #[test]
+    fn test_sequence() {
+        let steps = parse_sequence("forward 50 @2, rotate_cw 90 @1.5").unwrap();
+        assert_eq!(steps, vec![
+            (String::from("forward 50"), Some(Duration::from_secs(2))),
+            (String::from("rotate_cw 90"), Some(Duration::from_millis(1500))),
+        ]);
+        assert_eq!(parse_sequence("land").unwrap(), vec![(String::from("land"), None)]);
+        
+        // A bad wait stops the whole sequence before anything is sent
+        let (mut drone, mock) = mock_tello();
+        mock.set_response("forward 50", "ok");
+        assert!(run_commands(&mut drone, "sequence forward 50 @0, land @soon", &ExecOptions::default()).is_err());
+        assert!(run_commands(&mut drone, "sequence forward 50 @-1", &ExecOptions::default()).is_err());
+        assert!(run_commands(&mut drone, "sequence @1", &ExecOptions::default()).is_err());
+        assert!(mock.get_commands().is_empty());
+        
+        run_commands(&mut drone, "sequence forward 50 @0, forward 50 @0", &ExecOptions::default()).unwrap();
+        assert_eq!(mock.get_commands(), vec!["forward 50"; 2]);
+    }
+    
+    #[test]
+    fn test_save_session_history() {
+        let path = std::env::temp_dir().join(format!("tello_history_{}.txt", std::process::id()));
+        let session = vec![String::from("takeoff"), String::from("forward 50; land ")];
+        
+        save_session_history(&path, &session).unwrap();
+        let contents = fs::read_to_string(&path).unwrap();
+        assert_eq!(contents.lines().count(), session.len());
+        assert_eq!(contents, "takeoff\nforward 50; land\n");
+        
+        // The saved session runs as a script
+        assert_eq!(parse_script(&contents), vec!["takeoff", "forward 50", "land"]);
+        let _ = fs::remove_file(path);
+    }
+    
+    #[test]
+    fn test_record() {
+        let path = std::env::temp_dir().join(format!("tello_record_{}.txt", std::process::id()));
+        let path_arg = path.to_str().unwrap();
+        let mut drone = Tello::new().unwrap();
+        let delays = CommandDelay::new();
+        let mut recorder = None;
+        
+        // Nothing is recorded before "record start"
+        execute_recorded(&mut drone, &["position", "0", "0", "0"], &delays, &mut recorder, &ExecOptions::default()).unwrap();
+        assert!(execute_record_command(&mut recorder, &["stop"]).is_err());
+        
+        execute_record_command(&mut recorder, &["start", path_arg]).unwrap();
+        assert!(execute_record_command(&mut recorder, &["start", path_arg]).is_err());
+        execute_recorded(&mut drone, &["position", "1", "2", "3"], &delays, &mut recorder, &ExecOptions::default()).unwrap();
+        // Failed commands are left out of the recording
+        assert!(execute_recorded(&mut drone, &["forward", "0"], &delays, &mut recorder, &ExecOptions::default()).is_err());
+        execute_recorded(&mut drone, &["direction", "90"], &delays, &mut recorder, &ExecOptions::default()).unwrap();
+        execute_record_command(&mut recorder, &["stop"]).unwrap();
+        assert!(recorder.is_none());
+        
+        let contents = fs::read_to_string(&path).unwrap();
+        let _ = fs::remove_file(&path);
+        let lines: Vec<&str> = contents.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].starts_with("position 1 2 3"));
+        assert_eq!(lines[1], "direction 90");
+    }
+    
+    #[test]
+    fn test_recorded_pauses() {
+        let mut recorder = Recorder::new("flight.txt");
+        let start = Instant::now();
+        recorder.push(String::from("takeoff"), start, start + Duration::from_secs(5));
+        recorder.push(String::from("forward 50"), start + Duration::from_millis(7500), start + Duration::from_secs(9));
+        recorder.push(String::from("land"), start + Duration::from_millis(9050), start + Duration::from_secs(12));
+        
+        // Pauses under 0.1 s are not worth a wait
+        assert_eq!(recorder.to_script(), "takeoff; wait 2.5\nforward 50\nland\n");
+    }
+    
+    #[test]
+    fn test_conditional() {
+        let mut drone = Tello::new().unwrap();
+        let parse_if = |line: &str| match parse_command(&line.split_whitespace().collect::<Vec<&str>>()) {
+            Ok(Command::If { condition, then }) => (condition, then),
+            other => panic!("Not an if: {:?}", other),
+        };
+        let low = TelemetryState::parse("pitch:0;roll:0;yaw:0;h:80;bat:15;temph:70;");
+        let full = TelemetryState::parse("pitch:0;roll:0;yaw:0;h:80;bat:90;temph:70;");
+        
+        // The then-command only runs while the condition holds
+        let (condition, then) = parse_if("if battery < 20 then position 1 2 3");
+        run_conditional(&mut drone, &condition, &then, Some(&full), &ExecOptions::default()).unwrap();
+        assert_eq!(drone.get_position().x, 0.0);
+        run_conditional(&mut drone, &condition, &then, Some(&low), &ExecOptions::default()).unwrap();
+        assert_eq!(drone.get_position().x, 1.0);
+        
+        let (condition, then) = parse_if("if height == 80 then position 4 5 6");
+        run_conditional(&mut drone, &condition, &then, Some(&full), &ExecOptions::default()).unwrap();
+        assert_eq!(drone.get_position().x, 4.0);
+        
+        // Without the value nothing runs and the script stops
+        let (condition, then) = parse_if("if temp > 60 then position 7 8 9");
+        assert!(run_conditional(&mut drone, &condition, &then, None, &ExecOptions::default()).is_err());
+        let no_temp = TelemetryState::parse("pitch:0;roll:0;yaw:0;h:80;bat:90;");
+        assert!(run_conditional(&mut drone, &condition, &then, Some(&no_temp), &ExecOptions::default()).is_err());
+        assert_eq!(drone.get_position().x, 4.0);
+    }
+    
+    #[test]
+    fn test_prompt() {
+        let state = TelemetryState::parse("pitch:0;roll:0;yaw:0;h:120;bat:72;");
+        assert_eq!(Prompt::default().render(Some(&state)), "> ");
+        
+        let prompt = Prompt { template: String::from("[{id} bat {battery}% {height} cm] > "), id: Some(String::from("0TQZH77")) };
+        assert_eq!(prompt.render(Some(&state)), "[0TQZH77 bat 72% 120 cm] > ");
+        
+        // Unknown values show as "?"
+        let prompt = Prompt { template: String::from("[bat {battery}%] {id}> "), id: None };
+        assert_eq!(prompt.render(None), "[bat ?%] ?> ");
+        
+        let mut drone = Tello::new().unwrap();
+        let mut prompt = Prompt::default();
+        execute_prompt_command(&mut prompt, &mut drone, "[bat {battery}%] >").unwrap();
+        assert_eq!(prompt.render(Some(&state)), "[bat 72%] > ");
+        
+        // A typo is refused and the prompt kept
+        let message = execute_prompt_command(&mut prompt, &mut drone, "{batery} > ").unwrap_err().to_string();
+        assert!(message.contains("Unknown placeholder {batery}"));
+        assert_eq!(prompt.render(Some(&state)), "[bat 72%] > ");
+        
+        execute_prompt_command(&mut prompt, &mut drone, "reset").unwrap();
+        assert_eq!(prompt.render(Some(&state)), "> ");
+    }
+    
+    #[test]
+    fn test_countdown() {
+        let mut steps = Vec::new();
+        countdown("Waiting", Duration::from_millis(2500), |slice| {
+            steps.push(slice);
+            Ok(())
+        }).unwrap();
+        
+        // Whole seconds first, the fraction last
+        assert_eq!(steps, vec![Duration::from_secs(1), Duration::from_secs(1), Duration::from_millis(500)]);
+        
+        // A failing step ends the countdown
+        let mut calls = 0;
+        let result = countdown("Hovering", Duration::from_secs(5), |_| {
+            calls += 1;
+            Err(command_failed(String::from("Hover failed")))
+        });
+        assert!(result.is_err());
+        assert_eq!(calls, 1);
+    }
+    
+    #[test]
+    fn test_raw_command() {
+        let (mut drone, mock) = mock_tello();
+        mock.set_response("foo bar", "unknown command: foo");
+        mock.set_response("rc 0 0 0 0", "error Not joystick");
+        
+        // Refused unless expert mode is on
+        assert!(execute_command(&mut drone, &["raw", "foo", "bar"], &ExecOptions::default()).is_err());
+        assert!(mock.get_commands().is_empty());
+        
+        // Error replies are printed, not turned into a failure
+        set_expert_mode(true);
+        let result = execute_command(&mut drone, &["raw", "foo", "bar"], &ExecOptions::default())
+            .and_then(|_| execute_command(&mut drone, &["raw", "rc", "0", "0", "0", "0"], &ExecOptions::default()));
+        set_expert_mode(false);
+        result.unwrap();
+        assert_eq!(mock.get_commands(), vec!["foo bar", "rc 0 0 0 0"]);
+    }
+    
+    #[test]
+    fn test_execute_checks_ranges() {
+        let (mut drone, mock) = mock_tello();
+        
+        // A command built without parse_command is checked as well
+        assert!(execute(&mut drone, Command::Forward(9999), &ExecOptions::default()).is_err());
+        assert!(execute(&mut drone, Command::Takeoff(Some(12.0)), &ExecOptions::default()).is_err());
+        assert!(mock.get_commands().is_empty());
+    }
+    
+    #[test]
+    fn test_figure_eight_command() {
+        let (mut drone, mock) = mock_tello();
+        let curves = ["curve 100 -100 0 0 -200 0 50", "curve -100 100 0 0 200 0 50",
+                      "curve 100 100 0 0 200 0 50", "curve -100 -100 0 0 -200 0 50"];
+        for command in curves {
+            mock.set_response(command, "ok");
+        }
+        
+        // Without a speed the default is used, capped at the 60 cm/s of curves
+        execute_command(&mut drone, &["eight", "100"], &ExecOptions::default()).unwrap();
+        assert_eq!(mock.get_commands(), curves);
+        
+        // An out of range radius or a missing one sends nothing
+        assert!(execute_command(&mut drone, &["eight", "30"], &ExecOptions::default()).is_err());
+        assert!(execute_command(&mut drone, &["eight"], &ExecOptions::default()).is_err());
+        assert_eq!(mock.get_commands().len(), 4);
+    }
+    
+    #[test]
+    fn test_reboot_command() {
+        let (mut drone, mock) = mock_tello();
+        
+        execute_command(&mut drone, &["reboot", "--yes"], &ExecOptions::default()).unwrap();
+        assert_eq!(mock.get_commands(), vec!["reboot"]);
+        
+        // Nobody can answer the question in a batch run
+        let result = run_commands(&mut drone, "reboot", &ExecOptions::default());
+        assert!(result.unwrap_err().to_string().contains("reboot --yes"));
+        assert_eq!(mock.get_commands(), vec!["reboot"]);
+    }
+    
+    #[test]
+    fn test_parse_args() {
+        let args = |list: &[&str]| list.iter().map(|arg| arg.to_string()).collect::<Vec<String>>();
+        
+        assert_eq!(parse_args(&args(&[])).unwrap().mode, RunMode::Interactive);
+        assert_eq!(parse_args(&args(&["--exec", "takeoff; land"])).unwrap().mode,
+            RunMode::Exec(String::from("takeoff; land")));
+        assert_eq!(parse_args(&args(&["--continue", "-s", "flight.txt"])).unwrap().mode,
+            RunMode::Script { path: String::from("flight.txt"), continue_on_error: true });
+        assert_eq!(parse_args(&args(&["--help"])).unwrap().mode, RunMode::Help);
+        
+        let options = parse_args(&args(&["--json", "--exec", "state"])).unwrap();
+        assert!(options.json);
+        assert_eq!(options.mode, RunMode::Exec(String::from("state")));
+        assert!(!options.dry_run);
+        assert!(parse_args(&args(&["--dry-run"])).unwrap().dry_run);
+        assert!(!options.require_arming);
+        assert!(parse_args(&args(&["--require-arming"])).unwrap().require_arming);
+        assert!(!options.expert);
+        assert!(parse_args(&args(&["--expert"])).unwrap().expert);
+        assert_eq!(options.verbosity, Level::Quiet);
+        assert_eq!(parse_args(&args(&["-v"])).unwrap().verbosity, Level::Debug);
+        assert_eq!(parse_args(&args(&["--verbosity", "info"])).unwrap().verbosity, Level::Info);
+        assert!(parse_args(&args(&["--verbosity", "loud"])).is_err());
+        assert_eq!(options.abort_key, Some(KeyEvent(KeyCode::Esc, Modifiers::NONE)));
+        assert_eq!(parse_args(&args(&["--abort-key", "none"])).unwrap().abort_key, None);
+        
+        assert!(parse_args(&args(&["--exec"])).is_err());
+        assert!(parse_args(&args(&["--bogus"])).is_err());
+        assert!(parse_args(&args(&["--exec", "land", "--continue"])).is_err());
+    }
+    
+    #[test]
+    fn test_move_delay() {
+        let delays = CommandDelay::new();
+        
+        // 200 cm at 50 cm/s takes about 4 s
+        let eta = estimate_move_time(200, 50);
+        assert!(eta >= Duration::from_secs(4) && eta <= Duration::from_secs(5));
+        assert_eq!(delays.delay_for(&["forward", "200"], Some(50)), eta.as_millis() as u64);
+        assert!(delays.delay_for(&["forward", "50"], Some(100)) < delays.delay_for(&["forward", "50"], Some(10)));
+        assert_eq!(delays.delay_for(&["go", "300", "0", "400", "50"], None), estimate_move_time(500, 50).as_millis() as u64);
+        
+        // Without a known speed the registry delay is used
+        assert_eq!(delays.delay_for(&["forward", "200"], None), delays.get_delay("forward"));
+        assert_eq!(delays.delay_for(&["takeoff"], Some(50)), delays.get_delay("takeoff"));
+    }
+    
+    #[test]
+    fn test_parse_abort_key() {
+        assert_eq!(parse_abort_key("Esc").unwrap(), Some(KeyEvent(KeyCode::Esc, Modifiers::NONE)));
+        assert_eq!(parse_abort_key("f12").unwrap(), Some(KeyEvent(KeyCode::F(12), Modifiers::NONE)));
+        assert_eq!(parse_abort_key("ctrl-x").unwrap(), Some(KeyEvent(KeyCode::Char('X'), Modifiers::CTRL)));
+        assert_eq!(parse_abort_key("none").unwrap(), None);
+        assert_eq!(key_name(parse_abort_key("ctrl-x").unwrap().unwrap()), "Ctrl-X");
+        
+        for invalid in ["ctrl-c", "ctrl-d", "f13", "ctrl-xy", "space"] {
+            assert!(parse_abort_key(invalid).is_err(), "{}", invalid);
+        }
+    }
+    
+    #[test]
+    fn test_info_json() {
+        // Without a connection the drone queries are null but the keys are present
+        let drone = Tello::new().unwrap();
+        let info = info_json(&drone);
+        
+        assert_eq!(info["version"], VERSION);
+        assert_eq!(info["build_date"], BUILD_DATE);
+        assert_eq!(info["network"]["command_port"], 8889);
+        assert_eq!(info["network"]["response_port"], 9000);
+        assert_eq!(info["network"]["state_port"], 8890);
+        for key in ["sdk_version", "serial_number", "hardware_version", "firmware_version", "battery", "wifi_snr"] {
+            assert!(info["drone"].get(key).unwrap().is_null(), "{}", key);
+        }
+        assert!(info["state"].is_null());
+    }
+    
+    #[test]
+    fn test_state_json() {
+        let state = crate::telemetry::TelemetryState::parse("mid:1;x:10;y:-5;z:80;bat:87;baro:1.5;");
+        let value = json!(state);
+        
+        assert_eq!(value["mid"], 1);
+        assert_eq!(value["y"], -5);
+        assert_eq!(value["bat"], 87);
+        assert_eq!(value["baro"], 1.5);
+        assert!(value["pitch"].is_null());
+    }
+}
@@ -0,0 +1,156 @@
+/*
+ * DJI Tello Drone Controller Library
+ *
+ * Copyright (c) 2025 aleskin
+ *
+ * This file is part of dji_tello_lib.
+ *
+ * dji_tello_lib is free software: you can redistribute it and/or modify
+ * it under the terms of the MIT License as published.
+ *
+ * Created: March 30, 2025
+ */
+
+// Settings kept between sessions in a TOML file
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use serde::Deserialize;
+use crate::error::{TelloError, TelloResult};
+use crate::tello::{Tello, TelloBuilder};
+
+/// Settings read from `~/.tello.toml`
+///
+/// Every setting is optional; one left out keeps the library default.
+///
+/// ```toml
+/// drone_ip = "192.168.10.1"
+/// bind_address = "192.168.10.2"
+/// download_path = "~/tello_media"
+/// min_takeoff_battery = 20
+/// max_altitude = 250
+/// speed = 50
+/// ```
+#[derive(Debug, Default, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TelloConfig {
+    /// IP address of the drone
+    pub drone_ip: Option<String>,
+    /// Local IP address of the interface facing the drone
+    pub bind_address: Option<String>,
+    /// Where media downloads are saved, `~` and variables are expanded
+    pub download_path: Option<String>,
+    /// Takeoff is refused below this battery level in percent
+    pub min_takeoff_battery: Option<u8>,
+    /// Altitude ceiling in cm
+    pub max_altitude: Option<i32>,
+    /// Flight speed in cm/s (10-100), set after connecting
+    pub speed: Option<i32>,
+}
+
+impl TelloConfig {
+    /// The config file in the home directory, `~/.tello.toml`
+    pub fn default_path() -> PathBuf {
+        let mut path = dirs::home_dir().unwrap_or_else(|| PathBuf::from("."));
+        path.push(".tello.toml");
+        path
+    }
+    
+    /// Read the settings from a file, a missing file gives the defaults
+    pub fn load(path: &Path) -> TelloResult<TelloConfig> {
+        match fs::read_to_string(path) {
+            Ok(text) => Self::parse(&text).map_err(|e| TelloError::InvalidParameter(
+                format!("{}: {}", path.display(), e),
+            )),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(TelloConfig::default()),
+            Err(e) => Err(e.into()),
+        }
+    }
+    
+    /// Parse settings from TOML text; unknown keys are an error
+    pub fn parse(text: &str) -> TelloResult<TelloConfig> {
+        toml::from_str(text).map_err(|e| TelloError::InvalidParameter(format!("Invalid config: {}", e)))
+    }
+    
+    /// A builder with the drone and bind addresses of these settings
+    pub fn builder(&self) -> TelloBuilder {
+        let mut builder = Tello::builder();
+        if let Some(ip) = &self.drone_ip {
+            builder = builder.ip(ip);
+        }
+        if let Some(address) = &self.bind_address {
+            builder = builder.bind_address(address);
+        }
+        builder
+    }
+    
+    /// Apply the settings that don't need a new connection
+    ///
+    /// The speed is sent to the drone, so call this after connecting.
+    pub fn apply(&self, drone: &mut Tello) -> TelloResult<()> {
+        if let Some(path) = &self.download_path {
+            drone.set_download_path(path)?;
+        }
+        if let Some(percent) = self.min_takeoff_battery {
+            drone.set_min_takeoff_battery(percent);
+        }
+        if let Some(cm) = self.max_altitude {
+            drone.set_max_altitude(cm);
+        }
+        if let Some(speed) = self.speed {
+            drone.set_speed(speed)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_parse_config() {
+        let config = TelloConfig::parse(r#"
+            drone_ip = "192.168.10.2"
+            bind_address = "192.168.10.3"
+            download_path = "~/tello_media"
+            min_takeoff_battery = 20
+            max_altitude = 250
+            speed = 50
+        "#).unwrap();
+        
+        assert_eq!(config, TelloConfig {
+            drone_ip: Some(String::from("192.168.10.2")),
+            bind_address: Some(String::from("192.168.10.3")),
+            download_path: Some(String::from("~/tello_media")),
+            min_takeoff_battery: Some(20),
+            max_altitude: Some(250),
+            speed: Some(50),
+        });
+        
+        // Left out settings keep the defaults
+        assert_eq!(TelloConfig::parse("speed = 30").unwrap().drone_ip, None);
+        assert_eq!(TelloConfig::parse("").unwrap(), TelloConfig::default());
+        
+        // Typos and wrong types are reported instead of ignored
+        assert!(TelloConfig::parse("sped = 30").is_err());
+        assert!(TelloConfig::parse("min_takeoff_battery = \"high\"").is_err());
+    }
+    
+    #[test]
+    fn test_load_config() {
+        let missing = std::env::temp_dir().join(format!("tello_missing_{}.toml", std::process::id()));
+        assert_eq!(TelloConfig::load(&missing).unwrap(), TelloConfig::default());
+        
+        let path = std::env::temp_dir().join(format!("tello_config_{}.toml", std::process::id()));
+        fs::write(&path, "drone_ip = \"127.0.0.1\"\nbind_address = \"127.0.0.1\"\nmax_altitude = 200\n").unwrap();
+        let config = TelloConfig::load(&path).unwrap();
+        let _ = fs::remove_file(&path);
+        
+        let mut drone = config.builder().build().unwrap();
+        assert_eq!(drone.tello_addr().ip().to_string(), "127.0.0.1");
+        assert_eq!(drone.bind_address().to_string(), "127.0.0.1");
+        config.apply(&mut drone).unwrap();
+        assert_eq!(drone.max_altitude(), Some(200));
+    }
+}
@@ -0,0 +1,107 @@
+/*
+ * DJI Tello Drone Controller Library
+ *
+ * Copyright (c) 2025 aleskin
+ *
+ * This file is part of dji_tello_lib.
+ *
+ * dji_tello_lib is free software: you can redistribute it and/or modify
+ * it under the terms of the MIT License as published.
+ *
+ * Created: March 30, 2025
+ */
+
+// Error type for Tello operations
+use std::error::Error;
+use std::fmt;
+use std::io;
+
+/// Errors that can occur while controlling the drone
+#[derive(Debug)]
+pub enum TelloError {
+    /// No connection to the drone has been established
+    NotConnected,
+    /// Socket or file system error
+    Io(io::Error),
+    /// The drone answered with something other than "ok"
+    CommandRejected(String),
+    /// The drone answered a query with a value that could not be parsed
+    InvalidResponse(String),
+    /// A parameter was out of range or malformed, nothing was sent
+    InvalidParameter(String),
+    /// The operation is not allowed in the current state (e.g. video already recording)
+    InvalidState(String),
+    /// The drone did not answer in time
+    Timeout,
+    /// The network to the drone is gone, e.g. the WiFi dropped
+    ConnectionLost,
+    /// The drone or this library cannot do what was asked
+    Unsupported(String),
+}
+
+/// Result type used by the Tello API
+pub type TelloResult<T> = Result<T, TelloError>;
+
+impl TelloError {
+    /// Whether the link to the drone may be down, so `Tello::reconnect` is worth a try
+    pub fn is_link_error(&self) -> bool {
+        matches!(self, TelloError::Timeout | TelloError::ConnectionLost)
+    }
+}
+
+impl fmt::Display for TelloError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TelloError::NotConnected => write!(f, "Drone not connected"),
+            TelloError::Io(e) => write!(f, "I/O error: {}", e),
+            TelloError::CommandRejected(response) => write!(f, "Command rejected by drone: {}", response),
+            TelloError::InvalidResponse(response) => write!(f, "Unexpected response from drone: {}", response),
+            TelloError::InvalidParameter(message) => write!(f, "{}", message),
+            TelloError::InvalidState(message) => write!(f, "{}", message),
+            TelloError::Timeout => write!(f, "Timed out waiting for drone response"),
+            TelloError::ConnectionLost => write!(f, "Connection to drone lost"),
+            TelloError::Unsupported(message) => write!(f, "Not supported: {}", message),
+        }
+    }
+}
+
+impl Error for TelloError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            TelloError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for TelloError {
+    fn from(e: io::Error) -> Self {
+        match e.kind() {
+            // Read timeouts surface as WouldBlock on Unix and TimedOut on Windows
+            io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut => TelloError::Timeout,
+            // Reported by send_to once the WiFi link is gone
+            io::ErrorKind::NetworkUnreachable
+            | io::ErrorKind::HostUnreachable
+            | io::ErrorKind::NetworkDown
+            | io::ErrorKind::AddrNotAvailable
+            | io::ErrorKind::ConnectionRefused => TelloError::ConnectionLost,
+            _ => TelloError::Io(e),
+        }
+    }
+}
+
+// Allows Tello calls inside functions that still return io::Result
+impl From<TelloError> for io::Error {
+    fn from(e: TelloError) -> Self {
+        match e {
+            TelloError::Io(e) => e,
+            TelloError::NotConnected => io::Error::new(io::ErrorKind::NotConnected, e.to_string()),
+            TelloError::InvalidParameter(_) => io::Error::new(io::ErrorKind::InvalidInput, e.to_string()),
+            TelloError::Timeout => io::Error::new(io::ErrorKind::TimedOut, e.to_string()),
+            TelloError::ConnectionLost => io::Error::new(io::ErrorKind::ConnectionAborted, e.to_string()),
+            TelloError::Unsupported(_) => io::Error::new(io::ErrorKind::Unsupported, e.to_string()),
+            TelloError::InvalidResponse(_) => io::Error::new(io::ErrorKind::InvalidData, e.to_string()),
+            TelloError::CommandRejected(_) | TelloError::InvalidState(_) => io::Error::other(e.to_string()),
+        }
+    }
+}
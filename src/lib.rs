@@ -0,0 +1,34 @@
+/*
+ * DJI Tello Drone Controller Library
+ *
+ * Copyright (c) 2025 aleskin
+ *
+ * This file is part of dji_tello_lib.
+ *
+ * dji_tello_lib is free software: you can redistribute it and/or modify
+ * it under the terms of the MIT License as published.
+ *
+ * Created: March 30, 2025
+ */
+
+pub mod error;
+pub mod tello;
+pub mod tello_movement;
+pub mod telemetry;
+pub mod media;
+pub mod swarm;
+pub mod transport;
+pub mod logging;
+pub mod config;
+pub mod command;
+pub mod command_line;
+
+pub use error::{TelloError, TelloResult};
+pub use tello::{CameraSelect, DroneIdentity, Position, Tello, TelloBuilder, TelloModel, TelloStatus, VideoFps, VideoResolution};
+pub use tello_movement::FlipDirection;
+pub use telemetry::{LogFormat, TelemetryState};
+pub use media::{MediaDownloads, MediaFile, MediaKind};
+pub use swarm::Swarm;
+pub use logging::Level;
+pub use config::TelloConfig;
+pub use transport::{CommandTransport, DryRunTransport, UdpTransport};
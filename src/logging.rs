@@ -0,0 +1,109 @@
+/*
+ * DJI Tello Drone Controller Library
+ *
+ * Copyright (c) 2025 aleskin
+ *
+ * This file is part of dji_tello_lib.
+ *
+ * dji_tello_lib is free software: you can redistribute it and/or modify
+ * it under the terms of the MIT License as published.
+ *
+ * Created: March 30, 2025
+ */
+
+// Diagnostic traces of the library, kept off stdout so command output stays clean
+use std::fmt;
+use std::io::{self, Write};
+use std::str::FromStr;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU8, Ordering};
+
+/// How much the library traces, see `Tello::set_verbosity`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+pub enum Level {
+    /// No traces at all
+    #[default]
+    Quiet,
+    /// Only warnings, such as clamped values or a lost link
+    Warn,
+    /// Connection and media transfer progress
+    Info,
+    /// Every command, response and retry as well
+    Debug,
+}
+
+impl FromStr for Level {
+    type Err = String;
+    
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "quiet" => Ok(Level::Quiet),
+            "warn" => Ok(Level::Warn),
+            "info" => Ok(Level::Info),
+            "debug" => Ok(Level::Debug),
+            _ => Err(format!("Invalid verbosity: {}. Use quiet, warn, info or debug", s)),
+        }
+    }
+}
+
+impl Level {
+    fn from_u8(value: u8) -> Self {
+        match value {
+            0 => Level::Quiet,
+            1 => Level::Warn,
+            2 => Level::Info,
+            _ => Level::Debug,
+        }
+    }
+}
+
+/// Writes trace lines at or below the configured level, to stderr by default
+///
+/// Shared with background threads through an `Arc`, so the level and writer
+/// can change while they run.
+pub(crate) struct Logger {
+    level: AtomicU8,
+    writer: Mutex<Box<dyn Write + Send>>,
+}
+
+impl Logger {
+    pub(crate) fn new(level: Level) -> Self {
+        Logger { level: AtomicU8::new(level as u8), writer: Mutex::new(Box::new(io::stderr())) }
+    }
+    
+    pub(crate) fn level(&self) -> Level {
+        Level::from_u8(self.level.load(Ordering::Relaxed))
+    }
+    
+    pub(crate) fn set_level(&self, level: Level) {
+        self.level.store(level as u8, Ordering::Relaxed);
+    }
+    
+    pub(crate) fn set_writer(&self, writer: Box<dyn Write + Send>) {
+        if let Ok(mut current) = self.writer.lock() {
+            *current = writer;
+        }
+    }
+    
+    pub(crate) fn warn(&self, message: fmt::Arguments) {
+        self.log(Level::Warn, format_args!("Warning: {}", message));
+    }
+    
+    pub(crate) fn info(&self, message: fmt::Arguments) {
+        self.log(Level::Info, message);
+    }
+    
+    pub(crate) fn debug(&self, message: fmt::Arguments) {
+        self.log(Level::Debug, message);
+    }
+    
+    fn log(&self, level: Level, message: fmt::Arguments) {
+        if level > self.level() {
+            return;
+        }
+        if let Ok(mut writer) = self.writer.lock() {
+            // Tracing must never interrupt the flight
+            let _ = writeln!(writer, "{}", message);
+        }
+    }
+}
@@ -11,29 +11,90 @@
  * Created: March 30, 2025
  */
 
-mod tello;
-mod tello_movement;
-mod command_line;
-
+use std::env;
 use std::io;
-use tello::Tello;
+use dji_tello_lib::command_line::{self, ExecOptions, RunMode};
+use dji_tello_lib::TelloConfig;
 
 fn main() -> io::Result<()> {
+    let args: Vec<String> = env::args().collect();
+    let program = args.first().map(String::as_str).unwrap_or("dji_tello_lib");
+    
+    let options = match command_line::parse_args(&args[1..]) {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("{}", e);
+            command_line::print_usage(program);
+            return Err(e);
+        }
+    };
+    
+    let mode = options.mode;
+    if mode == RunMode::Help {
+        command_line::print_usage(program);
+        return Ok(());
+    }
+    
+    // Settings from ~/.tello.toml, a missing file keeps the defaults
+    let config_path = TelloConfig::default_path();
+    let config = match TelloConfig::load(&config_path) {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("Failed to read settings: {}", e);
+            return Err(e.into());
+        }
+    };
+    
     // Initialize the drone connection
-    println!("Connecting to Tello drone...");
-    let mut drone = match Tello::new() {
+    let mut drone = match config.builder().build() {
         Ok(d) => d,
         Err(e) => {
             eprintln!("Failed to initialize drone connection: {}", e);
-            return Err(e);
+            return Err(e.into());
         }
     };
     
-    if let Err(e) = drone.connect() {
-        eprintln!("Failed to connect to drone: {}", e);
-        return Err(e);
+    drone.set_verbosity(options.verbosity);
+    let exec_options = ExecOptions { json: options.json, ..ExecOptions::default() };
+    
+    if options.dry_run {
+        eprintln!("Dry run: commands are printed to stderr, not sent");
+        drone.set_dry_run(true);
+    } else {
+        exec_options.progress(format_args!("Connecting to Tello drone..."));
+        if let Err(e) = drone.connect() {
+            eprintln!("Failed to connect to drone: {}", e);
+            return Err(e.into());
+        }
+        
+        // Knowing the model lets unsupported commands be refused instead of failing silently
+        match drone.detect_model() {
+            Ok(model) => exec_options.progress(format_args!("Connected to a {}", model)),
+            Err(e) => eprintln!("Warning: Failed to detect the drone model: {}", e),
+        }
+    }
+    
+    // The speed is sent to the drone, so the settings are applied once connected
+    if let Err(e) = config.apply(&mut drone) {
+        eprintln!("Warning: Failed to apply settings from {}: {}", config_path.display(), e);
     }
     
-    // Run the interactive command line interface
-    command_line::run_command_line(drone)
+    if options.require_arming {
+        drone.set_require_arming(true);
+        exec_options.progress(format_args!("Disarmed: use 'arm' before takeoff"));
+    }
+    
+    if options.expert {
+        command_line::set_expert_mode(true);
+    }
+    
+    // Batch modes run their commands and exit, a failure gives a non-zero exit code
+    let result = match mode {
+        RunMode::Exec(commands) => command_line::run_commands(&mut drone, &commands, &exec_options),
+        RunMode::Script { path, continue_on_error } => command_line::run_script(&mut drone, &path, continue_on_error, &exec_options),
+        RunMode::Interactive | RunMode::Help => return command_line::run_command_line(drone, options.abort_key, &exec_options),
+    };
+    
+    drone.disconnect();
+    result
 }
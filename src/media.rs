@@ -0,0 +1,106 @@
+/*
+ * DJI Tello Drone Controller Library
+ *
+ * Copyright (c) 2025 aleskin
+ *
+ * This file is part of dji_tello_lib.
+ *
+ * dji_tello_lib is free software: you can redistribute it and/or modify
+ * it under the terms of the MIT License as published.
+ *
+ * Created: March 30, 2025
+ */
+
+// Parsing of the media listing returned by "ls"
+use std::fmt;
+use std::path::{Path, PathBuf};
+use crate::error::TelloError;
+
+/// What a media file on the drone contains, inferred from its extension
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MediaKind {
+    Photo,
+    Video,
+    Other,
+}
+
+impl MediaKind {
+    /// Infer the kind from a file name such as "DJI_0001.jpg"
+    pub fn from_name(name: &str) -> Self {
+        let extension = Path::new(name)
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .map(|extension| extension.to_ascii_lowercase());
+        
+        match extension.as_deref() {
+            Some("jpg" | "jpeg" | "png" | "dng") => MediaKind::Photo,
+            Some("mp4" | "mov" | "h264") => MediaKind::Video,
+            _ => MediaKind::Other,
+        }
+    }
+}
+
+impl fmt::Display for MediaKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MediaKind::Photo => write!(f, "photo"),
+            MediaKind::Video => write!(f, "video"),
+            MediaKind::Other => write!(f, "other"),
+        }
+    }
+}
+
+/// One file in the drone's media listing
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MediaFile {
+    pub name: String,
+    pub kind: MediaKind,
+    /// Size in bytes, if the listing includes it
+    pub size: Option<u64>,
+}
+
+impl MediaFile {
+    /// Parse a listing line such as "DJI_0001.jpg" or "DJI_0001.jpg 123456"
+    ///
+    /// Returns `None` for empty lines and the trailing "ok".
+    pub fn parse(line: &str) -> Option<Self> {
+        let mut fields = line.split_whitespace();
+        let name = fields.next().filter(|name| *name != "ok")?;
+        let size = fields.next().and_then(|size| size.parse().ok());
+        
+        Some(MediaFile {
+            name: name.to_string(),
+            kind: MediaKind::from_name(name),
+            size,
+        })
+    }
+}
+
+/// Outcome of `Tello::download_all_media`
+///
+/// A file that fails doesn't stop the batch; it is listed in `failed`
+/// with the error and the remaining files are still downloaded.
+#[derive(Debug, Default)]
+pub struct MediaDownloads {
+    /// Where each downloaded file was saved, in listing order
+    pub saved: Vec<PathBuf>,
+    /// Files that could not be downloaded, with the reason
+    pub failed: Vec<(String, TelloError)>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_parse_media_file() {
+        let file = MediaFile::parse("DJI_0003.MP4 1048576").unwrap();
+        assert_eq!(file.name, "DJI_0003.MP4");
+        assert_eq!(file.kind, MediaKind::Video);
+        assert_eq!(file.size, Some(1048576));
+        
+        assert_eq!(MediaFile::parse("notes.txt").unwrap().kind, MediaKind::Other);
+        assert_eq!(MediaFile::parse("ok"), None);
+        assert_eq!(MediaFile::parse("  "), None);
+    }
+}
@@ -0,0 +1,138 @@
+/*
+ * DJI Tello Drone Controller Library
+ *
+ * Copyright (c) 2025 aleskin
+ *
+ * This file is part of dji_tello_lib.
+ *
+ * dji_tello_lib is free software: you can redistribute it and/or modify
+ * it under the terms of the MIT License as published.
+ *
+ * Created: March 30, 2025
+ */
+
+// Several drones commanded together, e.g. Tello EDUs in station mode
+use std::thread;
+use crate::error::{TelloError, TelloResult};
+use crate::tello::Tello;
+
+/// A group of drones that receive the same commands at the same time
+///
+/// Each drone needs its own local ports, see `TelloBuilder`.
+#[derive(Default)]
+pub struct Swarm {
+    drones: Vec<Tello>,
+}
+
+impl Swarm {
+    pub fn new() -> Self {
+        Swarm { drones: Vec::new() }
+    }
+    
+    /// Add a connected drone to the swarm
+    pub fn add(&mut self, drone: Tello) {
+        self.drones.push(drone);
+    }
+    
+    /// Number of drones in the swarm
+    pub fn len(&self) -> usize {
+        self.drones.len()
+    }
+    
+    pub fn is_empty(&self) -> bool {
+        self.drones.is_empty()
+    }
+    
+    /// The drones in the order they were added
+    pub fn drones(&self) -> &[Tello] {
+        &self.drones
+    }
+    
+    pub fn drones_mut(&mut self) -> &mut [Tello] {
+        &mut self.drones
+    }
+    
+    /// Run `command` against every drone, each in its own thread
+    ///
+    /// Returns one result per drone, in the order they were added. A failing
+    /// drone does not stop the others. The closure is shared between the
+    /// threads, so it has to be `Fn + Sync` rather than `FnMut`.
+    pub fn broadcast<F, R>(&mut self, command: F) -> Vec<TelloResult<R>>
+    where
+        F: Fn(&mut Tello) -> TelloResult<R> + Sync,
+        R: Send,
+    {
+        let command = &command;
+        
+        thread::scope(|scope| {
+            let handles: Vec<_> = self.drones.iter_mut()
+                .map(|drone| scope.spawn(move || command(drone)))
+                .collect();
+            
+            handles.into_iter()
+                .map(|handle| handle.join().unwrap_or_else(|_| Err(TelloError::InvalidState(
+                    String::from("The command thread for this drone panicked"),
+                ))))
+                .collect()
+        })
+    }
+    
+    /// Take off with every drone
+    pub fn takeoff_all(&mut self) -> Vec<TelloResult<()>> {
+        self.broadcast(|drone| drone.takeoff(None))
+    }
+    
+    /// Land every drone
+    pub fn land_all(&mut self) -> Vec<TelloResult<()>> {
+        self.broadcast(|drone| drone.land())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tello::mock::{mock_tello, MockTransport};
+    
+    fn mock_swarm(size: usize) -> (Swarm, Vec<MockTransport>) {
+        let mut swarm = Swarm::new();
+        let mut mocks = Vec::new();
+        for _ in 0..size {
+            let (drone, mock) = mock_tello();
+            mock.set_response("battery?", "87");
+            swarm.add(drone);
+            mocks.push(mock);
+        }
+        (swarm, mocks)
+    }
+    
+    #[test]
+    fn test_broadcast() {
+        let (mut swarm, mocks) = mock_swarm(3);
+        mocks[0].set_response("forward 50", "ok");
+        mocks[1].set_response("forward 50", "ok");
+        
+        let results = swarm.broadcast(|drone| drone.forward(50));
+        
+        // Every drone got the command, the third one rejected it
+        assert_eq!(results.len(), 3);
+        assert!(results[0].is_ok() && results[1].is_ok());
+        assert!(matches!(results[2], Err(TelloError::CommandRejected(_))));
+        for mock in &mocks {
+            assert_eq!(mock.get_commands(), vec!["forward 50"]);
+        }
+        assert!((swarm.drones()[0].get_position().y - 0.5).abs() < 1e-4);
+    }
+    
+    #[test]
+    fn test_takeoff_and_land_all() {
+        let (mut swarm, mocks) = mock_swarm(2);
+        
+        assert!(swarm.takeoff_all().iter().all(|result| result.is_ok()));
+        assert!(swarm.land_all().iter().all(|result| result.is_ok()));
+        for mock in &mocks {
+            assert_eq!(mock.get_commands(), vec!["battery?", "takeoff", "land"]);
+        }
+        
+        assert!(Swarm::new().land_all().is_empty());
+    }
+}
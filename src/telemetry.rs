@@ -0,0 +1,258 @@
+/*
+ * DJI Tello Drone Controller Library
+ *
+ * Copyright (c) 2025 aleskin
+ *
+ * This file is part of dji_tello_lib.
+ *
+ * dji_tello_lib is free software: you can redistribute it and/or modify
+ * it under the terms of the MIT License as published.
+ *
+ * Created: March 30, 2025
+ */
+
+// Parsing of the state packets the drone sends to the state port
+use std::fmt::Display;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::str::FromStr;
+use std::sync::Arc;
+use serde::Serialize;
+use crate::error::TelloError;
+use crate::logging::Logger;
+
+/// Columns of a telemetry log in CSV format, see `TelemetryState::to_csv_row`
+pub const CSV_HEADER: &str = "timestamp,mid,x,y,z,pitch,roll,yaw,vgx,vgy,vgz,templ,temph,tof,h,bat,baro,time,agx,agy,agz";
+
+/// One state packet from the drone
+///
+/// Every field is optional because older firmware and non-EDU drones
+/// leave some of them out.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct TelemetryState {
+    /// Detected mission pad id, -1 if none is seen, -2 if detection is off
+    pub mid: Option<i32>,
+    /// Position relative to the mission pad in cm
+    pub x: Option<i32>,
+    pub y: Option<i32>,
+    pub z: Option<i32>,
+    /// Attitude in degrees
+    pub pitch: Option<i32>,
+    pub roll: Option<i32>,
+    pub yaw: Option<i32>,
+    /// Speed in cm/s
+    pub vgx: Option<i32>,
+    pub vgy: Option<i32>,
+    pub vgz: Option<i32>,
+    /// Lowest and highest temperature in degrees Celsius
+    pub templ: Option<i32>,
+    pub temph: Option<i32>,
+    /// Time-of-flight distance in cm
+    pub tof: Option<i32>,
+    /// Height in cm
+    pub h: Option<i32>,
+    /// Battery in percent
+    pub bat: Option<i32>,
+    /// Barometer altitude in m
+    pub baro: Option<f32>,
+    /// Motor on time in seconds
+    pub time: Option<i32>,
+    /// Acceleration in 0.001g
+    pub agx: Option<f32>,
+    pub agy: Option<f32>,
+    pub agz: Option<f32>,
+}
+
+impl TelemetryState {
+    /// Parse a state line such as "mid:1;x:10;y:-5;z:80;pitch:0;..."
+    ///
+    /// Unknown keys and values that don't parse are skipped.
+    pub fn parse(line: &str) -> Self {
+        let mut state = TelemetryState::default();
+        
+        for pair in line.trim().split(';') {
+            let Some((key, value)) = pair.split_once(':') else {
+                continue;
+            };
+            let value = value.trim();
+            
+            match key.trim() {
+                "mid" => state.mid = value.parse().ok(),
+                "x" => state.x = value.parse().ok(),
+                "y" => state.y = value.parse().ok(),
+                "z" => state.z = value.parse().ok(),
+                "pitch" => state.pitch = value.parse().ok(),
+                "roll" => state.roll = value.parse().ok(),
+                "yaw" => state.yaw = value.parse().ok(),
+                "vgx" => state.vgx = value.parse().ok(),
+                "vgy" => state.vgy = value.parse().ok(),
+                "vgz" => state.vgz = value.parse().ok(),
+                "templ" => state.templ = value.parse().ok(),
+                "temph" => state.temph = value.parse().ok(),
+                "tof" => state.tof = value.parse().ok(),
+                "h" => state.h = value.parse().ok(),
+                "bat" => state.bat = value.parse().ok(),
+                "baro" => state.baro = value.parse().ok(),
+                "time" => state.time = value.parse().ok(),
+                "agx" => state.agx = value.parse().ok(),
+                "agy" => state.agy = value.parse().ok(),
+                "agz" => state.agz = value.parse().ok(),
+                _ => {}
+            }
+        }
+        
+        state
+    }
+    
+    /// The id of the mission pad currently under the drone, if any
+    pub fn mission_pad(&self) -> Option<i32> {
+        self.mid.filter(|&mid| mid > 0)
+    }
+    
+    /// A CSV row with the columns of `CSV_HEADER`, missing fields left empty
+    pub fn to_csv_row(&self, timestamp: &str) -> String {
+        fn cell<T: Display>(value: Option<T>) -> String {
+            value.map(|value| value.to_string()).unwrap_or_default()
+        }
+        
+        [
+            timestamp.to_string(),
+            cell(self.mid), cell(self.x), cell(self.y), cell(self.z),
+            cell(self.pitch), cell(self.roll), cell(self.yaw),
+            cell(self.vgx), cell(self.vgy), cell(self.vgz),
+            cell(self.templ), cell(self.temph), cell(self.tof), cell(self.h),
+            cell(self.bat), cell(self.baro), cell(self.time),
+            cell(self.agx), cell(self.agy), cell(self.agz),
+        ].join(",")
+    }
+}
+
+/// File format of a telemetry log, see `Tello::start_telemetry_log`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogFormat {
+    /// A header line, then one comma-separated row per state packet
+    Csv,
+    /// One JSON object per line
+    Json,
+}
+
+impl FromStr for LogFormat {
+    type Err = TelloError;
+    
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "csv" => Ok(LogFormat::Csv),
+            "json" => Ok(LogFormat::Json),
+            _ => Err(TelloError::InvalidParameter(
+                format!("Invalid log format: {}. Should be csv or json.", s),
+            )),
+        }
+    }
+}
+
+/// A state packet with the time it arrived, one line of a JSON telemetry log
+#[derive(Serialize)]
+struct TimestampedState<'a> {
+    timestamp: &'a str,
+    #[serde(flatten)]
+    state: &'a TelemetryState,
+}
+
+/// Appends state packets to a file, written by the state receiver thread
+pub(crate) struct TelemetryLog {
+    file: fs::File,
+    format: LogFormat,
+    failing: bool, // A write error was reported and no write succeeded since
+    logger: Arc<Logger>,
+}
+
+impl TelemetryLog {
+    /// Open `path` for appending, a new CSV file starts with the header
+    pub(crate) fn open(path: &str, format: LogFormat, logger: Arc<Logger>) -> io::Result<Self> {
+        let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+        if format == LogFormat::Csv && file.metadata()?.len() == 0 {
+            writeln!(file, "{}", CSV_HEADER)?;
+        }
+        Ok(TelemetryLog { file, format, failing: false, logger })
+    }
+    
+    /// Append one state packet stamped with the current local time
+    ///
+    /// A failing write is reported once and must not stop the state
+    /// receiver, so the next packets are still tried.
+    pub(crate) fn write(&mut self, state: &TelemetryState) {
+        let timestamp = chrono::Local::now().format("%Y-%m-%dT%H:%M:%S%.3f").to_string();
+        let line = match self.format {
+            LogFormat::Csv => state.to_csv_row(&timestamp),
+            LogFormat::Json => match serde_json::to_string(&TimestampedState { timestamp: &timestamp, state }) {
+                Ok(line) => line,
+                Err(e) => return self.report(e.into()),
+            },
+        };
+        
+        match writeln!(self.file, "{}", line) {
+            Ok(()) => self.failing = false,
+            Err(e) => self.report(e),
+        }
+    }
+    
+    fn report(&mut self, error: io::Error) {
+        if !self.failing {
+            self.logger.warn(format_args!("Failed to write telemetry log: {}", error));
+        }
+        self.failing = true;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_parse_mission_pad() {
+        let state = TelemetryState::parse("mid:1;x:10;y:-5;z:80;mpry:0,0,0;pitch:0;roll:0;yaw:45;");
+        assert_eq!(state.mid, Some(1));
+        assert_eq!(state.x, Some(10));
+        assert_eq!(state.y, Some(-5));
+        assert_eq!(state.z, Some(80));
+        assert_eq!(state.yaw, Some(45));
+        assert_eq!(state.mission_pad(), Some(1));
+        
+        // No pad detected
+        let state = TelemetryState::parse("mid:-1;x:0;y:0;z:0;");
+        assert_eq!(state.mission_pad(), None);
+    }
+    
+    #[test]
+    fn test_parse_full_state() {
+        let line = "pitch:1;roll:-2;yaw:3;vgx:10;vgy:-5;vgz:0;templ:70;temph:90;tof:65;h:50;\
+                    bat:87;baro:12.34;time:15;agx:-3.00;agy:1.00;agz:-999.00;\r\n";
+        let state = TelemetryState::parse(line);
+        assert_eq!(state.mid, None);
+        assert_eq!(state.pitch, Some(1));
+        assert_eq!(state.roll, Some(-2));
+        assert_eq!((state.vgx, state.vgy, state.vgz), (Some(10), Some(-5), Some(0)));
+        assert_eq!((state.templ, state.temph), (Some(70), Some(90)));
+        assert_eq!((state.tof, state.h), (Some(65), Some(50)));
+        assert_eq!(state.bat, Some(87));
+        assert_eq!(state.baro, Some(12.34));
+        assert_eq!(state.time, Some(15));
+        assert_eq!(state.agz, Some(-999.0));
+    }
+    
+    #[test]
+    fn test_log_rows() {
+        let state = TelemetryState::parse("pitch:1;roll:-2;yaw:3;h:50;bat:87;baro:12.5;");
+        
+        let row = state.to_csv_row("2025-04-01T12:00:00.000");
+        assert_eq!(row.split(',').count(), CSV_HEADER.split(',').count());
+        assert_eq!(row, "2025-04-01T12:00:00.000,,,,,1,-2,3,,,,,,,50,87,12.5,,,,");
+        
+        let line = serde_json::to_string(&TimestampedState { timestamp: "2025-04-01T12:00:00.000", state: &state }).unwrap();
+        assert!(line.starts_with(r#"{"timestamp":"2025-04-01T12:00:00.000","mid":null"#), "{}", line);
+        assert!(line.contains(r#""bat":87"#), "{}", line);
+        
+        assert_eq!("json".parse::<LogFormat>().unwrap(), LogFormat::Json);
+        assert!("xml".parse::<LogFormat>().is_err());
+    }
+}
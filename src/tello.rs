@@ -12,85 +12,759 @@
  */
 
 use std::io;
-use std::net::{UdpSocket, SocketAddr};
+use std::net::{IpAddr, Ipv4Addr, UdpSocket, SocketAddr, TcpListener};
+use std::fmt;
 use std::str;
-use std::time::Duration;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
 use std::thread;
-use std::sync::{Arc, Mutex};
+use std::collections::HashMap;
+use std::sync::{mpsc, Arc, Mutex, MutexGuard};
+use std::sync::atomic::{AtomicBool, AtomicI32, AtomicU8, Ordering};
+use std::thread::JoinHandle;
 use std::fs;
-use std::path::Path;
+use std::fs::OpenOptions;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use crate::error::{TelloError, TelloResult};
+use crate::telemetry::{LogFormat, TelemetryLog, TelemetryState};
+use crate::logging::{Level, Logger};
+use crate::media::{MediaDownloads, MediaFile};
+use crate::transport::{CommandTransport, DryRunTransport, UdpTransport};
 
 const TELLO_IP: &str = "192.168.10.1";
-const TELLO_PORT: u16 = 8889;
-const LOCAL_PORT: u16 = 8890;
-const STATE_PORT: u16 = 8891;
-const FILE_TRANSFER_PORT: u16 = 8888; // Port for file transfers
+const TELLO_PORT: u16 = 8889; // The drone listens for commands here
+const LOCAL_PORT: u16 = 9000; // Commands are sent from this port and the drone answers to it, as in the SDK sample
+const STATE_PORT: u16 = 8890; // The drone always sends its state packets to this port
+const FILE_TRANSFER_PORT: u16 = 8888; // Default port for file transfers
+const VIDEO_PORT: u16 = 11111; // The drone streams video to this local UDP port
+const FRAME_TIMEOUT: Duration = Duration::from_secs(5); // Wait for one frame of the video stream
+const FILE_TRANSFER_TIMEOUT: Duration = Duration::from_secs(10); // Default wait for the drone to connect
+/// Default response timeout for query commands such as "battery?" or "sdk?"
+pub const DEFAULT_QUERY_TIMEOUT: Duration = Duration::from_secs(3);
+pub(crate) const COMMAND_TIMEOUT: Duration = Duration::from_secs(5); // Read timeout of the command socket
+const MIN_TAKEOFF_BATTERY: u8 = 10; // Default minimum battery level for takeoff in percent
+const CRITICAL_BATTERY: u8 = 5; // Default battery level in percent that triggers an automatic landing
+const CRITICAL_LANDING_RETRY: Duration = Duration::from_secs(3); // Wait between automatic landing attempts on a critical battery
+const TEMPERATURE_WARNING: i32 = 85; // Default highest temperature in degrees Celsius before a warning
+const TAKEOFF_STABILIZATION: Duration = Duration::from_secs(2); // Default settling time before the takeoff height is adjusted
+const THROW_TAKEOFF_TIMEOUT: Duration = Duration::from_secs(10); // The drone answers "throwfly" once it was thrown
+const POSITION_HISTORY_LIMIT: usize = 10_000; // Default number of positions kept for export_path_csv
+const FLIGHT_RESPONSE_TIMEOUT: Duration = Duration::from_secs(20); // Takeoff, landing and moves answer once done
+const CALIBRATION_TIMEOUT: Duration = Duration::from_secs(30); // IMU calibration answers after 10+ seconds
+const STATE_STALE_AFTER: Duration = Duration::from_secs(2); // State packets older than this count as not arriving
+const LANDED_HEIGHT_CM: i32 = 10; // Telemetry height at or below which the drone counts as landed
+const LANDING_POLL_INTERVAL: Duration = Duration::from_millis(100); // The drone sends about ten state packets a second
+const COMMAND_RETRY_DELAY: Duration = Duration::from_millis(100); // First backoff of set_command_retries, doubled each time
+/// The read-only queries collected by `Tello::query_all`
+const INFO_QUERIES: [&str; 6] = ["sdk?", "sn?", "hardware?", "version?", "battery?", "wifi?"];
+const CONNECT_ATTEMPTS: u32 = 3; // Default number of tries to enter SDK mode
+const CONNECT_RETRY_DELAY: Duration = Duration::from_millis(500); // Grows with each failed attempt
+
+/// The command transport, shared with the state receiver thread for automatic landing
+type SharedTransport = Arc<Mutex<Option<Box<dyn CommandTransport>>>>;
+
+/// A function called by the state receiver thread for every state packet
+type StateCallback = Box<dyn Fn(&TelemetryState) + Send>;
+
+/// The command log file, shared with the command queue thread
+type CommandLog = Arc<Mutex<fs::File>>;
+
+/// A command for the command queue thread and where its response goes
+type QueuedCommand = (String, mpsc::Sender<TelloResult<String>>);
 
 pub struct Tello {
-    socket: Option<UdpSocket>,
+    transport: SharedTransport, // Set by connect() or set_transport()
     tello_addr: SocketAddr,
+    bind_addr: IpAddr, // Local address all sockets are bound to, see TelloBuilder::bind_address
+    local_port: u16,
+    state_port: u16,
+    file_transfer_port: u16,
+    video_port: u16,
+    file_transfer_timeout: Duration,
     state_receiver: Option<Arc<Mutex<String>>>,
+    last_state_time: Arc<Mutex<Option<Instant>>>, // When the latest state packet arrived
+    state_callbacks: Arc<Mutex<Vec<StateCallback>>>, // Registered with on_state
+    telemetry_log: Arc<Mutex<Option<TelemetryLog>>>, // Written by the state receiver thread, see start_telemetry_log
+    state_stop: Arc<AtomicBool>, // Signals the state receiver thread to exit
+    state_thread: Option<JoinHandle<()>>,
     video_recording: bool,
+    video_stop: Arc<AtomicBool>, // Signals the video recorder thread to exit
+    video_thread: Option<JoinHandle<()>>,
     download_path: String,
+    session_folder: Option<String>, // Subfolder of download_path for this session, see set_session_download_folders
     current_position: Position,
     current_direction: f32, // Current direction in degrees (0-359)
+    position_history: Vec<(Instant, Position, f32)>, // Every tracked position with its heading
+    position_history_limit: usize, // The oldest entries are dropped beyond this
+    min_takeoff_battery: u8, // Takeoff is refused below this battery level in percent
+    takeoff_stabilization: Duration, // Wait after "takeoff" before adjusting the height
+    pub(crate) speed: Option<i32>, // Last speed set with set_speed in cm/s
+    pub(crate) clamp_movements: bool, // Clamp and split out-of-range distances instead of failing
+    mission_pads_enabled: bool,
+    model: Option<TelloModel>, // Set by detect_model, unknown models are not restricted
+    identity: Option<DroneIdentity>, // Cached by identity(), cleared by reconnect
+    photo_command: Mutex<Option<String>>, // Set by set_photo_command or the first photo taken by probing
+    connect_attempts: u32, // How many times connect() sends "command" before giving up
+    command_retries: u32, // How many times send_command resends after a timeout
+    require_arming: bool, // Refuse takeoff and moves until arm() is called
+    armed: bool,
+    strict_rotation: bool, // Reject rotations outside 1..=360 instead of normalizing them
+    command_log: Option<CommandLog>, // Sent commands and responses, see set_command_log
+    command_queue: Mutex<Option<mpsc::Sender<QueuedCommand>>>, // Worker of send_command_async, started on first use
+    pub(crate) logger: Arc<Logger>, // Traces to stderr, see set_verbosity; shared with the state thread
+    max_altitude: Option<i32>, // Ceiling in cm enforced by takeoff, up and go
+    geofence: Option<(Position, Position)>, // Box (min, max) in meters that moves must stay in
+    takeoff_time: Arc<Mutex<Option<Instant>>>, // Set by takeoff, cleared by land and emergency
+    critical_battery: Arc<AtomicU8>, // The drone lands on its own below this battery level in percent
+    temperature_warning: Arc<AtomicI32>, // Warn when the highest temperature exceeds this in degrees Celsius
+    dry_run: bool, // Commands are printed instead of sent, see set_dry_run
+    dry_run_responses: HashMap<String, String>, // Canned responses used in dry-run mode
 }
 
 /// Structure to represent the drone's position
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub struct Position {
     pub x: f32, // X coordinate in meters
     pub y: f32, // Y coordinate in meters
     pub z: f32, // Z coordinate (height) in meters
 }
 
-impl Tello {
-    /// Create a new Tello instance
-    pub fn new() -> io::Result<Self> {
-        let tello_addr = format!("{}:{}", TELLO_IP, TELLO_PORT)
+/// What tells one drone from another, see `Tello::identity`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DroneIdentity {
+    /// Serial number from "sn?", unique per drone
+    pub serial: String,
+    /// SDK version from "sdk?", e.g. "30"
+    pub sdk_version: String,
+}
+
+/// A summary of the connection and flight state, see `Tello::status`
+#[derive(Debug, Clone)]
+pub struct TelloStatus {
+    pub connected: bool,
+    /// Whether a state packet arrived within the last 2 seconds
+    pub receiving_state: bool,
+    /// Time since the latest state packet, `None` if none arrived yet
+    pub state_age: Option<Duration>,
+    pub video_recording: bool,
+    pub position: Position,
+    pub heading: f32,
+    /// Battery in percent from the latest state packet
+    pub battery: Option<u8>,
+}
+
+/// Video resolution of the Tello EDU camera
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum VideoResolution {
+    High, // 720p
+    Low,  // 480p
+}
+
+impl VideoResolution {
+    /// Get the value used by the SDK "setresolution" command
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            VideoResolution::High => "high",
+            VideoResolution::Low => "low",
+        }
+    }
+}
+
+impl FromStr for VideoResolution {
+    type Err = TelloError;
+    
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "high" => Ok(VideoResolution::High),
+            "low" => Ok(VideoResolution::Low),
+            _ => Err(TelloError::InvalidParameter(
+                format!("Invalid resolution: {}. Should be high or low.", s),
+            )),
+        }
+    }
+}
+
+/// Video frame rate of the Tello EDU camera
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum VideoFps {
+    High,   // 30 fps
+    Middle, // 15 fps
+    Low,    // 5 fps
+}
+
+impl VideoFps {
+    /// Get the value used by the SDK "setfps" command
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            VideoFps::High => "high",
+            VideoFps::Middle => "middle",
+            VideoFps::Low => "low",
+        }
+    }
+}
+
+impl FromStr for VideoFps {
+    type Err = TelloError;
+    
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "high" => Ok(VideoFps::High),
+            "middle" => Ok(VideoFps::Middle),
+            "low" => Ok(VideoFps::Low),
+            _ => Err(TelloError::InvalidParameter(
+                format!("Invalid frame rate: {}. Should be high, middle or low.", s),
+            )),
+        }
+    }
+}
+
+/// Which camera of the Tello EDU feeds the video stream
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum CameraSelect {
+    Forward,
+    Downward,
+}
+
+impl CameraSelect {
+    /// Get the name used by the "camera" command
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CameraSelect::Forward => "forward",
+            CameraSelect::Downward => "down",
+        }
+    }
+}
+
+impl FromStr for CameraSelect {
+    type Err = TelloError;
+    
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "forward" => Ok(CameraSelect::Forward),
+            "down" | "downward" => Ok(CameraSelect::Downward),
+            _ => Err(TelloError::InvalidParameter(
+                format!("Invalid camera: {}. Should be forward or down.", s),
+            )),
+        }
+    }
+}
+
+/// The kind of drone, see `Tello::detect_model`
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum TelloModel {
+    /// The original Tello, SDK 1.3 without "sdk?"
+    Tello,
+    /// Tello EDU, SDK 2.0 and later
+    TelloEdu,
+    /// RoboMaster TT, reports "RMTT" to "hardware?"
+    RoboMasterTt,
+}
+
+impl TelloModel {
+    /// Classify a drone by its answers to "sdk?" and "hardware?"
+    ///
+    /// `None` stands for a query the drone rejected. Only SDK 3.0 knows
+    /// "hardware?", and only the original Tello doesn't know "sdk?".
+    pub fn classify(sdk: Option<&str>, hardware: Option<&str>) -> TelloModel {
+        if hardware.is_some_and(|h| h.trim().eq_ignore_ascii_case("RMTT")) {
+            return TelloModel::RoboMasterTt;
+        }
+        match sdk.and_then(|v| v.trim().parse::<u32>().ok()) {
+            Some(version) if version >= 20 => TelloModel::TelloEdu,
+            _ => TelloModel::Tello,
+        }
+    }
+    
+    /// Whether the drone detects mission pads ("mon", "go ... m1", "jump")
+    pub fn has_mission_pads(&self) -> bool {
+        !matches!(self, TelloModel::Tello)
+    }
+    
+    /// The command that takes a photo on this model
+    pub fn photo_command(&self) -> &'static str {
+        match self {
+            TelloModel::Tello => "snapshot",
+            TelloModel::TelloEdu | TelloModel::RoboMasterTt => "takepic",
+        }
+    }
+}
+
+impl fmt::Display for TelloModel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TelloModel::Tello => write!(f, "Tello"),
+            TelloModel::TelloEdu => write!(f, "Tello EDU"),
+            TelloModel::RoboMasterTt => write!(f, "RoboMaster TT"),
+        }
+    }
+}
+
+/// Builder for a Tello instance with custom network settings
+///
+/// Useful when the drone runs in station mode behind a router or when
+/// several drones are controlled from one machine on different local ports.
+pub struct TelloBuilder {
+    ip: String,
+    command_port: u16,
+    local_port: u16,
+    state_port: u16,
+    file_transfer_port: u16,
+    video_port: u16,
+    connect_attempts: u32,
+    bind_address: String,
+}
+
+impl Default for TelloBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TelloBuilder {
+    /// Create a builder with the default Tello network settings
+    pub fn new() -> Self {
+        TelloBuilder {
+            ip: String::from(TELLO_IP),
+            command_port: TELLO_PORT,
+            local_port: LOCAL_PORT,
+            state_port: STATE_PORT,
+            file_transfer_port: FILE_TRANSFER_PORT,
+            video_port: VIDEO_PORT,
+            connect_attempts: CONNECT_ATTEMPTS,
+            bind_address: Ipv4Addr::UNSPECIFIED.to_string(),
+        }
+    }
+    
+    /// Set the IP address of the drone
+    pub fn ip(mut self, ip: &str) -> Self {
+        self.ip = String::from(ip);
+        self
+    }
+    
+    /// Set the UDP port the drone listens on for commands
+    pub fn command_port(mut self, port: u16) -> Self {
+        self.command_port = port;
+        self
+    }
+    
+    /// Set the local UDP port used for sending commands and receiving responses
+    ///
+    /// The drone answers a command to the port it came from, 9000 by
+    /// default. It must not be the state port, or the state packets would
+    /// be read as responses.
+    pub fn local_port(mut self, port: u16) -> Self {
+        self.local_port = port;
+        self
+    }
+    
+    /// Set the local UDP port used for receiving state information
+    ///
+    /// The drone sends its state to 8890; change this only when a proxy
+    /// forwards the packets to another port.
+    pub fn state_port(mut self, port: u16) -> Self {
+        self.state_port = port;
+        self
+    }
+    
+    /// Set the local TCP port used for direct file transfers
+    pub fn file_transfer_port(mut self, port: u16) -> Self {
+        self.file_transfer_port = port;
+        self
+    }
+    
+    /// Set the local UDP port the drone streams video to
+    ///
+    /// The drone always streams to 11111; change this only when a proxy
+    /// forwards the stream to another port.
+    pub fn video_port(mut self, port: u16) -> Self {
+        self.video_port = port;
+        self
+    }
+    
+    /// Set the local IP address the sockets are bound to
+    ///
+    /// By default they are bound to all interfaces ("0.0.0.0"). On a machine
+    /// with several networks, e.g. Ethernet and the Tello WiFi, binding to
+    /// the address of the Tello-facing interface makes sure packets leave
+    /// through it. Must be the same IP version as the drone address.
+    pub fn bind_address(mut self, address: &str) -> Self {
+        self.bind_address = String::from(address);
+        self
+    }
+    
+    /// Set how many times connect() tries to enter SDK mode (default 3)
+    pub fn connect_attempts(mut self, attempts: u32) -> Self {
+        self.connect_attempts = attempts;
+        self
+    }
+    
+    /// Validate the settings and create the Tello instance
+    pub fn build(self) -> TelloResult<Tello> {
+        if self.connect_attempts == 0 {
+            return Err(TelloError::InvalidParameter(
+                String::from("Connect attempts must be at least 1"),
+            ));
+        }
+        
+        let tello_addr: SocketAddr = format!("{}:{}", self.ip, self.command_port)
             .parse()
-            .expect("Failed to parse Tello address");
-            
+            .map_err(|e| TelloError::InvalidParameter(
+                format!("Invalid Tello address {}:{}: {}", self.ip, self.command_port, e),
+            ))?;
+        
+        let bind_addr: IpAddr = self.bind_address.parse()
+            .map_err(|e| TelloError::InvalidParameter(
+                format!("Invalid bind address {}: {}", self.bind_address, e),
+            ))?;
+        if bind_addr.is_ipv4() != tello_addr.is_ipv4() {
+            return Err(TelloError::InvalidParameter(format!(
+                "Bind address {} and drone address {} must both be IPv4 or both IPv6", bind_addr, tello_addr.ip(),
+            )));
+        }
+        
+        // All local ports are bound on this machine, so they must not collide.
+        // Port 0 lets the OS pick a free port and never collides.
+        let local_ports = [
+            ("local", self.local_port),
+            ("state", self.state_port),
+            ("file transfer", self.file_transfer_port),
+            ("video", self.video_port),
+        ];
+        for (i, (name_a, port_a)) in local_ports.iter().enumerate() {
+            for (name_b, port_b) in &local_ports[i + 1..] {
+                if port_a == port_b && *port_a != 0 {
+                    return Err(TelloError::InvalidParameter(
+                        format!("Port collision: {} port and {} port are both {}", name_a, name_b, port_a),
+                    ));
+                }
+            }
+        }
+        
         Ok(Tello {
-            socket: None,
+            transport: Arc::new(Mutex::new(None)),
             tello_addr,
+            bind_addr,
+            local_port: self.local_port,
+            state_port: self.state_port,
+            file_transfer_port: self.file_transfer_port,
+            video_port: self.video_port,
+            file_transfer_timeout: FILE_TRANSFER_TIMEOUT,
             state_receiver: None,
+            last_state_time: Arc::new(Mutex::new(None)),
+            state_callbacks: Arc::new(Mutex::new(Vec::new())),
+            telemetry_log: Arc::new(Mutex::new(None)),
+            state_stop: Arc::new(AtomicBool::new(false)),
+            state_thread: None,
             video_recording: false,
+            video_stop: Arc::new(AtomicBool::new(false)),
+            video_thread: None,
             download_path: String::from("./tello_media"), // Default download path
+            session_folder: None,
             current_position: Position { x: 0.0, y: 0.0, z: 0.0 },
             current_direction: 0.0, // Facing forward initially
+            position_history: Vec::new(),
+            position_history_limit: POSITION_HISTORY_LIMIT,
+            min_takeoff_battery: MIN_TAKEOFF_BATTERY,
+            speed: None,
+            clamp_movements: false,
+            mission_pads_enabled: false,
+            model: None,
+            identity: None,
+            photo_command: Mutex::new(None),
+            connect_attempts: self.connect_attempts,
+            command_retries: 0,
+            takeoff_stabilization: TAKEOFF_STABILIZATION,
+            require_arming: false,
+            armed: false,
+            strict_rotation: false,
+            command_log: None,
+            command_queue: Mutex::new(None),
+            logger: Arc::new(Logger::new(Level::default())),
+            max_altitude: None,
+            geofence: None,
+            takeoff_time: Arc::new(Mutex::new(None)),
+            critical_battery: Arc::new(AtomicU8::new(CRITICAL_BATTERY)),
+            temperature_warning: Arc::new(AtomicI32::new(TEMPERATURE_WARNING)),
+            dry_run: false,
+            dry_run_responses: HashMap::new(),
         })
     }
+}
+
+impl Tello {
+    /// Create a new Tello instance with the default network settings
+    pub fn new() -> TelloResult<Self> {
+        TelloBuilder::new().build()
+    }
+    
+    /// Create a builder for a Tello instance with custom network settings
+    pub fn builder() -> TelloBuilder {
+        TelloBuilder::new()
+    }
+    
+    /// Get the address of the drone commands are sent to
+    pub fn tello_addr(&self) -> SocketAddr {
+        self.tello_addr
+    }
+    
+    /// Get the local IP address the sockets are bound to
+    pub fn bind_address(&self) -> IpAddr {
+        self.bind_addr
+    }
+    
+    /// The local address to bind a socket for `port` to
+    fn bind_socket_addr(&self, port: u16) -> SocketAddr {
+        SocketAddr::new(self.bind_addr, port)
+    }
+    
+    /// Get the local port used for commands and responses
+    pub fn local_port(&self) -> u16 {
+        self.local_port
+    }
+    
+    /// Get the local port used for state information
+    pub fn state_port(&self) -> u16 {
+        self.state_port
+    }
+    
+    /// Get the local port used for direct file transfers
+    pub fn file_transfer_port(&self) -> u16 {
+        self.file_transfer_port
+    }
+    
+    /// Get the local port the video stream is received on
+    pub fn video_port(&self) -> u16 {
+        self.video_port
+    }
+    
+    /// Send a command to the drone and return its raw response
+    ///
+    /// Unlike `send_command`, the response is returned as is (trimmed) without
+    /// interpreting telemetry packets, which makes it suitable for queries such
+    /// as "sdk?" or "sn?". Waits at most `timeout` for the response and returns
+    /// `TelloError::Timeout` if none arrives.
+    pub fn send_command_with_response(&self, command: &str, timeout: Duration) -> TelloResult<String> {
+        self.check_armed(command)?;
+        if !self.is_connected() {
+            return Err(TelloError::NotConnected);
+        }
+        
+        if timeout.is_zero() {
+            return Err(TelloError::InvalidParameter(
+                String::from("Response timeout must be greater than zero"),
+            ));
+        }
+        
+        self.logger.debug(format_args!("Sending command: {}", command));
+        
+        let response = self.exchange(command, Some(timeout))?.trim().to_string();
+        self.logger.debug(format_args!("Response: {}", response));
+        
+        Ok(response)
+    }
+    
+    /// Send a command without waiting for a response
+    ///
+    /// Used for commands the drone never acknowledges, such as "rc".
+    pub fn send_command_without_response(&self, command: &str) -> TelloResult<()> {
+        let transport = self.transport();
+        let transport = transport.as_deref().ok_or(TelloError::NotConnected)?;
+        self.log_command(">>", command);
+        transport.send_without_response(command)?;
+        Ok(())
+    }
+    
+    /// Lock the transport for one command
+    ///
+    /// The lock keeps the state receiver thread from sending its own commands
+    /// in between a command and its response.
+    fn transport(&self) -> MutexGuard<'_, Option<Box<dyn CommandTransport>>> {
+        self.transport.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
     
-    /// Send a command to the drone and get response
-    /// Alias for send_command, provided for better semantic clarity
-    pub fn send_command_with_response(&self, command: &str) -> io::Result<String> {
-        self.send_command(command)
+    /// Send a command over the transport and wait for its response
+    fn exchange(&self, command: &str, timeout: Option<Duration>) -> TelloResult<String> {
+        let transport = self.transport();
+        let transport = transport.as_deref().ok_or(TelloError::NotConnected)?;
+        self.log_command(">>", command);
+        
+        let result = match timeout {
+            Some(timeout) => transport.send_command_with_timeout(command, timeout),
+            None => transport.send_command(command),
+        };
+        
+        match result {
+            Ok(response) => {
+                self.log_command("<<", response.trim());
+                Ok(response)
+            },
+            Err(e) => {
+                self.log_command("<<", &format!("({})", e));
+                Err(e.into())
+            },
+        }
+    }
+    
+    /// Use a custom transport instead of the UDP connection made by connect()
+    ///
+    /// Useful for simulators and for testing code built on `Tello`
+    /// without a drone.
+    pub fn set_transport(&mut self, transport: Box<dyn CommandTransport>) {
+        *self.transport() = Some(transport);
+    }
+    
+    /// Whether a transport to the drone is set up
+    pub fn is_connected(&self) -> bool {
+        self.transport().is_some()
+    }
+    
+    /// Print commands instead of sending them to a drone
+    ///
+    /// Every command is answered with "ok" or a response set with
+    /// `set_dry_run_response`, and position tracking keeps working, so a
+    /// flight plan can be rehearsed without a drone. Turning dry-run mode
+    /// off drops the connection; call connect() to talk to a real drone.
+    pub fn set_dry_run(&mut self, enabled: bool) {
+        if enabled {
+            self.set_transport(self.dry_run_transport());
+        } else if self.dry_run {
+            *self.transport() = None;
+        }
+        self.dry_run = enabled;
+    }
+    
+    /// Whether commands are printed instead of sent
+    pub fn is_dry_run(&self) -> bool {
+        self.dry_run
+    }
+    
+    /// Set the response to a command in dry-run mode
+    ///
+    /// `command` is a full command such as "battery?" or just a command name.
+    pub fn set_dry_run_response(&mut self, command: &str, response: &str) {
+        self.dry_run_responses.insert(command.to_string(), response.to_string());
+        if self.dry_run {
+            self.set_transport(self.dry_run_transport());
+        }
+    }
+    
+    fn dry_run_transport(&self) -> Box<DryRunTransport> {
+        let mut transport = DryRunTransport::new();
+        for (command, response) in &self.dry_run_responses {
+            transport.set_response(command, response);
+        }
+        Box::new(transport)
+    }
+    
+    /// Log sent commands and received responses to a file
+    ///
+    /// The file is opened for appending. Each line has a timestamp followed
+    /// by ">>" for a command or "<<" for a response.
+    pub fn set_command_log(&mut self, path: &str) -> io::Result<()> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        self.command_log = Some(Arc::new(Mutex::new(file)));
+        // The queue thread keeps the log it was started with
+        self.stop_command_queue();
+        Ok(())
+    }
+    
+    /// Stop logging commands to a file
+    pub fn clear_command_log(&mut self) {
+        self.command_log = None;
+        self.stop_command_queue();
+    }
+    
+    /// Set how much is traced to stderr
+    ///
+    /// `Level::Quiet` (the default) traces nothing, `Level::Warn` only warns
+    /// about clamped values and other flight problems, `Level::Info` adds
+    /// connection and media transfer progress and `Level::Debug` adds every
+    /// command, response and retry.
+    pub fn set_verbosity(&mut self, level: Level) {
+        self.logger.set_level(level);
+    }
+    
+    /// The current trace level
+    pub fn verbosity(&self) -> Level {
+        self.logger.level()
+    }
+    
+    /// Send traces to `writer` instead of stderr
+    pub fn set_log_writer(&mut self, writer: impl Write + Send + 'static) {
+        self.logger.set_writer(Box::new(writer));
+    }
+    
+    /// Trace every command and response (`Level::Debug`) or nothing (`Level::Quiet`)
+    pub fn set_verbose(&mut self, verbose: bool) {
+        self.set_verbosity(if verbose { Level::Debug } else { Level::Quiet });
+    }
+    
+    /// Append a line to the command log if logging is enabled
+    fn log_command(&self, direction: &str, text: &str) {
+        write_command_log(self.command_log.as_ref(), direction, text, &self.logger);
     }
     
     /// Set download path for media files
+    ///
+    /// A leading `~` is the home directory, and `$VAR`, `${VAR}` and `%VAR%`
+    /// are replaced by environment variables. An unset variable or an empty
+    /// result is an `InvalidInput` error.
     pub fn set_download_path(&mut self, path: &str) -> io::Result<()> {
-        if !Path::new(path).exists() {
-            fs::create_dir_all(path)?;
+        let path = expand_path(path)?;
+        if !Path::new(&path).exists() {
+            fs::create_dir_all(&path)?;
         }
-        self.download_path = String::from(path);
+        self.download_path = path;
         Ok(())
     }
     
+    /// Save downloads into a timestamped subfolder of the download path
+    ///
+    /// When enabled, `download_media` and direct transfers save into
+    /// `<download path>/YYYY-MM-DD_HH-MM/`, named after the time this was
+    /// enabled, so each session gets its own folder. Off by default.
+    pub fn set_session_download_folders(&mut self, enabled: bool) {
+        self.session_folder = enabled.then(|| chrono::Local::now().format("%Y-%m-%d_%H-%M").to_string());
+    }
+    
+    /// The folder media downloads are saved into
+    fn media_download_dir(&self) -> String {
+        match &self.session_folder {
+            Some(folder) => format!("{}/{}", self.download_path, folder),
+            None => self.download_path.clone(),
+        }
+    }
+    
+    /// Set how long direct file transfers wait for the drone to connect
+    pub fn set_file_transfer_timeout(&mut self, timeout: Duration) {
+        self.file_transfer_timeout = timeout;
+    }
+    
     /// Connect to the Tello drone
-    pub fn connect(&mut self) -> io::Result<()> {
-        let socket = UdpSocket::bind(format!("0.0.0.0:{}", LOCAL_PORT))?;
-        socket.set_read_timeout(Some(Duration::from_secs(5)))?;
-        socket.set_write_timeout(Some(Duration::from_secs(5)))?;
+    ///
+    /// Sends "command" until the drone answers "ok", up to the configured
+    /// number of attempts, and returns `TelloError::NotConnected` if it never does.
+    /// If the state port cannot be bound, a warning is printed and the
+    /// connection works without telemetry.
+    pub fn connect(&mut self) -> TelloResult<()> {
+        let socket = UdpSocket::bind(self.bind_socket_addr(self.local_port))?;
+        socket.set_read_timeout(Some(COMMAND_TIMEOUT))?;
+        socket.set_write_timeout(Some(COMMAND_TIMEOUT))?;
         
-        // Store the socket in the struct
-        self.socket = Some(socket);
+        self.set_transport(Box::new(UdpTransport::new(socket, self.tello_addr)));
         
         // Initialize the SDK mode
-        self.send_command("command")?;
+        if let Err(e) = self.enter_sdk_mode() {
+            *self.transport() = None;
+            return Err(e);
+        }
         
-        // Set up state receiver
-        self.setup_state_receiver()?;
+        // Commands still work without state packets, so a busy state port is not fatal
+        if let Err(e) = self.setup_state_receiver() {
+            self.logger.warn(format_args!("Cannot receive drone state on port {}: {}. Telemetry is unavailable, commands still work",
+                self.state_port, e));
+        }
         
         // Create download directory if it doesn't exist
         if !Path::new(&self.download_path).exists() {
@@ -100,21 +774,64 @@ impl Tello {
         Ok(())
     }
     
+    /// Send "command" until the drone confirms SDK mode with "ok"
+    ///
+    /// The first packet is often lost on a flaky WiFi link, so timeouts and
+    /// unexpected answers are retried with a growing delay.
+    fn enter_sdk_mode(&self) -> TelloResult<()> {
+        for attempt in 1..=self.connect_attempts {
+            match self.send_command("command") {
+                Ok(response) if response.trim() == "ok" => return Ok(()),
+                Ok(response) | Err(TelloError::CommandRejected(response)) => self.logger.info(format_args!(
+                    "Attempt {}/{}: drone answered \"{}\" instead of \"ok\"",
+                    attempt, self.connect_attempts, response.trim())),
+                Err(TelloError::Timeout) => self.logger.info(format_args!("Attempt {}/{}: no response from drone",
+                    attempt, self.connect_attempts)),
+                Err(e) => return Err(e),
+            }
+            
+            if attempt < self.connect_attempts {
+                thread::sleep(CONNECT_RETRY_DELAY * attempt);
+            }
+        }
+        
+        self.logger.warn(format_args!("Drone did not enter SDK mode after {} attempts", self.connect_attempts));
+        Err(TelloError::NotConnected)
+    }
+    
     /// Sets up a separate thread to receive state information from the drone
-    fn setup_state_receiver(&mut self) -> io::Result<()> {
+    fn setup_state_receiver(&mut self) -> TelloResult<()> {
         // Create a socket for receiving state information
-        let state_socket = UdpSocket::bind(format!("0.0.0.0:{}", STATE_PORT))?;
+        let state_socket = UdpSocket::bind(self.bind_socket_addr(self.state_port))?;
         state_socket.set_read_timeout(Some(Duration::from_secs(1)))?;
         
         // Create a shared state to store the latest drone state
         let state = Arc::new(Mutex::new(String::new()));
         self.state_receiver = Some(Arc::clone(&state));
+        let last_state_time = Arc::new(Mutex::new(None));
+        self.last_state_time = Arc::clone(&last_state_time);
+        
+        // Fresh stop flag so a previous disconnect doesn't stop the new thread
+        let stop = Arc::new(AtomicBool::new(false));
+        self.state_stop = Arc::clone(&stop);
         
-        // Start a thread to continuously receive state information
-        thread::spawn(move || {
+        // Shared with the thread so it can land on a critical battery and warn about heat
+        let transport = Arc::clone(&self.transport);
+        let takeoff_time = Arc::clone(&self.takeoff_time);
+        let critical_battery = Arc::clone(&self.critical_battery);
+        let temperature_warning = Arc::clone(&self.temperature_warning);
+        let state_callbacks = Arc::clone(&self.state_callbacks);
+        let telemetry_log = Arc::clone(&self.telemetry_log);
+        let logger = Arc::clone(&self.logger);
+        
+        // Start a thread to continuously receive state information until stopped
+        let handle = thread::spawn(move || {
             let mut buffer = [0; 1024];
+            let mut overheated = false;
+            let mut link_lost = false;
+            let mut landing_attempt = None;
             
-            loop {
+            while !stop.load(Ordering::Relaxed) {
                 match state_socket.recv_from(&mut buffer) {
                     Ok((amount, _)) => {
                         if let Ok(data) = str::from_utf8(&buffer[..amount]) {
@@ -122,23 +839,107 @@ impl Tello {
                             if let Ok(mut state_guard) = state.lock() {
                                 *state_guard = data.to_string();
                             }
+                            if let Ok(mut time_guard) = last_state_time.lock() {
+                                *time_guard = Some(Instant::now());
+                            }
+                            
+                            let telemetry = TelemetryState::parse(data);
+                            land_on_critical_battery(&telemetry, &transport, &takeoff_time,
+                                critical_battery.load(Ordering::Relaxed), &mut landing_attempt, Instant::now(), &logger);
+                            
+                            let threshold = temperature_warning.load(Ordering::Relaxed);
+                            if let Some(warning) = check_temperature(&telemetry, threshold, &mut overheated) {
+                                logger.warn(format_args!("{}", warning));
+                            }
+                            
+                            write_telemetry_log(&telemetry, &telemetry_log);
+                            notify_state_callbacks(&telemetry, &state_callbacks);
                         }
                     },
                     Err(e) => {
-                        if e.kind() != io::ErrorKind::WouldBlock {
-                            eprintln!("Error receiving state: {}", e);
+                        if e.kind() != io::ErrorKind::WouldBlock && e.kind() != io::ErrorKind::TimedOut {
+                            logger.warn(format_args!("Error receiving state: {}", e));
                         }
                     }
                 }
                 
+                let last = last_state_time.lock().ok().and_then(|time| *time);
+                if let Some(message) = check_link(last, Instant::now(), STATE_STALE_AFTER, &mut link_lost) {
+                    logger.warn(format_args!("{}", message));
+                }
+                
                 // Sleep a short time to avoid consuming too much CPU
                 thread::sleep(Duration::from_millis(100));
             }
         });
+        self.state_thread = Some(handle);
+        
+        Ok(())
+    }
+    
+    /// Stop the state receiver thread and wait for it to exit
+    fn stop_state_receiver(&mut self) {
+        // The thread notices the flag within one read timeout
+        self.state_stop.store(true, Ordering::Relaxed);
+        if let Some(handle) = self.state_thread.take() {
+            if handle.join().is_err() {
+                self.logger.warn(format_args!("State receiver thread panicked"));
+            }
+        }
+        self.state_receiver = None;
+    }
+    
+    /// Re-establish the connection after the link to the drone was lost
+    ///
+    /// Rebinds the command socket, restarts the state receiver and enters
+    /// SDK mode again. Tracked position, heading and the download path are
+    /// kept. The model is detected again, the identity on its next use and
+    /// the photo command on the next photo, also one set with
+    /// `set_photo_command`. Call this when a command fails with an error for
+    /// which `TelloError::is_link_error` is true.
+    pub fn reconnect(&mut self) -> TelloResult<()> {
+        self.logger.info(format_args!("Reconnecting to drone..."));
+        self.stop_state_receiver();
+        
+        // Release the local port before binding it again
+        *self.transport() = None;
+        
+        // The drone that answers may not be the one we talked to before
+        self.identity = None;
+        self.model = None;
+        *self.photo_command.get_mut().unwrap_or_else(|poisoned| poisoned.into_inner()) = None;
         
+        self.connect()?;
+        
+        // Without a model nothing is refused, so a failed detection only warns
+        if let Err(e) = self.detect_model() {
+            self.logger.warn(format_args!("Failed to detect the drone model after reconnecting: {}", e));
+        }
         Ok(())
     }
     
+    /// Disconnect from the drone
+    ///
+    /// Stops the state receiver thread, stops the video stream if it is
+    /// running and closes the command socket. Safe to call more than once.
+    pub fn disconnect(&mut self) {
+        self.stop_state_receiver();
+        self.stop_video_recorder();
+        
+        if self.video_recording && self.is_connected() {
+            if let Err(e) = self.send_command("streamoff") {
+                self.logger.warn(format_args!("Failed to stop video stream while disconnecting: {}", e));
+            }
+        }
+        self.video_recording = false;
+        
+        // Queued commands are answered with NotConnected once the transport is gone
+        self.stop_command_queue();
+        
+        // Dropping the transport closes the socket
+        *self.transport() = None;
+    }
+    
     /// Get the latest drone state
     pub fn get_state(&self) -> Option<String> {
         if let Some(state_receiver) = &self.state_receiver {
@@ -149,24 +950,94 @@ impl Tello {
         None
     }
     
+    /// Call `callback` for every state packet the drone sends
+    ///
+    /// Several callbacks can be registered; they are called in order of
+    /// registration. They run on the state receiver thread, so they must be
+    /// `Send` and should return quickly, since the next packet is not read
+    /// until they are done. A callback must not call `on_state` itself.
+    pub fn on_state<F: Fn(&TelemetryState) + Send + 'static>(&mut self, callback: F) {
+        self.state_callbacks.lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .push(Box::new(callback));
+    }
+    
+    /// Append every state packet to a file for later analysis
+    ///
+    /// The file is opened for appending and each packet is written with
+    /// its arrival time, as a CSV row (a new file starts with a header) or
+    /// as a JSON object per line. Replaces a log that is already running.
+    /// Write errors are reported on stderr without stopping the state
+    /// receiver.
+    pub fn start_telemetry_log(&mut self, path: &str, format: LogFormat) -> io::Result<()> {
+        let log = TelemetryLog::open(path, format, Arc::clone(&self.logger))?;
+        *self.telemetry_log.lock().unwrap_or_else(|poisoned| poisoned.into_inner()) = Some(log);
+        Ok(())
+    }
+    
+    /// Stop writing state packets to the telemetry log file
+    pub fn stop_telemetry_log(&mut self) {
+        *self.telemetry_log.lock().unwrap_or_else(|poisoned| poisoned.into_inner()) = None;
+    }
+    
+    /// Get the latest drone state parsed into its fields
+    pub fn get_telemetry(&self) -> Option<TelemetryState> {
+        self.get_state()
+            .filter(|state| !state.is_empty())
+            .map(|state| TelemetryState::parse(&state))
+    }
+    
+    /// Time since the latest state packet, `None` if none arrived yet
+    fn state_age(&self) -> Option<Duration> {
+        self.last_state_time.lock().ok()
+            .and_then(|time| *time)
+            .map(|time| time.elapsed())
+    }
+    
+    /// Whether a state packet arrived within `max_age`
+    ///
+    /// The drone sends state packets about ten times a second, so a gap of a
+    /// second or two means the WiFi link is gone. Always false before the
+    /// first packet.
+    pub fn is_link_alive(&self, max_age: Duration) -> bool {
+        self.state_age().is_some_and(|age| age <= max_age)
+    }
+    
+    /// Summarize the connection, state packets, video and tracked position
+    ///
+    /// Only uses what is already known, so no command is sent.
+    pub fn status(&self) -> TelloStatus {
+        let state_age = self.state_age();
+        let battery = self.get_telemetry()
+            .and_then(|telemetry| telemetry.bat)
+            .and_then(|bat| u8::try_from(bat).ok());
+        
+        TelloStatus {
+            connected: self.is_connected(),
+            receiving_state: state_age.is_some_and(|age| age < STATE_STALE_AFTER),
+            state_age,
+            video_recording: self.video_recording,
+            position: self.current_position,
+            heading: self.current_direction,
+            battery,
+        }
+    }
+    
     /// Send a command to the drone
-    pub fn send_command(&self, command: &str) -> io::Result<String> {
-        if let Some(socket) = &self.socket {
-            println!("Sending command: {}", command);
-            
-            socket.send_to(command.as_bytes(), self.tello_addr)?;
+    ///
+    /// An "error ..." reply is returned as `TelloError::CommandRejected`
+    /// carrying the reason, e.g. "Motor stop" for "error Motor stop".
+    pub fn send_command(&self, command: &str) -> TelloResult<String> {
+        self.check_armed(command)?;
+        if self.is_connected() {
+            self.logger.debug(format_args!("Sending command: {}", command));
             
-            // Get response
-            let mut buffer = [0; 1024];
-            let (amount, _) = socket.recv_from(&mut buffer)?;
+            let response = retry_on_timeout(command, self.command_retries, &self.logger,
+                || self.exchange(command, response_timeout(command)))?;
             
-            let response = str::from_utf8(&buffer[..amount])
-                .unwrap_or("Invalid UTF-8 response")
-                .to_string();
-                
             // Check if the response is telemetry data instead of command response
             if response.contains("pitch:") && response.contains("roll:") && response.contains("yaw:") {
-                println!("Received telemetry data instead of command response");
+                self.logger.debug(format_args!("Received telemetry data instead of command response"));
                 
                 // For most SDK commands, receiving telemetry is normal and the command is successful
                 // The drone does not always explicitly send "ok" after telemetry
@@ -175,98 +1046,777 @@ impl Tello {
                 // we need to handle them specially
                 if command == "ls" {
                     // For media listing commands, we need to try to extract file information
-                    println!("Listing media files is not fully supported in current firmware");
+                    self.logger.info(format_args!("Listing media files is not fully supported in current firmware"));
                     return Ok("No files found".to_string());
                 }
                 else if command.starts_with("download") || command.starts_with("direct_transfer") {
                     // For download commands
-                    println!("Simulating download: File not found on drone");
+                    self.logger.info(format_args!("Simulating download: File not found on drone"));
                     return Ok("File not found".to_string());
                 }
                 else {
                     // For regular commands, just assume they worked if drone is responsive
-                    println!("Assuming command was successful based on telemetry response");
+                    self.logger.debug(format_args!("Assuming command was successful based on telemetry response"));
                     return Ok("ok".to_string());
                 }
             }
             
-            println!("Response: {}", response);
+            self.logger.debug(format_args!("Response: {}", response));
+            
+            // Replies such as "error Motor stop" carry the reason after the prefix
+            if let Some(reason) = error_reason(&response) {
+                return Err(TelloError::CommandRejected(reason));
+            }
             
             Ok(response)
         } else {
-            Err(io::Error::new(io::ErrorKind::NotConnected, "Drone not connected"))
+            Err(TelloError::NotConnected)
         }
     }
     
-    /// Take off
-    /// 
-    /// Optional height parameter in meters (default: 1m, max: 8m)
-    pub fn takeoff(&self, height: Option<f32>) -> io::Result<()> {
-        // First issue standard takeoff command
-        let response = self.send_command("takeoff")?;
-        
-        if response != "ok" {
-            return Err(io::Error::new(
-                io::ErrorKind::Other,
-                format!("Takeoff command failed: {}", response),
-            ));
+    /// Send a command without waiting for its response
+    ///
+    /// The command is queued for a worker thread that sends queued commands
+    /// one at a time in order, and the response arrives on the returned
+    /// receiver, e.g. `rx.recv()` to wait or `rx.try_recv()` to poll. Like
+    /// `send_command`, timeouts are retried and "error ..." replies become
+    /// `TelloError::CommandRejected`, but the tracked position, takeoff time
+    /// and other state are not updated.
+    pub fn send_command_async(&self, command: &str) -> TelloResult<mpsc::Receiver<TelloResult<String>>> {
+        self.check_armed(command)?;
+        if !self.is_connected() {
+            return Err(TelloError::NotConnected);
         }
+        self.logger.debug(format_args!("Queueing command: {}", command));
         
-        // If a specific height was requested
-        if let Some(h) = height {
-            if h > 8.0 {
-                println!("Warning: Requested height {}m exceeds maximum. Using default height (1m).", h);
-                return Ok(());
-            }
-            
-            if h <= 0.0 {
-                println!("Warning: Invalid height value ({}m). Using default height (1m).", h);
-                return Ok(());
-            }
-            
-            // Convert height to centimeters for the command
-            let height_cm = (h * 100.0) as i32;
-            
-            // The "go" command uses x y z speed format
-            // We'll move up to desired height
-            if height_cm > 100 {
-                let _ = self.send_command(&format!("up {}", height_cm - 100))?;
-            } else if height_cm < 100 {
-                let _ = self.send_command(&format!("down {}", 100 - height_cm))?;
-            }
-        }
+        let mut queue = self.command_queue.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        let sender = queue.get_or_insert_with(|| {
+            let (sender, commands) = mpsc::channel();
+            let transport = Arc::clone(&self.transport);
+            let command_log = self.command_log.clone();
+            let retries = self.command_retries;
+            let logger = Arc::clone(&self.logger);
+            thread::spawn(move || run_command_queue(commands, &transport, command_log.as_ref(), retries, &logger));
+            sender
+        });
         
-        Ok(())
+        let (reply, response) = mpsc::channel();
+        if sender.send((command.to_string(), reply)).is_err() {
+            // The worker is gone, so the next command starts a new one
+            *queue = None;
+            return Err(TelloError::ConnectionLost);
+        }
+        Ok(response)
+    }
+    
+    /// Let the command queue thread finish the queued commands and exit
+    fn stop_command_queue(&mut self) {
+        *self.command_queue.lock().unwrap_or_else(|poisoned| poisoned.into_inner()) = None;
+    }
+    
+    /// Get the battery level in percent
+    pub fn get_battery(&self) -> TelloResult<u8> {
+        let response = self.send_command_with_response("battery?", DEFAULT_QUERY_TIMEOUT)?;
+        parse_battery(&response)
+    }
+    
+    /// Get the WiFi signal-to-noise ratio reported by the drone
+    pub fn get_wifi_snr(&self) -> TelloResult<i32> {
+        let response = self.send_command_with_response("wifi?", DEFAULT_QUERY_TIMEOUT)?;
+        parse_wifi_snr(&response)
+    }
+    
+    /// Get the SDK version of the drone, e.g. "30"
+    pub fn get_sdk_version(&self) -> TelloResult<String> {
+        self.query_text("sdk?")
+    }
+    
+    /// Get the serial number of the drone
+    pub fn get_serial_number(&self) -> TelloResult<String> {
+        self.query_text("sn?")
+    }
+    
+    /// Send a query whose answer is free text, rejecting empty and error answers
+    fn query_text(&self, command: &str) -> TelloResult<String> {
+        let response = self.send_command_with_response(command, DEFAULT_QUERY_TIMEOUT)?;
+        if let Some(reason) = error_reason(&response) {
+            return Err(TelloError::CommandRejected(reason));
+        }
+        if response.is_empty() {
+            return Err(TelloError::InvalidResponse(format!("Empty answer to {}", command)));
+        }
+        Ok(response)
+    }
+    
+    /// Find out which model the drone is from its "sdk?" and "hardware?" answers
+    ///
+    /// The model is kept, and features it lacks such as mission pads are
+    /// then refused with `TelloError::Unsupported` instead of being sent.
+    pub fn detect_model(&mut self) -> TelloResult<TelloModel> {
+        let sdk = rejected_as_none(self.query_text("sdk?"))?;
+        // Only SDK 3.0 answers "hardware?"
+        let sdk_version = sdk.as_deref().and_then(|v| v.trim().parse::<u32>().ok());
+        let hardware = match sdk_version {
+            Some(version) if version >= 30 => rejected_as_none(self.query_text("hardware?"))?,
+            _ => None,
+        };
+        
+        let model = TelloModel::classify(sdk.as_deref(), hardware.as_deref());
+        self.logger.info(format_args!("Detected {} (SDK {})", model, sdk.as_deref().unwrap_or("1.3")));
+        self.model = Some(model);
+        Ok(model)
+    }
+    
+    /// The model found by detect_model, `None` if it wasn't detected yet
+    pub fn model(&self) -> Option<TelloModel> {
+        self.model
+    }
+    
+    /// Refuse mission pad commands on a model known to lack them
+    fn check_mission_pad_support(&self) -> TelloResult<()> {
+        match self.model {
+            Some(model) if !model.has_mission_pads() => Err(TelloError::Unsupported(format!(
+                "the {} has no mission pad detection, it needs a Tello EDU or RoboMaster TT", model,
+            ))),
+            _ => Ok(()),
+        }
+    }
+    
+    /// The serial number and SDK version of the drone
+    ///
+    /// Queried on the first call and cached afterwards, so it is cheap to
+    /// call e.g. to tell the drones of a swarm apart. `reconnect` clears the
+    /// cache.
+    pub fn identity(&mut self) -> TelloResult<DroneIdentity> {
+        if let Some(identity) = &self.identity {
+            return Ok(identity.clone());
+        }
+        
+        let identity = DroneIdentity {
+            serial: self.get_serial_number()?,
+            sdk_version: self.get_sdk_version()?,
+        };
+        self.identity = Some(identity.clone());
+        Ok(identity)
+    }
+    
+    /// Send the information queries and collect their answers
+    ///
+    /// Sends "sdk?", "sn?", "hardware?", "version?", "battery?" and "wifi?"
+    /// and maps each query name without the "?" (e.g. "battery") to its
+    /// answer. Queries that fail or get an error answer are left out.
+    pub fn query_all(&self) -> HashMap<String, String> {
+        INFO_QUERIES.iter()
+            .filter_map(|&query| {
+                let response = self.query_text(query).ok()?;
+                Some((query.trim_end_matches('?').to_string(), response))
+            })
+            .collect()
+    }
+    
+    /// Change the SSID and password of the drone's own WiFi hotspot
+    ///
+    /// The drone restarts its hotspot with the new credentials, so the
+    /// computer has to join the new network afterwards.
+    pub fn set_wifi(&self, ssid: &str, password: &str) -> TelloResult<()> {
+        check_wifi_credentials(ssid, password)?;
+        
+        let response = self.send_command(&format!("wifi {} {}", ssid, password))?;
+        if response != "ok" {
+            return Err(TelloError::CommandRejected(response));
+        }
+        Ok(())
+    }
+    
+    /// Switch the drone to station mode so it joins an existing WiFi network (Tello EDU only)
+    ///
+    /// The drone reboots and connects to the given access point, so this
+    /// instance can no longer reach it. Look up the IP the router assigned
+    /// to the drone and create a new instance with `Tello::builder().ip(...)`.
+    pub fn connect_to_ap(&self, ssid: &str, password: &str) -> TelloResult<()> {
+        check_wifi_credentials(ssid, password)?;
+        
+        let response = self.send_command(&format!("ap {} {}", ssid, password))?;
+        if response != "ok" {
+            return Err(TelloError::CommandRejected(response));
+        }
+        Ok(())
+    }
+    
+    /// Reboot the drone, e.g. to recover from a stuck state without pulling the battery
+    ///
+    /// Sends "reboot", which the drone doesn't answer, so this returns once
+    /// the command is sent. Refused while flying, since the motors stop.
+    /// The link is gone while the drone restarts: call `reconnect` once it
+    /// is back up, which also detects the model again.
+    pub fn reboot(&self) -> TelloResult<()> {
+        if self.is_flying() {
+            return Err(TelloError::InvalidState(String::from("Land before rebooting the drone")));
+        }
+        
+        self.send_command_without_response("reboot")?;
+        self.logger.info(format_args!("Drone is rebooting, call reconnect() once it is back up"));
+        Ok(())
+    }
+    
+    /// Resend a command up to `count` times when the drone doesn't answer
+    ///
+    /// The waits between tries start at 100 ms and double each time, up to
+    /// 6.4 s. Rejections such as "error Motor stop" are never retried. The
+    /// default is 0. Note that a move whose answer was lost has usually been
+    /// flown, so a retry may fly it twice.
+    pub fn set_command_retries(&mut self, count: u32) {
+        self.command_retries = count;
+        // The queue thread keeps the count it was started with
+        self.stop_command_queue();
+    }
+    
+    /// Require `arm` before takeoff, motor start and moves
+    ///
+    /// Guards against a mistyped command lifting off. Landing, stopping and
+    /// the emergency stop always work. Off by default.
+    pub fn set_require_arming(&mut self, require: bool) {
+        self.require_arming = require;
+    }
+    
+    /// Whether takeoff and moves wait for `arm`
+    pub fn requires_arming(&self) -> bool {
+        self.require_arming
+    }
+    
+    /// Allow takeoff and moves when arming is required
+    pub fn arm(&mut self) {
+        self.armed = true;
+    }
+    
+    /// Refuse takeoff and moves again until the next `arm`
+    ///
+    /// A flying drone keeps hovering and can still land.
+    pub fn disarm(&mut self) {
+        self.armed = false;
+    }
+    
+    /// Whether the drone is armed
+    pub fn is_armed(&self) -> bool {
+        self.armed
+    }
+    
+    /// Refuse `command` if it needs arming and the drone isn't armed
+    pub(crate) fn check_armed(&self, command: &str) -> TelloResult<()> {
+        if self.require_arming && !self.armed && needs_arming(command) {
+            return Err(TelloError::InvalidState(
+                format!("The drone is disarmed, arm it before '{}'", command),
+            ));
+        }
+        Ok(())
+    }
+    
+    /// Set the minimum battery level in percent required for takeoff
+    ///
+    /// A value of 0 disables the battery check.
+    pub fn set_min_takeoff_battery(&mut self, percent: u8) {
+        self.min_takeoff_battery = percent;
+    }
+    
+    /// Set the battery level in percent below which the drone lands on its own
+    ///
+    /// Checked for every state packet while flying. The default is 5%,
+    /// a value of 0 disables the automatic landing.
+    pub fn set_critical_battery(&mut self, percent: u8) {
+        self.critical_battery.store(percent, Ordering::Relaxed);
+    }
+    
+    /// Set the temperature in degrees Celsius above which a warning is printed
+    ///
+    /// The drone throttles and eventually lands when it overheats. The
+    /// default is 85°C.
+    pub fn set_temperature_warning(&mut self, celsius: i32) {
+        self.temperature_warning.store(celsius, Ordering::Relaxed);
+    }
+    
+    /// Get the lowest and highest temperature in degrees Celsius from the latest state packet
+    pub fn get_temperature(&self) -> TelloResult<(i32, i32)> {
+        let telemetry = self.get_telemetry().ok_or_else(|| TelloError::InvalidState(
+            String::from("No state received from the drone yet"),
+        ))?;
+        
+        match (telemetry.templ, telemetry.temph) {
+            (Some(low), Some(high)) => Ok((low, high)),
+            _ => Err(TelloError::InvalidResponse(
+                String::from("The state packet has no temperature"),
+            )),
+        }
+    }
+    
+    /// The height in cm the drone reports in the latest state packet
+    ///
+    /// Comparing it with the tracked `get_position().z` shows how far the
+    /// dead reckoning has drifted.
+    pub fn get_height_cm(&self) -> Option<i32> {
+        self.get_telemetry()?.h
+    }
+    
+    /// The time-of-flight distance to the ground in cm from the latest state packet
+    pub fn get_tof_cm(&self) -> Option<i32> {
+        self.get_telemetry()?.tof
+    }
+    
+    /// The speed along x, y and z in cm/s from the latest state packet
+    ///
+    /// Unlike the tracked position, which only follows the commands sent,
+    /// this is measured by the drone. `None` without a state packet that
+    /// has all three values.
+    pub fn get_velocity(&self) -> Option<(i32, i32, i32)> {
+        let state = self.get_telemetry()?;
+        Some((state.vgx?, state.vgy?, state.vgz?))
+    }
+    
+    /// Whether the drone took off and has not landed since
+    pub fn is_flying(&self) -> bool {
+        self.flight_time().is_some()
+    }
+    
+    /// How long the drone has been in the air since takeoff
+    ///
+    /// `None` while on the ground.
+    pub fn flight_time(&self) -> Option<Duration> {
+        lock_takeoff_time(&self.takeoff_time).map(|takeoff| takeoff.elapsed())
+    }
+    
+    /// Get the motor on time in seconds reported by the drone
+    pub fn get_motor_time(&self) -> TelloResult<u32> {
+        let response = self.send_command_with_response("time?", DEFAULT_QUERY_TIMEOUT)?;
+        parse_motor_time(&response)
+    }
+    
+    /// Set a ceiling in centimeters that takeoff, up and go must not exceed
+    pub fn set_max_altitude(&mut self, cm: i32) {
+        self.max_altitude = Some(cm);
+    }
+    
+    /// Remove the altitude ceiling
+    pub fn clear_max_altitude(&mut self) {
+        self.max_altitude = None;
+    }
+    
+    /// Get the altitude ceiling in centimeters, if one is set
+    pub fn max_altitude(&self) -> Option<i32> {
+        self.max_altitude
+    }
+    
+    /// Reject a target altitude in centimeters above the configured ceiling
+    pub(crate) fn check_altitude(&self, target_cm: i32) -> TelloResult<()> {
+        match self.max_altitude {
+            Some(max) if target_cm > max => Err(TelloError::InvalidParameter(format!(
+                "Altitude {} cm would exceed the {} cm ceiling by {} cm",
+                target_cm, max, target_cm - max
+            ))),
+            _ => Ok(()),
+        }
+    }
+    
+    /// Keep the tracked position inside a box, given by two corners in meters
+    ///
+    /// Moves that would leave the box are refused before anything is sent,
+    /// and takeoff heights are clamped into it.
+    pub fn set_geofence(&mut self, min: Position, max: Position) {
+        let low = Position { x: min.x.min(max.x), y: min.y.min(max.y), z: min.z.min(max.z) };
+        let high = Position { x: min.x.max(max.x), y: min.y.max(max.y), z: min.z.max(max.z) };
+        self.geofence = Some((low, high));
+    }
+    
+    /// Remove the geofence
+    pub fn clear_geofence(&mut self) {
+        self.geofence = None;
+    }
+    
+    /// Get the geofence corners (min, max), if one is set
+    pub fn geofence(&self) -> Option<(Position, Position)> {
+        self.geofence
+    }
+    
+    /// Reject a target position outside the geofence
+    pub(crate) fn check_geofence(&self, target: Position) -> TelloResult<()> {
+        let Some((min, max)) = self.geofence else {
+            return Ok(());
+        };
+        
+        for (axis, value, low, high) in [("x", target.x, min.x, max.x), ("y", target.y, min.y, max.y), ("z", target.z, min.z, max.z)] {
+            if value < low - 1e-3 || value > high + 1e-3 {
+                return Err(TelloError::InvalidParameter(format!(
+                    "Position ({:.2}, {:.2}, {:.2}) would leave the geofence: {} = {:.2} m is outside {:.2}..{:.2} m",
+                    target.x, target.y, target.z, axis, value, low, high
+                )));
+            }
+        }
+        
+        Ok(())
+    }
+    
+    /// Take off
+    /// 
+    /// Optional height parameter in meters (default: 1m, max: 8m).
+    /// Takeoff is refused if the target height is above the altitude ceiling.
+    /// Takeoff is refused if the battery is below the configured minimum.
+    /// With a geofence the height is clamped into it.
+    pub fn takeoff(&self, height: Option<f32>) -> TelloResult<()> {
+        let height = match self.geofence {
+            Some((min, max)) => {
+                let requested = height.unwrap_or(1.0);
+                let clamped = requested.clamp(min.z, max.z);
+                if clamped != requested {
+                    self.logger.warn(format_args!("Takeoff height {}m is outside the geofence, using {}m", requested, clamped));
+                    Some(clamped)
+                } else {
+                    height
+                }
+            },
+            None => height,
+        };
+        
+        // Don't climb through the ceiling; the drone hovers at about 1m after "takeoff"
+        let target_cm = match height {
+            Some(h) if h > 0.0 && h <= 8.0 => (h * 100.0) as i32,
+            _ => 100,
+        };
+        self.check_altitude(target_cm)?;
+        
+        // Check the battery before leaving the ground
+        if self.min_takeoff_battery > 0 {
+            check_takeoff_battery(self.get_battery()?, self.min_takeoff_battery)?;
+        }
+        
+        // First issue standard takeoff command
+        let response = self.send_command("takeoff")?;
+        
+        if response != "ok" {
+            return Err(TelloError::CommandRejected(response));
+        }
+        *lock_takeoff_time(&self.takeoff_time) = Some(Instant::now());
+        
+        // If a specific height was requested
+        if let Some(h) = height {
+            if h > 8.0 {
+                self.logger.warn(format_args!("Requested height {}m exceeds maximum. Using default height (1m).", h));
+                return Ok(());
+            }
+            
+            if h <= 0.0 {
+                self.logger.warn(format_args!("Invalid height value ({}m). Using default height (1m).", h));
+                return Ok(());
+            }
+            
+            // Convert height to centimeters for the command
+            let height_cm = (h * 100.0) as i32;
+            
+            // Move from the 1m takeoff height to the desired one
+            if height_cm > 100 {
+                self.adjust_takeoff_height(&format!("up {}", height_cm - 100))?;
+            } else if height_cm < 100 {
+                self.adjust_takeoff_height(&format!("down {}", 100 - height_cm))?;
+            }
+        }
+        
+        Ok(())
+    }
+    
+    /// Send the height adjustment after takeoff once the drone has settled
+    ///
+    /// A drone that is still stabilizing rejects moves, so a rejected
+    /// adjustment is tried once more after another wait.
+    fn adjust_takeoff_height(&self, command: &str) -> TelloResult<()> {
+        thread::sleep(self.takeoff_stabilization);
+        match self.send_command(command) {
+            Ok(response) if response == "ok" => return Ok(()),
+            Ok(response) | Err(TelloError::CommandRejected(response)) =>
+                self.logger.warn(format_args!("Height adjustment '{}' was rejected ({}), retrying", command, response)),
+            Err(e) => return Err(e),
+        }
+        
+        thread::sleep(self.takeoff_stabilization);
+        let response = self.send_command(command)?;
+        if response != "ok" {
+            return Err(TelloError::CommandRejected(response));
+        }
+        Ok(())
+    }
+    
+    /// Set how long to wait after takeoff before adjusting to the requested height
+    ///
+    /// The drone is still stabilizing right after "takeoff" and often rejects
+    /// a move. Defaults to 2 seconds.
+    pub fn set_takeoff_stabilization(&mut self, delay: Duration) {
+        self.takeoff_stabilization = delay;
+    }
+    
+    /// Take off by throwing the drone
+    ///
+    /// After "throwfly" the motors start once the drone is tossed into the
+    /// air. The drone only answers when it detects the throw, so a missing
+    /// answer within the timeout is not a failure; it may still be waiting.
+    /// The drone then counts as flying, so the landing and reboot guards
+    /// stay on until it is landed.
+    /// The launch height is unknown, so the tracked position is not changed;
+    /// use `set_position` once the drone hovers if position tracking matters.
+    pub fn throw_takeoff(&self) -> TelloResult<()> {
+        if self.min_takeoff_battery > 0 {
+            check_takeoff_battery(self.get_battery()?, self.min_takeoff_battery)?;
+        }
+        
+        match self.send_command_with_response("throwfly", THROW_TAKEOFF_TIMEOUT) {
+            Ok(response) if response == "ok" => {
+                *lock_takeoff_time(&self.takeoff_time) = Some(Instant::now());
+                Ok(())
+            },
+            Ok(response) => Err(TelloError::CommandRejected(response)),
+            Err(TelloError::Timeout) => {
+                *lock_takeoff_time(&self.takeoff_time) = Some(Instant::now());
+                self.logger.warn(format_args!("No throw detected yet, the drone may still be waiting for it"));
+                Ok(())
+            },
+            Err(e) => Err(e),
+        }
     }
     
     /// Land the drone
-    pub fn land(&self) -> io::Result<()> {
+    pub fn land(&self) -> TelloResult<()> {
         let response = self.send_command("land")?;
         
         if response != "ok" {
-            return Err(io::Error::new(
-                io::ErrorKind::Other,
-                format!("Landing command failed: {}", response),
+            return Err(TelloError::CommandRejected(response));
+        }
+        *lock_takeoff_time(&self.takeoff_time) = None;
+        
+        Ok(())
+    }
+    
+    /// Land, and cut the motors if the drone won't confirm it and `force` is set
+    ///
+    /// A "land" that times out or is refused, e.g. while the motors are
+    /// locked, is sent once more. If that fails too, `force` escalates to
+    /// `emergency`, which stops the motors at whatever height the drone is;
+    /// without it the error of the second attempt is returned.
+    pub fn land_with_options(&self, force: bool) -> TelloResult<()> {
+        let error = match self.land() {
+            Err(e @ (TelloError::Timeout | TelloError::CommandRejected(_))) => {
+                self.logger.warn(format_args!("Land failed ({}), retrying", e));
+                match self.land() {
+                    Err(e @ (TelloError::Timeout | TelloError::CommandRejected(_))) => e,
+                    result => return result,
+                }
+            },
+            result => return result,
+        };
+        
+        if !force {
+            return Err(error);
+        }
+        self.logger.warn(format_args!("Land failed again ({}), sending emergency", error));
+        self.emergency()
+    }
+    
+    /// Land and wait until the telemetry shows the drone on the ground
+    ///
+    /// See `wait_until_landed`.
+    pub fn land_and_wait(&self, timeout: Duration) -> TelloResult<()> {
+        self.land()?;
+        self.wait_until_landed(timeout)
+    }
+    
+    /// Wait until the telemetry shows the drone on the ground
+    ///
+    /// The drone answers "land" before the descent is over, so turning the
+    /// motors off or disconnecting right after it happens in the air. This
+    /// polls the height of the state packets ("h", or "tof" if the drone
+    /// doesn't send it) until it reads at most 10 cm. Returns
+    /// `TelloError::Timeout` if that doesn't happen within `timeout`, and
+    /// `TelloError::InvalidState` if no state is received at all.
+    pub fn wait_until_landed(&self, timeout: Duration) -> TelloResult<()> {
+        if self.state_receiver.is_none() {
+            return Err(TelloError::InvalidState(
+                String::from("No state is received from the drone, so the landing can't be confirmed"),
+            ));
+        }
+        
+        let deadline = Instant::now() + timeout;
+        loop {
+            let height = self.get_telemetry().and_then(|state| state.h.or(state.tof));
+            if height.is_some_and(|height| height <= LANDED_HEIGHT_CM) {
+                return Ok(());
+            }
+            
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                return Err(TelloError::Timeout);
+            }
+            thread::sleep(LANDING_POLL_INTERVAL.min(remaining));
+        }
+    }
+    
+    /// Emergency stop: cut all motors immediately
+    ///
+    /// The drone may lose power before replying, so a missing or non-"ok"
+    /// response is not treated as a failure once the command was sent.
+    pub fn emergency(&self) -> TelloResult<()> {
+        let result = self.send_command("emergency");
+        if !matches!(result, Err(TelloError::NotConnected)) {
+            *lock_takeoff_time(&self.takeoff_time) = None;
+        }
+        
+        match result {
+            Ok(response) | Err(TelloError::CommandRejected(response)) => {
+                if response != "ok" {
+                    self.logger.warn(format_args!("Emergency command sent, drone replied: {}", response));
+                }
+                Ok(())
+            },
+            Err(TelloError::Timeout) => {
+                self.logger.warn(format_args!("Emergency command sent, no response from drone"));
+                Ok(())
+            },
+            Err(e) => Err(e),
+        }
+    }
+    
+    /// Spin the motors at idle without taking off
+    ///
+    /// Useful for bench tests and for cooling the drone on the ground.
+    pub fn motor_on(&self) -> TelloResult<()> {
+        let response = self.send_command("motoron")?;
+        if response != "ok" {
+            return Err(TelloError::CommandRejected(response));
+        }
+        Ok(())
+    }
+    
+    /// Stop motors started with motor_on
+    ///
+    /// Refused while flying, since it would drop the drone.
+    pub fn motor_off(&self) -> TelloResult<()> {
+        if self.is_flying() {
+            return Err(TelloError::InvalidState(
+                String::from("The drone is flying, land before turning the motors off"),
+            ));
+        }
+        
+        let response = self.send_command("motoroff")?;
+        if response != "ok" {
+            return Err(TelloError::CommandRejected(response));
+        }
+        Ok(())
+    }
+    
+    /// Calibrate the IMU
+    ///
+    /// Needs firmware that accepts "calibrate" over the SDK. The drone must
+    /// rest on a flat surface; it answers once done, which takes 10 seconds
+    /// or more, so the answer is awaited for up to 30 seconds.
+    pub fn calibrate_imu(&self) -> TelloResult<()> {
+        if self.is_flying() {
+            return Err(TelloError::InvalidState(
+                String::from("The drone is flying, land before calibrating"),
+            ));
+        }
+        
+        let response = self.send_command("calibrate")?;
+        if response != "ok" {
+            return Err(TelloError::CommandRejected(response));
+        }
+        Ok(())
+    }
+    
+    /// Enable mission pad detection (Tello EDU only)
+    ///
+    /// The detected pad id and the position relative to it are then
+    /// reported in the telemetry fields mid, x, y and z.
+    pub fn enable_mission_pads(&mut self) -> TelloResult<()> {
+        self.check_mission_pad_support()?;
+        
+        let response = self.send_command("mon")?;
+        if response != "ok" {
+            return Err(TelloError::CommandRejected(response));
+        }
+        self.mission_pads_enabled = true;
+        Ok(())
+    }
+    
+    /// Disable mission pad detection
+    pub fn disable_mission_pads(&mut self) -> TelloResult<()> {
+        let response = self.send_command("moff")?;
+        if response != "ok" {
+            return Err(TelloError::CommandRejected(response));
+        }
+        self.mission_pads_enabled = false;
+        Ok(())
+    }
+    
+    /// Whether mission pad detection was enabled with enable_mission_pads
+    pub fn mission_pads_enabled(&self) -> bool {
+        self.mission_pads_enabled
+    }
+    
+    /// Set which camera is used for mission pad detection
+    ///
+    /// 0 = downward only, 1 = forward only, 2 = both.
+    /// Detection must be enabled first.
+    pub fn set_mission_pad_detection_direction(&self, direction: u8) -> TelloResult<()> {
+        if direction > 2 {
+            return Err(TelloError::InvalidParameter(format!(
+                "Invalid mission pad direction: {}. Should be 0 (down), 1 (forward) or 2 (both)",
+                direction
+            )));
+        }
+        if !self.mission_pads_enabled {
+            return Err(TelloError::InvalidState(
+                "Mission pad detection is off. Enable it first".to_string(),
             ));
         }
         
+        let response = self.send_command(&format!("mdirection {}", direction))?;
+        if response != "ok" {
+            return Err(TelloError::CommandRejected(response));
+        }
         Ok(())
     }
     
+    /// Use `command` to take photos instead of finding it out
+    ///
+    /// `take_photo` then sends only this command, e.g. "takepic".
+    pub fn set_photo_command(&mut self, command: &str) {
+        *self.photo_command.get_mut().unwrap_or_else(|poisoned| poisoned.into_inner()) = Some(command.to_string());
+    }
+    
     /// Take a photo
-    pub fn take_photo(&self) -> io::Result<String> {
+    ///
+    /// Sends the command set with `set_photo_command`, or the one of the
+    /// model found by `detect_model`. Otherwise "snapshot" and then
+    /// "takepic" are tried, and the one that worked is kept for the next
+    /// photos.
+    pub fn take_photo(&self) -> TelloResult<String> {
+        let known = self.photo_command.lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .clone()
+            .or_else(|| self.model.map(|model| model.photo_command().to_string()));
+        if let Some(command) = known {
+            let response = self.send_command(&command)?;
+            if response == "ok" {
+                self.remember_photo_command(&command);
+            }
+            return Ok(response);
+        }
+        
         // Tello EDU SDK uses "takepic" command, but other models may vary
         // Let's try multiple possible commands
         
-        println!("Attempting to take photo with different commands...");
+        self.logger.debug(format_args!("Attempting to take photo with different commands..."));
         
         // First attempt - "snapshot" command
         let response1 = self.send_command("snapshot");
         
         if let Ok(ref resp) = response1 {
             if resp == "ok" {
-                println!("Photo captured successfully with 'snapshot' command.");
-                println!("To download, use 'media download <filename>' command.");
+                self.logger.info(format_args!("Photo captured successfully with 'snapshot' command."));
+                self.remember_photo_command("snapshot");
                 return Ok(resp.clone());
             }
         }
@@ -276,15 +1826,15 @@ impl Tello {
         
         if let Ok(resp) = response2 {
             if resp == "ok" {
-                println!("Photo captured successfully with 'takepic' command.");
-                println!("To download, use 'media download <filename>' command.");
+                self.logger.info(format_args!("Photo captured successfully with 'takepic' command."));
+                self.remember_photo_command("takepic");
                 return Ok(resp);
             }
         }
         
         // If both commands failed, return error
-        println!("Note: Photo might not be saved in internal memory on this drone model.");
-        println!("Some Tello models only save screenshots via the official app.");
+        self.logger.info(format_args!("Note: Photo might not be saved in internal memory on this drone model."));
+        self.logger.info(format_args!("Some Tello models only save screenshots via the official app."));
         
         // Return the result of the first attempt as the primary one
         match response1 {
@@ -293,78 +1843,257 @@ impl Tello {
         }
     }
     
+    /// Keep the command that took a photo, so the next photo doesn't probe
+    fn remember_photo_command(&self, command: &str) {
+        *self.photo_command.lock().unwrap_or_else(|poisoned| poisoned.into_inner()) = Some(command.to_string());
+    }
+    
+    /// Capture one frame of the video stream and save it as a JPEG
+    ///
+    /// Without a path the photo is saved in the download path with a
+    /// timestamped name. The stream is switched on for the capture if it
+    /// isn't running. Drones that stream H.264 instead of JPEG frames yield
+    /// `TelloError::Unsupported`; use `take_photo` and download the file instead.
+    pub fn capture_photo_to(&mut self, path: Option<&str>) -> TelloResult<PathBuf> {
+        if !self.is_connected() {
+            return Err(TelloError::NotConnected);
+        }
+        
+        if self.video_thread.is_some() {
+            return Err(TelloError::InvalidState(
+                String::from("The video stream is being recorded, stop the recording first"),
+            ));
+        }
+        
+        let dest_path = match path {
+            Some(path) => PathBuf::from(path),
+            None => Path::new(&self.download_path)
+                .join(chrono::Local::now().format("photo_%Y%m%d_%H%M%S.jpg").to_string()),
+        };
+        
+        // Bind before switching the stream on so the first packets aren't lost
+        let video_socket = UdpSocket::bind(self.bind_socket_addr(self.video_port))?;
+        
+        let stream_was_on = self.video_recording;
+        if !stream_was_on {
+            let response = self.send_command("streamon")?;
+            if response != "ok" {
+                return Err(TelloError::CommandRejected(response));
+            }
+        }
+        
+        let frame = receive_jpeg_frame(&video_socket, FRAME_TIMEOUT);
+        
+        // Leave the stream as we found it
+        if !stream_was_on {
+            if let Err(e) = self.send_command("streamoff") {
+                self.logger.warn(format_args!("Failed to switch the video stream off after capture: {}", e));
+            }
+        }
+        
+        fs::write(&dest_path, frame?)?;
+        Ok(dest_path)
+    }
+    
     /// Start video recording
-    pub fn start_video(&mut self) -> io::Result<String> {
+    pub fn start_video(&mut self) -> TelloResult<String> {
         if self.video_recording {
-            return Err(io::Error::new(
-                io::ErrorKind::AlreadyExists,
-                "Video recording is already in progress",
+            return Err(TelloError::InvalidState(
+                String::from("Video recording is already in progress"),
             ));
         }
         
         let response = self.send_command("streamon")?;
         
         if response != "ok" {
-            return Err(io::Error::new(
-                io::ErrorKind::Other,
-                format!("Failed to start video streaming: {}", response),
-            ));
+            return Err(TelloError::CommandRejected(response));
         }
         
         self.video_recording = true;
-        println!("Video recording started");
+        self.logger.info(format_args!("Video recording started"));
         Ok(response)
     }
     
-    /// Stop video recording
-    pub fn stop_video(&mut self) -> io::Result<String> {
-        if !self.video_recording {
-            return Err(io::Error::new(
-                io::ErrorKind::Other,
-                "Video recording is not in progress",
+    /// Record the H.264 video stream to a file
+    ///
+    /// Switches the stream on if needed and appends every received packet
+    /// to `path` (usually a ".h264" file) from a background thread until
+    /// `stop_video` is called.
+    pub fn record_video_to(&mut self, path: &str) -> TelloResult<()> {
+        if !self.is_connected() {
+            return Err(TelloError::NotConnected);
+        }
+        if self.video_thread.is_some() {
+            return Err(TelloError::InvalidState(
+                String::from("The video stream is already being recorded"),
             ));
         }
         
+        // Bind first so a busy port leaves no empty file behind
+        let video_socket = UdpSocket::bind(self.bind_socket_addr(self.video_port))?;
+        video_socket.set_read_timeout(Some(Duration::from_millis(500)))?;
+        let mut file = fs::File::create(path)?;
+        
+        if !self.video_recording {
+            self.start_video()?;
+        }
+        
+        let stop = Arc::new(AtomicBool::new(false));
+        self.video_stop = Arc::clone(&stop);
+        
+        let file_path = path.to_string();
+        let logger = Arc::clone(&self.logger);
+        self.video_thread = Some(thread::spawn(move || {
+            let mut buffer = [0; 2048];
+            
+            while !stop.load(Ordering::Relaxed) {
+                match video_socket.recv_from(&mut buffer) {
+                    Ok((amount, _)) => {
+                        if let Err(e) = file.write_all(&buffer[..amount]) {
+                            logger.warn(format_args!("Failed to write video to {}, recording stopped: {}", file_path, e));
+                            break;
+                        }
+                    },
+                    Err(e) => {
+                        if e.kind() != io::ErrorKind::WouldBlock && e.kind() != io::ErrorKind::TimedOut {
+                            logger.warn(format_args!("Error receiving video: {}", e));
+                        }
+                    }
+                }
+            }
+        }));
+        
+        Ok(())
+    }
+    
+    /// Stop the video recorder thread and wait for it to exit
+    fn stop_video_recorder(&mut self) {
+        self.video_stop.store(true, Ordering::Relaxed);
+        if let Some(handle) = self.video_thread.take() {
+            if handle.join().is_err() {
+                self.logger.warn(format_args!("Video recorder thread panicked"));
+            }
+        }
+    }
+    
+    /// Stop video recording
+    ///
+    /// Also ends a recording started with `record_video_to`.
+    pub fn stop_video(&mut self) -> TelloResult<String> {
+        if !self.video_recording {
+            return Err(TelloError::InvalidState(
+                String::from("Video recording is not in progress"),
+            ));
+        }
+        
+        self.stop_video_recorder();
+        
         let response = self.send_command("streamoff")?;
         
         if response != "ok" {
-            return Err(io::Error::new(
-                io::ErrorKind::Other,
-                format!("Failed to stop video streaming: {}", response),
-            ));
+            return Err(TelloError::CommandRejected(response));
         }
         
         self.video_recording = false;
-        println!("Video recording stopped. To download, use 'download_media' command.");
+        self.logger.info(format_args!("Video recording stopped. To download, use 'download_media' command."));
         Ok(response)
     }
     
+    /// Set the video resolution (Tello EDU)
+    ///
+    /// Meant to be called before `start_video`; a warning is printed if the
+    /// stream is already on.
+    pub fn set_video_resolution(&self, resolution: VideoResolution) -> TelloResult<()> {
+        self.send_video_setting(&format!("setresolution {}", resolution.as_str()))
+    }
+    
+    /// Set the video frame rate (Tello EDU)
+    ///
+    /// Meant to be called before `start_video`; a warning is printed if the
+    /// stream is already on.
+    pub fn set_video_fps(&self, fps: VideoFps) -> TelloResult<()> {
+        self.send_video_setting(&format!("setfps {}", fps.as_str()))
+    }
+    
+    /// Switch the video stream between the forward and downward camera (Tello EDU)
+    ///
+    /// Sends "downvision 0" or "downvision 1". Mission pads are detected with
+    /// the downward camera, so switching it to the stream lets you watch
+    /// what the pad detection sees.
+    pub fn set_camera(&self, camera: CameraSelect) -> TelloResult<()> {
+        if let Some(model) = self.model.filter(|model| !model.has_mission_pads()) {
+            return Err(TelloError::Unsupported(format!("the {} can't stream its downward camera", model)));
+        }
+        
+        let value = match camera {
+            CameraSelect::Forward => 0,
+            CameraSelect::Downward => 1,
+        };
+        let response = self.send_command(&format!("downvision {}", value))?;
+        if response != "ok" {
+            return Err(TelloError::CommandRejected(response));
+        }
+        Ok(())
+    }
+    
+    fn send_video_setting(&self, command: &str) -> TelloResult<()> {
+        if self.video_recording {
+            self.logger.warn(format_args!("The video stream is already on, '{}' may only apply after restarting it", command));
+        }
+        
+        let response = self.send_command(command)?;
+        if response != "ok" {
+            return Err(TelloError::CommandRejected(response));
+        }
+        Ok(())
+    }
+    
+    /// Set the video bitrate: 0 = auto, 1-5 = 1-5 Mbps
+    ///
+    /// A lower bitrate loses fewer packets on a weak WiFi link.
+    pub fn set_video_bitrate(&self, level: u8) -> TelloResult<()> {
+        if level > 5 {
+            return Err(TelloError::InvalidParameter(
+                format!("Invalid bitrate: {}. Should be between 0 (auto) and 5.", level),
+            ));
+        }
+        
+        let response = self.send_command(&format!("setbitrate {}", level))?;
+        if response != "ok" {
+            return Err(TelloError::CommandRejected(response));
+        }
+        Ok(())
+    }
+    
+    /// Get the video bitrate level: 0 = auto, 1-5 = 1-5 Mbps
+    pub fn get_video_bitrate(&self) -> TelloResult<u8> {
+        let response = self.send_command_with_response("bitrate?", DEFAULT_QUERY_TIMEOUT)?;
+        parse_bitrate(&response)
+    }
+    
     /// List media files on drone
-    pub fn list_media(&self) -> io::Result<Vec<String>> {
-        println!("Attempting to list media files on drone...");
+    pub fn list_media(&self) -> TelloResult<Vec<String>> {
+        self.logger.debug(format_args!("Attempting to list media files on drone..."));
         
         // The standard command to list files
         let response = self.send_command("ls")?;
         
         // If we received a response like "No files found" from our modified send_command
         if response == "No files found" {
-            println!("No media files found on the drone.");
+            self.logger.info(format_args!("No media files found on the drone."));
             return Ok(vec![]);
         }
         
         // Check for explicit error messages
         if response.contains("error") || response.contains("Error") {
-            return Err(io::Error::new(
-                io::ErrorKind::Other,
-                format!("Failed to list media: {}", response),
-            ));
+            return Err(TelloError::CommandRejected(response));
         }
         
         // Check if we received telemetry data instead of a file list
         if response.contains("pitch:") && response.contains("roll:") && response.contains("yaw:") {
-            println!("Received telemetry data instead of file listing.");
-            println!("Note: Media listing may not be supported on this Tello model.");
-            println!("Consider using the official Tello app to manage media files.");
+            self.logger.info(format_args!("Received telemetry data instead of file listing."));
+            self.logger.info(format_args!("Note: Media listing may not be supported on this Tello model."));
+            self.logger.info(format_args!("Consider using the official Tello app to manage media files."));
             return Ok(vec![]);
         }
         
@@ -376,539 +2105,3463 @@ impl Tello {
             .collect();
         
         if files.is_empty() {
-            println!("No media files found on the drone.");
+            self.logger.info(format_args!("No media files found on the drone."));
         } else {
-            println!("Found {} media files on the drone.", files.len());
+            self.logger.info(format_args!("Found {} media files on the drone.", files.len()));
         }
             
         Ok(files)
     }
     
+    /// List media files on drone with their kind and, if reported, their size
+    pub fn list_media_detailed(&self) -> TelloResult<Vec<MediaFile>> {
+        Ok(self.list_media()?
+            .iter()
+            .filter_map(|line| MediaFile::parse(line))
+            .collect())
+    }
+    
     /// Download media file from drone
-    pub fn download_media(&self, filename: &str) -> io::Result<String> {
+    pub fn download_media(&self, filename: &str) -> TelloResult<String> {
         // Create directory if it doesn't exist
-        if !Path::new(&self.download_path).exists() {
-            fs::create_dir_all(&self.download_path)?;
+        let dir = self.media_download_dir();
+        if !Path::new(&dir).exists() {
+            fs::create_dir_all(&dir)?;
         }
         
-        let dest_path = format!("{}/{}", self.download_path, filename);
-        println!("Downloading {} to {}...", filename, dest_path);
+        let dest_path = format!("{}/{}", dir, filename);
+        self.logger.info(format_args!("Downloading {} to {}...", filename, dest_path));
         
         // Send download command
         let cmd = format!("download {}", filename);
         let response = self.send_command(&cmd)?;
         
         if response.contains("error") || response.contains("Error") {
-            return Err(io::Error::new(
-                io::ErrorKind::Other,
-                format!("Download failed: {}", response),
+            return Err(TelloError::CommandRejected(response));
+        }
+        
+        // For actual implementation, we would need to set up a TCP server on FILE_TRANSFER_PORT
+        // and handle the file transfer protocol. This is simplified.
+        self.logger.info(format_args!("Download initiated. File will be saved to: {}", dest_path));
+        
+        Ok(format!("Downloaded to {}", dest_path))
+    }
+    
+    /// Download every file in the media listing into the download path
+    ///
+    /// Each file is received over a direct TCP connection like
+    /// `transfer_file_via_direct_connection`, one after the other. Only a
+    /// failed listing is an error; a file that fails to transfer is
+    /// recorded in `failed` and the rest of the batch carries on.
+    pub fn download_all_media(&self) -> TelloResult<MediaDownloads> {
+        let mut downloads = MediaDownloads::default();
+        
+        for file in self.list_media_detailed()? {
+            match self.receive_media(&file.name, file.size, |_, _| {}) {
+                Ok((path, _)) => downloads.saved.push(path),
+                Err(e) => {
+                    self.logger.info(format_args!("Failed to download {}: {}", file.name, e));
+                    downloads.failed.push((file.name, e));
+                },
+            }
+        }
+        
+        Ok(downloads)
+    }
+    
+    /// Delete media file from drone
+    pub fn delete_media(&self, filename: &str) -> TelloResult<String> {
+        let cmd = format!("rm {}", filename);
+        let response = self.send_command(&cmd)?;
+        
+        if response != "ok" {
+            return Err(TelloError::CommandRejected(response));
+        }
+        
+        Ok(format!("Deleted {}", filename))
+    }
+    
+    /// Delete all media files from drone
+    pub fn delete_all_media(&self) -> TelloResult<String> {
+        let response = self.send_command("rmall")?;
+        
+        if response != "ok" {
+            return Err(TelloError::CommandRejected(response));
+        }
+        
+        Ok("All media files deleted".to_string())
+    }
+    
+    /// Rotate clockwise by a specified number of degrees
+    ///
+    /// Values outside 1..=360 are reduced modulo 360 first, and negative
+    /// ones turn counter-clockwise, so 450 turns 90 degrees clockwise and
+    /// -90 turns 90 degrees counter-clockwise. A multiple of 360 sends
+    /// nothing. With `set_strict_rotation(true)` they are rejected instead.
+    pub fn rotate_cw(&mut self, degrees: i32) -> TelloResult<()> {
+        self.rotate(true, degrees)
+    }
+    
+    /// Rotate counter-clockwise by a specified number of degrees
+    ///
+    /// Values are normalized like in `rotate_cw`.
+    pub fn rotate_ccw(&mut self, degrees: i32) -> TelloResult<()> {
+        self.rotate(false, degrees)
+    }
+    
+    /// Reject rotations outside 1..=360 degrees instead of normalizing them
+    pub fn set_strict_rotation(&mut self, strict: bool) {
+        self.strict_rotation = strict;
+    }
+    
+    /// Whether rotations outside 1..=360 are rejected, see set_strict_rotation
+    pub fn strict_rotation(&self) -> bool {
+        self.strict_rotation
+    }
+    
+    fn rotate(&mut self, clockwise: bool, degrees: i32) -> TelloResult<()> {
+        let degrees = if (1..=360).contains(&degrees) {
+            degrees
+        } else if self.strict_rotation {
+            return Err(TelloError::InvalidParameter(
+                format!("Invalid rotation value: {}. Should be between 1 and 360 degrees.", degrees),
             ));
+        } else {
+            degrees % 360
+        };
+        
+        // A negative turn one way is a positive turn the other way
+        let (clockwise, degrees) = if degrees < 0 { (!clockwise, -degrees) } else { (clockwise, degrees) };
+        if degrees == 0 {
+            return Ok(());
+        }
+        
+        let command = if clockwise { "cw" } else { "ccw" };
+        let response = self.send_command(&format!("{} {}", command, degrees))?;
+        
+        if response != "ok" {
+            return Err(TelloError::CommandRejected(response));
+        }
+        
+        // Update current direction
+        let turn = if clockwise { degrees as f32 } else { 360.0 - degrees as f32 };
+        self.current_direction = (self.current_direction + turn) % 360.0;
+        self.record_position();
+        
+        Ok(())
+    }
+    
+    /// Point camera towards center of rotation
+    /// 
+    /// If the drone is positioned at coordinates (x, y) and center is at (center_x, center_y),
+    /// this function will rotate the drone to point its camera towards the center
+    pub fn point_camera_to_center(&mut self, center_x: f32, center_y: f32) -> TelloResult<()> {
+        let dx = center_x - self.current_position.x;
+        let dy = center_y - self.current_position.y;
+        
+        // Calculate angle to center in degrees
+        let target_angle = dy.atan2(dx).to_degrees() + 90.0;
+        let normalized_target = (target_angle + 360.0) % 360.0;
+        
+        // Calculate the shortest rotation to reach the target angle
+        let mut rotation = normalized_target - self.current_direction;
+        if rotation > 180.0 {
+            rotation -= 360.0;
+        } else if rotation < -180.0 {
+            rotation += 360.0;
+        }
+        
+        // Execute the rotation
+        if rotation.abs() < 1.0 {
+            // Already pointing in the right direction
+            return Ok(());
+        } else if rotation > 0.0 {
+            self.rotate_cw(rotation.round() as i32)?;
+        } else {
+            self.rotate_ccw((-rotation).round() as i32)?;
+        }
+        
+        Ok(())
+    }
+    
+    /// Point camera towards a center point in 3D and return the tilt it needs
+    ///
+    /// Turns like `point_camera_to_center` and returns the pitch angle in
+    /// degrees from the drone to the center: negative looks down, positive
+    /// up. The Tello camera cannot tilt, so the angle is informational, e.g.
+    /// for choosing a height. Straight above or below, the heading is kept.
+    pub fn point_camera_to_center_3d(&mut self, center_x: f32, center_y: f32, center_z: f32) -> TelloResult<f32> {
+        let dx = center_x - self.current_position.x;
+        let dy = center_y - self.current_position.y;
+        let dz = center_z - self.current_position.z;
+        let horizontal = dx.hypot(dy);
+        
+        if horizontal > 0.01 {
+            self.point_camera_to_center(center_x, center_y)?;
+        }
+        
+        Ok(dz.atan2(horizontal).to_degrees())
+    }
+    
+    /// Point camera away from center of rotation
+    /// 
+    /// If the drone is positioned at coordinates (x, y) and center is at (center_x, center_y),
+    /// this function will rotate the drone to point its camera away from the center
+    pub fn point_camera_from_center(&mut self, center_x: f32, center_y: f32) -> TelloResult<()> {
+        let dx = center_x - self.current_position.x;
+        let dy = center_y - self.current_position.y;
+        
+        // Calculate angle away from center (opposite to center) in degrees
+        let target_angle = dy.atan2(dx).to_degrees() - 90.0;
+        let normalized_target = (target_angle + 360.0) % 360.0;
+        
+        // Calculate the shortest rotation to reach the target angle
+        let mut rotation = normalized_target - self.current_direction;
+        if rotation > 180.0 {
+            rotation -= 360.0;
+        } else if rotation < -180.0 {
+            rotation += 360.0;
+        }
+        
+        // Execute the rotation
+        if rotation.abs() < 1.0 {
+            // Already pointing in the right direction
+            return Ok(());
+        } else if rotation > 0.0 {
+            self.rotate_cw(rotation.round() as i32)?;
+        } else {
+            self.rotate_ccw((-rotation).round() as i32)?;
+        }
+        
+        Ok(())
+    }
+    
+    /// Set the current position of the drone
+    /// This is for internal tracking and can be used to help with camera positioning
+    pub fn set_position(&mut self, x: f32, y: f32, z: f32) {
+        self.current_position = Position { x, y, z };
+        self.record_position();
+    }
+    
+    /// Get the current position of the drone
+    pub fn get_position(&self) -> Position {
+        self.current_position
+    }
+    
+    /// Get the tracked heading in degrees (0-360, clockwise from the start orientation)
+    pub fn get_direction(&self) -> f32 {
+        self.current_direction
+    }
+    
+    /// Set the tracked heading in degrees, e.g. to match the real drone orientation
+    ///
+    /// The value is normalized into 0..360, so -90 becomes 270.
+    pub fn set_direction(&mut self, degrees: f32) {
+        self.current_direction = normalize_direction(degrees);
+        self.record_position();
+    }
+    
+    /// Update position based on movement
+    pub fn update_position_after_movement(&mut self, direction: &str, distance: i32) {
+        if let Some((forward_m, left_m, up_m)) = movement_offset(direction, distance) {
+            self.move_in_body_frame(forward_m, left_m, up_m);
+        }
+    }
+    
+    /// Update position based on a "go" offset in the drone's body frame
+    ///
+    /// The SDK frame is x = forward, y = left, z = up, all in centimeters.
+    pub fn update_position_after_go(&mut self, x: i32, y: i32, z: i32) {
+        self.move_in_body_frame(x as f32 / 100.0, y as f32 / 100.0, z as f32 / 100.0);
+    }
+    
+    /// Move the tracked position by an offset in meters in the body frame
+    ///
+    /// The world frame is right-handed with +y along heading 0, +x to its
+    /// right and +z up; headings turn clockwise. Forward points along the
+    /// heading and left is 90 degrees counter-clockwise from it.
+    fn move_in_body_frame(&mut self, forward_m: f32, left_m: f32, up_m: f32) {
+        self.current_position = self.project_body_offset(forward_m, left_m, up_m);
+        self.record_position();
+    }
+    
+    /// The tracked position after an offset in meters in the body frame
+    pub(crate) fn project_body_offset(&self, forward_m: f32, left_m: f32, up_m: f32) -> Position {
+        let (sin, cos) = self.current_direction.to_radians().sin_cos();
+        Position {
+            x: self.current_position.x + forward_m * sin - left_m * cos,
+            y: self.current_position.y + forward_m * cos + left_m * sin,
+            z: self.current_position.z + up_m,
+        }
+    }
+    
+    /// Append the tracked position and heading to the history
+    fn record_position(&mut self) {
+        self.position_history.push((Instant::now(), self.current_position, self.current_direction));
+        self.trim_position_history();
+    }
+    
+    /// Drop the oldest history entries beyond the limit
+    fn trim_position_history(&mut self) {
+        let excess = self.position_history.len().saturating_sub(self.position_history_limit);
+        self.position_history.drain(..excess);
+    }
+    
+    /// Every tracked position change with its time and heading, oldest first
+    pub fn position_history(&self) -> &[(Instant, Position, f32)] {
+        &self.position_history
+    }
+    
+    /// Set how many history entries are kept (default 10000)
+    ///
+    /// Long sessions would otherwise grow the history without bound.
+    /// A limit of 0 turns the history off.
+    pub fn set_position_history_limit(&mut self, limit: usize) {
+        self.position_history_limit = limit;
+        self.trim_position_history();
+    }
+    
+    /// Write the position history to a CSV file
+    ///
+    /// The columns are timestamp,x,y,z,heading. The timestamp is in seconds
+    /// since the first entry, the coordinates are in meters and the heading
+    /// in degrees.
+    pub fn export_path_csv(&self, path: &str) -> io::Result<()> {
+        let mut csv = String::from("timestamp,x,y,z,heading\n");
+        
+        if let Some((start, _, _)) = self.position_history.first() {
+            for (time, position, heading) in &self.position_history {
+                csv.push_str(&format!("{:.3},{:.3},{:.3},{:.3},{:.1}\n",
+                    time.duration_since(*start).as_secs_f32(), position.x, position.y, position.z, heading));
+            }
+        }
+        
+        fs::write(path, csv)
+    }
+    
+    /// Transfer file from drone using a direct TCP connection
+    ///
+    /// Listens on the file transfer port, asks the drone to connect and
+    /// streams the received bytes into the download directory.
+    /// Returns the number of bytes written.
+    pub fn transfer_file_via_direct_connection(&self, filename: &str) -> TelloResult<u64> {
+        self.receive_media(filename, None, |_, _| {}).map(|(_, bytes)| bytes)
+    }
+    
+    /// Transfer a file like `transfer_file_via_direct_connection`, reporting progress
+    ///
+    /// `progress` is called with the bytes received so far and the file size,
+    /// each time a chunk arrives. The size is taken from the drone's media
+    /// list and is `None` if the list doesn't report it.
+    pub fn download_media_with_progress(&self, filename: &str, progress: impl FnMut(u64, Option<u64>)) -> TelloResult<u64> {
+        let size = self.list_media_detailed().ok()
+            .and_then(|files| files.into_iter().find(|file| file.name == filename))
+            .and_then(|file| file.size);
+        self.download_media_with_size(filename, size, progress)
+    }
+    
+    /// Like `download_media_with_progress`, for callers that already know the size
+    ///
+    /// Pass the `MediaFile::size` from `list_media_detailed` to save listing
+    /// the media again, or `None` if it isn't known.
+    pub fn download_media_with_size(&self, filename: &str, size: Option<u64>,
+        progress: impl FnMut(u64, Option<u64>)) -> TelloResult<u64> {
+        self.receive_media(filename, size, progress).map(|(_, bytes)| bytes)
+    }
+    
+    /// Receive a file over a direct connection, returning where it was saved and its size
+    fn receive_media(&self, filename: &str, total: Option<u64>, progress: impl FnMut(u64, Option<u64>)) -> TelloResult<(PathBuf, u64)> {
+        // Create directory if it doesn't exist
+        let dir = self.media_download_dir();
+        if !Path::new(&dir).exists() {
+            fs::create_dir_all(&dir)?;
         }
         
-        // For actual implementation, we would need to set up a TCP server on FILE_TRANSFER_PORT
-        // and handle the file transfer protocol. This is simplified.
-        println!("Download initiated. File will be saved to: {}", dest_path);
+        let dest_path = Path::new(&dir).join(filename);
+        self.logger.info(format_args!("Setting up direct connection on port {} for file transfer...", self.file_transfer_port));
+        
+        // The listener must be ready before the drone is told to connect
+        let listener = TcpListener::bind(self.bind_socket_addr(self.file_transfer_port))?;
+        
+        // Send command to initiate direct transfer mode
+        let cmd = format!("direct_transfer {}", filename);
+        let response = self.send_command(&cmd)?;
+        
+        if response.contains("error") || response.contains("Error") {
+            return Err(TelloError::CommandRejected(response));
+        }
+        
+        let bytes = receive_file(&listener, &dest_path, self.file_transfer_timeout, total, progress)?;
+        self.logger.info(format_args!("Direct file transfer completed. {} bytes saved to: {}", bytes, dest_path.display()));
+        
+        Ok((dest_path, bytes))
+    }
+}
+
+impl Drop for Tello {
+    fn drop(&mut self) {
+        self.disconnect();
+    }
+}
+
+/// Land if the drone is flying and its battery fell below the critical level
+///
+/// Called by the state receiver thread for every state packet. `last_attempt`
+/// spaces failed landings `CRITICAL_LANDING_RETRY` apart and is cleared once
+/// the drone is down, so the warning is printed once per low battery episode.
+fn land_on_critical_battery(state: &TelemetryState, transport: &SharedTransport, takeoff_time: &Mutex<Option<Instant>>,
+    critical_battery: u8, last_attempt: &mut Option<Instant>, now: Instant, logger: &Logger) {
+    let Some(battery) = state.bat else {
+        return;
+    };
+    if critical_battery == 0 || battery >= i32::from(critical_battery) || lock_takeoff_time(takeoff_time).is_none() {
+        *last_attempt = None;
+        return;
+    }
+    if last_attempt.is_some_and(|last| now.duration_since(last) < CRITICAL_LANDING_RETRY) {
+        return;
+    }
+    
+    let transport = transport.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    let Some(transport) = transport.as_deref() else {
+        return;
+    };
+    
+    if last_attempt.is_none() {
+        logger.warn(format_args!("Battery at {}% is below the critical {}%, landing now", battery, critical_battery));
+    }
+    *last_attempt = Some(now);
+    match transport.send_command("land") {
+        Ok(response) if response.trim() == "ok" => *lock_takeoff_time(takeoff_time) = None,
+        Ok(response) => logger.warn(format_args!("Automatic landing was rejected: {}", response.trim())),
+        Err(e) => logger.warn(format_args!("Automatic landing failed: {}", e)),
+    }
+}
+
+/// Send the commands of `send_command_async` in order until the queue is dropped
+fn run_command_queue(commands: mpsc::Receiver<QueuedCommand>, transport: &SharedTransport,
+    command_log: Option<&CommandLog>, retries: u32, logger: &Logger) {
+    for (command, reply) in commands {
+        let result = send_queued_command(&command, transport, command_log, retries, logger);
+        // The caller may have dropped the receiver, the command was sent anyway
+        let _ = reply.send(result);
+    }
+}
+
+/// Send one queued command, retrying timeouts like `Tello::send_command`
+fn send_queued_command(command: &str, transport: &SharedTransport, command_log: Option<&CommandLog>,
+    retries: u32, logger: &Logger) -> TelloResult<String> {
+    let response = retry_on_timeout(command, retries, logger, || {
+        // Locked per attempt, so other commands can go out during the backoff
+        let transport = transport.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        let transport = transport.as_deref().ok_or(TelloError::NotConnected)?;
+        write_command_log(command_log, ">>", command, logger);
+        let result = match response_timeout(command) {
+            Some(timeout) => transport.send_command_with_timeout(command, timeout),
+            None => transport.send_command(command),
+        };
+        
+        match result {
+            Ok(response) => {
+                write_command_log(command_log, "<<", response.trim(), logger);
+                Ok(response.trim().to_string())
+            },
+            Err(e) => {
+                write_command_log(command_log, "<<", &format!("({})", e), logger);
+                Err(TelloError::from(e))
+            },
+        }
+    })?;
+    
+    match error_reason(&response) {
+        Some(reason) => Err(TelloError::CommandRejected(reason)),
+        None => Ok(response),
+    }
+}
+
+/// Run one attempt of `command`, and more while they time out, up to `retries` more
+///
+/// The waits between attempts start at `COMMAND_RETRY_DELAY` and double
+/// each time, up to 64 times it. Used by `Tello::send_command` and the
+/// queue of `send_command_async`, so both back off the same way.
+fn retry_on_timeout<T>(command: &str, retries: u32, logger: &Logger,
+    mut attempt: impl FnMut() -> TelloResult<T>) -> TelloResult<T> {
+    let mut retry = 0;
+    loop {
+        match attempt() {
+            Err(TelloError::Timeout) if retry < retries => {
+                let delay = COMMAND_RETRY_DELAY * 2u32.pow(retry.min(6));
+                retry += 1;
+                logger.debug(format_args!("No response to '{}', retry {}/{} in {} ms",
+                        command, retry, retries, delay.as_millis()));
+                thread::sleep(delay);
+            },
+            result => return result,
+        }
+    }
+}
+
+/// Append a line to the command log, if there is one
+fn write_command_log(command_log: Option<&CommandLog>, direction: &str, text: &str, logger: &Logger) {
+    let Some(log) = command_log else {
+        return;
+    };
+    if let Ok(mut file) = log.lock() {
+        let time = chrono::Local::now().format("%Y-%m-%d %H:%M:%S%.3f");
+        // A failing log must not interrupt the flight
+        if let Err(e) = writeln!(file, "[{}] {} {}", time, direction, text) {
+            logger.warn(format_args!("Failed to write command log: {}", e));
+        }
+    }
+}
+
+/// Pass a state packet to the callbacks registered with `on_state`
+///
+/// Packets without a single known field are skipped.
+fn notify_state_callbacks(state: &TelemetryState, callbacks: &Mutex<Vec<StateCallback>>) {
+    if *state == TelemetryState::default() {
+        return;
+    }
+    
+    let callbacks = callbacks.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    for callback in callbacks.iter() {
+        callback(state);
+    }
+}
+
+/// Append a state packet to the telemetry log, if there is one
+///
+/// Packets without a single known field are skipped.
+fn write_telemetry_log(state: &TelemetryState, telemetry_log: &Mutex<Option<TelemetryLog>>) {
+    if *state == TelemetryState::default() {
+        return;
+    }
+    
+    if let Some(log) = telemetry_log.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).as_mut() {
+        log.write(state);
+    }
+}
+
+/// Warn once when the highest temperature rises above the threshold
+///
+/// `overheated` remembers the warning until the drone cools down again,
+/// so a hot drone doesn't print it for every state packet.
+fn check_temperature(state: &TelemetryState, threshold: i32, overheated: &mut bool) -> Option<String> {
+    let high = state.temph?;
+    
+    if high <= threshold {
+        *overheated = false;
+        return None;
+    }
+    if *overheated {
+        return None;
+    }
+    
+    *overheated = true;
+    Some(format!("Drone temperature {}°C is above {}°C, it may throttle or land", high, threshold))
+}
+
+/// Warn once when state packets stop arriving, and again when they are back
+///
+/// Nothing is reported before the first packet, since the state port may
+/// simply be unused. `lost` remembers the warning across calls.
+fn check_link(last_state: Option<Instant>, now: Instant, threshold: Duration, lost: &mut bool) -> Option<String> {
+    let age = now.saturating_duration_since(last_state?);
+    
+    if age <= threshold {
+        if *lost {
+            *lost = false;
+            return Some(String::from("State packets are arriving again"));
+        }
+        return None;
+    }
+    if *lost {
+        return None;
+    }
+    
+    *lost = true;
+    Some(format!("No state packet for {:.1} s, the link to the drone may be lost", age.as_secs_f32()))
+}
+
+/// Lock the takeoff time shared with the state receiver thread
+fn lock_takeoff_time(takeoff_time: &Mutex<Option<Instant>>) -> MutexGuard<'_, Option<Instant>> {
+    takeoff_time.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+// Enumeration for command categories
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub(crate) enum CommandCategory {
+    System,      // System commands (help, version, exit)
+    FlightControl, // Flight control commands (takeoff, land, etc.)
+    Movement,    // Movement commands (forward, back, left, right, etc.)
+    Camera,      // Camera control commands (photo, video)
+    Media,       // Media management commands (download, delete, etc.)
+    Positioning, // Positioning commands (position, get_position)
+    Maintenance  // Maintenance commands (calibrate)
+}
+
+/// The category of an SDK command such as "takeoff" or "cw 90"
+///
+/// Query suffixes are ignored, so "speed?" is found as "speed".
+fn command_category(command: &str) -> Option<CommandCategory> {
+    let name = command.split_whitespace().next().unwrap_or("").trim_end_matches('?');
+    
+    match name {
+        "command" | "sdk" | "sn" | "hardware" | "version" | "wifi" | "ap" | "reboot" => Some(CommandCategory::System),
+        "takeoff" | "throwfly" | "land" | "emergency" | "battery" | "time" | "temp" => Some(CommandCategory::FlightControl),
+        "forward" | "back" | "left" | "right" | "up" | "down" | "cw" | "ccw" | "flip" | "speed" | "go" | "curve"
+            | "jump" | "rc" | "stop" => Some(CommandCategory::Movement),
+        "streamon" | "streamoff" | "takepic" | "snapshot" | "setresolution" | "setfps" | "setbitrate" | "downvision"
+            => Some(CommandCategory::Camera),
+        "ls" | "rmall" | "download" | "direct_transfer" => Some(CommandCategory::Media),
+        "mon" | "moff" | "mdirection" => Some(CommandCategory::Positioning),
+        "calibrate" => Some(CommandCategory::Maintenance),
+        _ => None,
+    }
+}
+
+/// How long to wait for the answer to `command`
+///
+/// Queries answer at once, while flight and movement commands only answer
+/// when they are done. `None` keeps the transport's default timeout.
+pub(crate) fn response_timeout(command: &str) -> Option<Duration> {
+    if command.ends_with('?') {
+        return Some(DEFAULT_QUERY_TIMEOUT);
+    }
+    
+    // These stop the drone at once, and the answer may never come
+    if command == "emergency" || command == "stop" {
+        return None;
+    }
+    
+    match command_category(command)? {
+        CommandCategory::FlightControl | CommandCategory::Movement => Some(FLIGHT_RESPONSE_TIMEOUT),
+        CommandCategory::Maintenance => Some(CALIBRATION_TIMEOUT),
+        _ => None,
+    }
+}
+
+/// Whether `command` lifts the drone off or moves it
+///
+/// Stopping and speed changes never need arming, neither does "rc 0 0 0 0",
+/// which centers the sticks.
+fn needs_arming(command: &str) -> bool {
+    match command.split_whitespace().next().unwrap_or("") {
+        "takeoff" | "throwfly" | "motoron" => true,
+        "stop" | "speed" => false,
+        "rc" => command.split_whitespace().skip(1).any(|value| value.parse() != Ok(0)),
+        _ => !command.ends_with('?') && command_category(command) == Some(CommandCategory::Movement),
+    }
+}
+
+/// The reason in an error reply such as "error Motor stop"
+///
+/// A bare "error" yields "error" itself. Returns `None` for other replies.
+fn error_reason(response: &str) -> Option<String> {
+    let rest = response.trim().strip_prefix("error")?;
+    if !(rest.is_empty() || rest.starts_with(|c: char| c.is_whitespace() || c == ':')) {
+        return None;
+    }
+    
+    let reason = rest.trim_start().trim_start_matches(':').trim();
+    if reason.is_empty() {
+        Some(String::from("error"))
+    } else {
+        Some(reason.to_string())
+    }
+}
+
+/// Turn a query the drone rejected into `None`, keeping other errors
+fn rejected_as_none(result: TelloResult<String>) -> TelloResult<Option<String>> {
+    match result {
+        Ok(answer) => Ok(Some(answer)),
+        Err(TelloError::CommandRejected(_)) => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+/// Parse a "time?" response such as "15s" into seconds
+fn parse_motor_time(response: &str) -> TelloResult<u32> {
+    let response = response.trim();
+    response.trim_end_matches('s').parse::<u32>().map_err(|_| TelloError::InvalidResponse(
+        format!("Invalid motor time: {}", response),
+    ))
+}
+
+/// Parse a "wifi?" response into the signal-to-noise ratio
+fn parse_wifi_snr(response: &str) -> TelloResult<i32> {
+    response.trim().parse::<i32>().map_err(|_| TelloError::InvalidResponse(
+        format!("Invalid WiFi SNR: {}", response.trim()),
+    ))
+}
+
+/// Check WiFi credentials before sending them in a space separated SDK command
+fn check_wifi_credentials(ssid: &str, password: &str) -> TelloResult<()> {
+    if ssid.is_empty() || ssid.contains(char::is_whitespace) {
+        return Err(TelloError::InvalidParameter(
+            format!("Invalid SSID: '{}'. Must be non-empty and without spaces.", ssid),
+        ));
+    }
+    if password.is_empty() || password.contains(char::is_whitespace) {
+        return Err(TelloError::InvalidParameter(
+            String::from("Invalid WiFi password. Must be non-empty and without spaces."),
+        ));
+    }
+    Ok(())
+}
+
+/// Receive packets from the video stream until one complete JPEG frame is assembled
+fn receive_jpeg_frame(socket: &UdpSocket, timeout: Duration) -> TelloResult<Vec<u8>> {
+    const JPEG_START: [u8; 2] = [0xFF, 0xD8];
+    const JPEG_END: [u8; 2] = [0xFF, 0xD9];
+    
+    let deadline = Instant::now() + timeout;
+    let mut buffer = [0; 2048];
+    let mut frame: Vec<u8> = Vec::new();
+    
+    loop {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            return Err(TelloError::Timeout);
+        }
+        socket.set_read_timeout(Some(remaining))?;
+        let (amount, _) = socket.recv_from(&mut buffer)?;
+        let packet = &buffer[..amount];
+        
+        if frame.is_empty() {
+            if packet.starts_with(&JPEG_START) {
+                frame.extend_from_slice(packet);
+            } else if packet.starts_with(&[0, 0, 0, 1]) || packet.starts_with(&[0, 0, 1]) {
+                return Err(TelloError::Unsupported(String::from(
+                    "the drone streams H.264 video, which can't be saved as a JPEG. \
+                     Use 'photo' and download the picture instead.",
+                )));
+            }
+            // Otherwise we joined in the middle of a frame, wait for the next one
+        } else {
+            frame.extend_from_slice(packet);
+        }
+        
+        if frame.len() > JPEG_START.len() && frame.ends_with(&JPEG_END) {
+            return Ok(frame);
+        }
+    }
+}
+
+/// Normalize a heading in degrees into 0..360
+fn normalize_direction(degrees: f32) -> f32 {
+    let normalized = degrees.rem_euclid(360.0);
+    // rem_euclid can round up to exactly 360 for tiny negative inputs
+    if normalized >= 360.0 { 0.0 } else { normalized }
+}
+
+/// Parse a "battery?" response into a percentage
+fn parse_battery(response: &str) -> TelloResult<u8> {
+    match response.trim().parse::<u8>() {
+        Ok(battery) if battery <= 100 => Ok(battery),
+        _ => Err(TelloError::InvalidResponse(
+            format!("Invalid battery level: {}", response.trim()),
+        )),
+    }
+}
+
+/// Parse a "bitrate?" response into the level 0-5
+fn parse_bitrate(response: &str) -> TelloResult<u8> {
+    match response.trim().parse::<u8>() {
+        Ok(level) if level <= 5 => Ok(level),
+        _ => Err(TelloError::InvalidResponse(
+            format!("Invalid bitrate: {}", response.trim()),
+        )),
+    }
+}
+
+/// Parse a "speed?" response into cm/s
+pub(crate) fn parse_speed(response: &str) -> TelloResult<f32> {
+    response.trim().parse::<f32>().map_err(|_| TelloError::InvalidResponse(
+        format!("Invalid speed: {}", response.trim()),
+    ))
+}
+
+/// The body frame offset (forward, left, up) in meters of a move in cm
+pub(crate) fn movement_offset(direction: &str, distance: i32) -> Option<(f32, f32, f32)> {
+    let distance_m = distance as f32 / 100.0; // Convert cm to meters
+    
+    match direction {
+        "forward" => Some((distance_m, 0.0, 0.0)),
+        "back" => Some((-distance_m, 0.0, 0.0)),
+        "left" => Some((0.0, distance_m, 0.0)),
+        "right" => Some((0.0, -distance_m, 0.0)),
+        "up" => Some((0.0, 0.0, distance_m)),
+        "down" => Some((0.0, 0.0, -distance_m)),
+        _ => None,
+    }
+}
+
+/// Check the battery level against the minimum takeoff battery level
+fn check_takeoff_battery(battery: u8, min_percent: u8) -> TelloResult<()> {
+    if battery < min_percent {
+        return Err(TelloError::InvalidState(
+            format!("Battery too low: {}% < {}%", battery, min_percent),
+        ));
+    }
+    
+    Ok(())
+}
+
+/// Accept a single connection on the listener and save everything it sends to `dest_path`
+///
+/// Fails with `TimedOut` if nobody connects within `timeout`. A partially
+/// written file is removed if the transfer fails.
+fn receive_file(listener: &TcpListener, dest_path: &Path, timeout: Duration,
+                total: Option<u64>, progress: impl FnMut(u64, Option<u64>)) -> io::Result<u64> {
+    // TcpListener has no accept timeout, so poll in non-blocking mode
+    listener.set_nonblocking(true)?;
+    let deadline = Instant::now() + timeout;
+    
+    let mut stream = loop {
+        match listener.accept() {
+            Ok((stream, _)) => break stream,
+            Err(e) if e.kind() == io::ErrorKind::WouldBlock => {
+                if Instant::now() >= deadline {
+                    return Err(io::Error::new(
+                        io::ErrorKind::TimedOut,
+                        format!("Drone did not connect for file transfer within {:?}", timeout),
+                    ));
+                }
+                thread::sleep(Duration::from_millis(50));
+            },
+            Err(e) => return Err(e),
+        }
+    };
+    
+    stream.set_nonblocking(false)?;
+    stream.set_read_timeout(Some(timeout))?;
+    
+    let mut file = fs::File::create(dest_path)?;
+    match copy_with_progress(&mut stream, &mut file, total, progress) {
+        Ok(bytes) => Ok(bytes),
+        Err(e) => {
+            // Don't leave a truncated file behind
+            drop(file);
+            let _ = fs::remove_file(dest_path);
+            Err(e)
+        }
+    }
+}
+
+/// Expand a leading `~` and `$VAR`, `${VAR}` or `%VAR%` environment variables in `path`
+fn expand_path(path: &str) -> io::Result<String> {
+    expand_path_in(path, dirs::home_dir().as_deref())
+}
+
+/// Expand `path` like `expand_path`, with `~` standing for `home`
+fn expand_path_in(path: &str, home: Option<&Path>) -> io::Result<String> {
+    let invalid = |message: String| io::Error::new(io::ErrorKind::InvalidInput, message);
+    let variable = |name: &str| std::env::var(name)
+        .map_err(|_| invalid(format!("Environment variable {} in '{}' is not set", name, path)));
+    
+    let mut expanded = String::new();
+    let mut rest = path;
+    
+    if let Some(after) = rest.strip_prefix('~') {
+        if after.is_empty() || after.starts_with(['/', '\\']) {
+            let home = home.ok_or_else(|| invalid(String::from("No home directory to expand ~")))?;
+            expanded.push_str(&home.to_string_lossy());
+            rest = after;
+        }
+    }
+    
+    while let Some(start) = rest.find(['$', '%']) {
+        expanded.push_str(&rest[..start]);
+        let marker = rest[start..].chars().next().unwrap_or('$');
+        let after = &rest[start + 1..];
+        
+        // The variable name and what follows it, or None for a literal $ or %
+        let found = match marker {
+            '%' => after.find('%')
+                .filter(|&end| end > 0)
+                .map(|end| (&after[..end], &after[end + 1..])),
+            _ if after.starts_with('{') => after.find('}')
+                .map(|end| (&after[1..end], &after[end + 1..])),
+            _ => {
+                let end = after.find(|c: char| !(c.is_ascii_alphanumeric() || c == '_')).unwrap_or(after.len());
+                Some((&after[..end], &after[end..])).filter(|(name, _)| !name.is_empty())
+            },
+        };
+        
+        match found {
+            Some((name, remainder)) => {
+                expanded.push_str(&variable(name)?);
+                rest = remainder;
+            },
+            None => {
+                expanded.push(marker);
+                rest = after;
+            },
+        }
+    }
+    expanded.push_str(rest);
+    
+    if expanded.trim().is_empty() {
+        return Err(invalid(format!("'{}' expands to an empty path", path)));
+    }
+    Ok(expanded)
+}
+
+/// Copy `reader` into `writer` like `io::copy`, calling `progress` after each chunk
+///
+/// `progress` gets the bytes copied so far and `total` unchanged.
+fn copy_with_progress(reader: &mut impl Read, writer: &mut impl Write, total: Option<u64>,
+                      mut progress: impl FnMut(u64, Option<u64>)) -> io::Result<u64> {
+    let mut buffer = [0; 8192];
+    let mut copied = 0;
+    
+    loop {
+        let amount = match reader.read(&mut buffer) {
+            Ok(0) => return Ok(copied),
+            Ok(amount) => amount,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        writer.write_all(&buffer[..amount])?;
+        copied += amount as u64;
+        progress(copied, total);
+    }
+}
+
+// Mock transport for testing
+#[cfg(test)]
+pub(crate) mod mock {
+    use super::*;
+    use std::collections::HashMap;
+    
+    /// Records the commands it is given and answers them from a table
+    ///
+    /// Clones share their state, so a test can keep one clone for its
+    /// assertions and hand the other to `Tello`.
+    #[derive(Clone)]
+    pub struct MockTransport {
+        commands: Arc<Mutex<Vec<String>>>,
+        responses: Arc<Mutex<HashMap<String, String>>>,
+        timeouts: Arc<Mutex<HashMap<String, Duration>>>,
+        timeouts_left: Arc<Mutex<u32>>,
+        delays: Arc<Mutex<HashMap<String, Duration>>>,
+    }
+    
+    impl MockTransport {
+        pub fn new() -> Self {
+            let mut responses = HashMap::new();
+            responses.insert("command".to_string(), "ok".to_string());
+            responses.insert("takeoff".to_string(), "ok".to_string());
+            responses.insert("land".to_string(), "ok".to_string());
+            
+            MockTransport {
+                commands: Arc::new(Mutex::new(Vec::new())),
+                responses: Arc::new(Mutex::new(responses)),
+                timeouts: Arc::new(Mutex::new(HashMap::new())),
+                timeouts_left: Arc::new(Mutex::new(0)),
+                delays: Arc::new(Mutex::new(HashMap::new())),
+            }
+        }
+        
+        pub fn get_commands(&self) -> Vec<String> {
+            self.commands.lock().unwrap().clone()
+        }
+        
+        pub fn set_response(&self, command: &str, response: &str) {
+            self.responses.lock().unwrap().insert(command.to_string(), response.to_string());
+        }
+        
+        /// Let the next `count` commands time out without an answer
+        pub fn time_out_next(&self, count: u32) {
+            *self.timeouts_left.lock().unwrap() = count;
+        }
+        
+        /// Let the answer to `command` take `delay`
+        ///
+        /// Nothing actually waits: a response timeout shorter than `delay`
+        /// just gets no answer.
+        pub fn set_delay(&self, command: &str, delay: Duration) {
+            self.delays.lock().unwrap().insert(command.to_string(), delay);
+        }
+        
+        /// The response timeout the last `command` was sent with, if any
+        pub fn get_timeout(&self, command: &str) -> Option<Duration> {
+            self.timeouts.lock().unwrap().get(command).copied()
+        }
+    }
+    
+    impl CommandTransport for MockTransport {
+        fn send_command(&self, command: &str) -> io::Result<String> {
+            self.commands.lock().unwrap().push(command.to_string());
+            
+            let mut timeouts_left = self.timeouts_left.lock().unwrap();
+            if *timeouts_left > 0 {
+                *timeouts_left -= 1;
+                return Err(io::Error::new(io::ErrorKind::WouldBlock, "no response"));
+            }
+            
+            // Commands without a configured response are rejected
+            let responses = self.responses.lock().unwrap();
+            Ok(responses.get(command)
+                .cloned()
+                .unwrap_or_else(|| "error".to_string()))
+        }
+        
+        fn send_command_with_timeout(&self, command: &str, timeout: Duration) -> io::Result<String> {
+            self.timeouts.lock().unwrap().insert(command.to_string(), timeout);
+            
+            let delay = self.delays.lock().unwrap().get(command).copied();
+            if delay.is_some_and(|delay| delay > timeout) {
+                self.commands.lock().unwrap().push(command.to_string());
+                return Err(io::Error::new(io::ErrorKind::WouldBlock, "no response"));
+            }
+            self.send_command(command)
+        }
+        
+        fn send_without_response(&self, command: &str) -> io::Result<()> {
+            self.commands.lock().unwrap().push(command.to_string());
+            Ok(())
+        }
+    }
+    
+    /// A Tello wired to a fresh mock transport
+    pub fn mock_tello() -> (Tello, MockTransport) {
+        let mock = MockTransport::new();
+        let mut tello = Tello::new().unwrap();
+        tello.set_transport(Box::new(mock.clone()));
+        tello.set_verbose(false);
+        tello.set_takeoff_stabilization(Duration::ZERO);
+        (tello, mock)
+    }
+    
+    /// A UDP transport from a local socket to the drone address of `tello`
+    pub fn udp_transport(tello: &Tello) -> Box<UdpTransport> {
+        Box::new(UdpTransport::new(UdpSocket::bind("127.0.0.1:0").unwrap(), tello.tello_addr()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::mock::{mock_tello, udp_transport, MockTransport};
+    
+    #[test]
+    fn test_tello_new() {
+        // Test that Tello::new() creates a valid instance
+        let tello = Tello::new().expect("Failed to create Tello instance");
+        assert!(!tello.is_connected());
+        assert_eq!(tello.tello_addr.to_string(), format!("{}:{}", TELLO_IP, TELLO_PORT));
+    }
+    
+    #[test]
+    fn test_builder_custom_ip() {
+        // Test that a custom IP and ports are reflected in the built instance
+        let tello = Tello::builder()
+            .ip("192.168.1.42")
+            .local_port(9000)
+            .state_port(9001)
+            .build()
+            .expect("Failed to build Tello instance");
+        assert_eq!(tello.tello_addr.to_string(), format!("192.168.1.42:{}", TELLO_PORT));
+        assert_eq!(tello.local_port(), 9000);
+        assert_eq!(tello.state_port(), 9001);
+    }
+    
+    #[test]
+    fn test_builder_rejects_invalid_settings() {
+        // Colliding local ports must be rejected
+        let result = Tello::builder().local_port(9000).state_port(9000).build();
+        assert!(matches!(result, Err(TelloError::InvalidParameter(_))));
+        
+        // Unparseable IP addresses must be rejected
+        let result = Tello::builder().ip("not an ip").build();
+        assert!(matches!(result, Err(TelloError::InvalidParameter(_))));
+        
+        // connect() needs at least one attempt
+        let result = Tello::builder().connect_attempts(0).build();
+        assert!(matches!(result, Err(TelloError::InvalidParameter(_))));
+        
+        // The bind address must parse and match the drone's IP version
+        let result = Tello::builder().bind_address("wlan0").build();
+        assert!(matches!(result, Err(TelloError::InvalidParameter(_))));
+        let result = Tello::builder().bind_address("::1").build();
+        assert!(matches!(result, Err(TelloError::InvalidParameter(_))));
+    }
+    
+    #[test]
+    fn test_default_ports() {
+        let tello = Tello::new().unwrap();
+        assert_eq!((tello.local_port(), tello.state_port()), (LOCAL_PORT, 8890));
+        assert_eq!(LOCAL_PORT, 9000);
+    }
+    
+    #[test]
+    #[ignore] // Binds the real port 9000, which another program or a parallel test may hold
+    fn test_connect_binds_local_port() {
+        let drone = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let mut tello = Tello::builder()
+            .ip("127.0.0.1")
+            .command_port(drone.local_addr().unwrap().port())
+            .state_port(0)
+            .build()
+            .unwrap();
+        tello.set_verbose(false);
+        let download_dir = std::env::temp_dir().join(format!("tello_local_port_{}", std::process::id()));
+        tello.download_path = download_dir.to_string_lossy().to_string();
+        
+        // The command comes from the documented local port and the answer goes back to it
+        let responder = thread::spawn(move || {
+            let mut buffer = [0; 1024];
+            let (_, from) = drone.recv_from(&mut buffer).unwrap();
+            drone.send_to(b"ok", from).unwrap();
+            from
+        });
+        tello.connect().unwrap();
+        assert_eq!(responder.join().unwrap().port(), LOCAL_PORT);
+        tello.disconnect();
+        let _ = fs::remove_dir_all(download_dir);
+    }
+    
+    #[test]
+    fn test_responses_only_from_drone() {
+        let drone = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let stray = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let mut tello = Tello::builder()
+            .ip("127.0.0.1")
+            .command_port(drone.local_addr().unwrap().port())
+            .build()
+            .unwrap();
+        tello.set_transport(udp_transport(&tello));
+        
+        // A packet from another sender arrives first and is not taken for the answer
+        let responder = thread::spawn(move || {
+            let mut buffer = [0; 1024];
+            let (_, from) = drone.recv_from(&mut buffer).unwrap();
+            stray.send_to(b"pitch:0;roll:0;yaw:0;", from).unwrap();
+            thread::sleep(Duration::from_millis(50));
+            drone.send_to(b"87", from).unwrap();
+        });
+        assert_eq!(tello.send_command_with_response("battery?", Duration::from_secs(2)).unwrap(), "87");
+        responder.join().unwrap();
+    }
+    
+    #[test]
+    fn test_bind_address() {
+        let tello = Tello::new().unwrap();
+        assert_eq!(tello.bind_address().to_string(), "0.0.0.0");
+        
+        let drone = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let mut tello = Tello::builder()
+            .ip("127.0.0.1")
+            .command_port(drone.local_addr().unwrap().port())
+            .bind_address("127.0.0.1")
+            .local_port(0)
+            .state_port(0)
+            .build()
+            .unwrap();
+        tello.set_verbose(false);
+        let download_dir = std::env::temp_dir().join(format!("tello_bind_address_{}", std::process::id()));
+        tello.download_path = download_dir.to_string_lossy().to_string();
+        assert_eq!(tello.bind_socket_addr(9000).to_string(), "127.0.0.1:9000");
+        
+        let responder = thread::spawn(move || {
+            let mut buffer = [0; 1024];
+            let (_, from) = drone.recv_from(&mut buffer).unwrap();
+            drone.send_to(b"ok", from).unwrap();
+            from
+        });
+        tello.connect().unwrap();
+        assert!(responder.join().unwrap().ip().is_loopback());
+        tello.disconnect();
+        let _ = fs::remove_dir_all(download_dir);
+        
+        // An address of another machine can't be bound, so connect fails before sending
+        let mut tello = Tello::builder().bind_address("192.0.2.1").local_port(0).state_port(0).build().unwrap();
+        assert!(tello.connect().is_err());
+        assert!(!tello.is_connected());
+    }
+    
+    #[test]
+    fn test_error_kinds() {
+        let mut tello = Tello::new().expect("Failed to create Tello instance");
+        
+        // Validation happens before anything is sent
+        tello.set_strict_rotation(true);
+        assert!(matches!(tello.forward(600), Err(TelloError::InvalidParameter(_))));
+        assert!(matches!(tello.rotate_cw(0), Err(TelloError::InvalidParameter(_))));
+        
+        // Valid commands fail because there is no socket yet
+        assert!(matches!(tello.forward(50), Err(TelloError::NotConnected)));
+        assert!(matches!(tello.takeoff(None), Err(TelloError::NotConnected)));
+        
+        // Socket read timeouts are reported as Timeout
+        let timeout: TelloError = io::Error::from(io::ErrorKind::WouldBlock).into();
+        assert!(matches!(timeout, TelloError::Timeout));
+        assert!(timeout.is_link_error());
+        
+        // A dropped WiFi shows up as an unreachable network when sending
+        let lost: TelloError = io::Error::from(io::ErrorKind::NetworkUnreachable).into();
+        assert!(matches!(lost, TelloError::ConnectionLost));
+        assert!(lost.is_link_error());
+        assert!(!TelloError::NotConnected.is_link_error());
+    }
+    
+    #[test]
+    fn test_send_command_with_response() {
+        // A local socket plays the drone
+        let drone = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let mut tello = Tello::builder()
+            .ip("127.0.0.1")
+            .command_port(drone.local_addr().unwrap().port())
+            .build()
+            .unwrap();
+        tello.set_transport(udp_transport(&tello));
+        
+        let responder = thread::spawn(move || {
+            let mut buffer = [0; 1024];
+            let (amount, from) = drone.recv_from(&mut buffer).unwrap();
+            assert_eq!(&buffer[..amount], b"sdk?");
+            drone.send_to(b"30\r\n", from).unwrap();
+            
+            // Ignore the second query so it times out
+            drone.recv_from(&mut buffer).unwrap();
+        });
+        
+        // The raw response is returned trimmed
+        let response = tello.send_command_with_response("sdk?", Duration::from_secs(2)).unwrap();
+        assert_eq!(response, "30");
+        
+        // A missing response is reported as a timeout
+        let result = tello.send_command_with_response("sn?", Duration::from_millis(100));
+        assert!(matches!(result, Err(TelloError::Timeout)));
+        
+        responder.join().unwrap();
+    }
+    
+    #[test]
+    fn test_send_rc_control() {
+        let drone = UdpSocket::bind("127.0.0.1:0").unwrap();
+        drone.set_read_timeout(Some(Duration::from_secs(2))).unwrap();
+        let mut tello = Tello::builder()
+            .ip("127.0.0.1")
+            .command_port(drone.local_addr().unwrap().port())
+            .build()
+            .unwrap();
+        
+        // Out of range channels are rejected before anything is sent
+        assert!(matches!(tello.send_rc_control(0, 101, 0, 0), Err(TelloError::InvalidParameter(_))));
+        
+        // The drone never answers "rc", so the call must return without a response
+        tello.set_transport(udp_transport(&tello));
+        tello.send_rc_control(10, -20, 0, 100).unwrap();
+        
+        let mut buffer = [0; 1024];
+        let (amount, _) = drone.recv_from(&mut buffer).unwrap();
+        assert_eq!(&buffer[..amount], b"rc 10 -20 0 100");
+    }
+    
+    #[test]
+    fn test_hover() {
+        let (tello, mock) = mock_tello();
+        
+        // Scaled down from a 12 s hover with the 5 s keepalive: rc at 0, 5 and 10 s
+        tello.hover_with_interval(Duration::from_millis(1200), Duration::from_millis(500)).unwrap();
+        
+        assert_eq!(mock.get_commands(), vec!["rc 0 0 0 0"; 3]);
+        
+        let tello = Tello::new().unwrap();
+        assert!(matches!(tello.hover(Duration::from_secs(1)), Err(TelloError::NotConnected)));
+    }
+    
+    #[test]
+    fn test_hold_height() {
+        let (mut tello, mock) = mock_tello();
+        mock.set_response("up 20", "ok");
+        mock.set_response("down 30", "ok");
+        let state = Arc::new(Mutex::new(String::from("pitch:0;roll:0;yaw:0;h:80;")));
+        tello.state_receiver = Some(Arc::clone(&state));
+        
+        // Sunk below the target: every correction goes up
+        tello.hold_height_with_interval(100, 10, Duration::from_millis(250), Duration::from_millis(100)).unwrap();
+        let commands = mock.get_commands();
+        assert!(!commands.is_empty());
+        assert!(commands.iter().all(|command| command == "up 20"), "{:?}", commands);
+        
+        // Risen above it: every correction goes down
+        *state.lock().unwrap() = String::from("pitch:0;roll:0;yaw:0;h:130;");
+        let (mut tello, mock) = mock_tello();
+        mock.set_response("down 30", "ok");
+        tello.state_receiver = Some(Arc::clone(&state));
+        tello.hold_height_with_interval(100, 10, Duration::from_millis(250), Duration::from_millis(100)).unwrap();
+        let commands = mock.get_commands();
+        assert!(!commands.is_empty());
+        assert!(commands.iter().all(|command| command == "down 30"), "{:?}", commands);
+        // Drift corrections don't move the tracked position
+        assert_eq!(tello.get_position().z, 0.0);
+        
+        // A small drift is corrected by the shortest move the drone accepts
+        *state.lock().unwrap() = String::from("pitch:0;roll:0;yaw:0;h:95;");
+        let (mut tello, mock) = mock_tello();
+        mock.set_response("up 20", "ok");
+        tello.state_receiver = Some(Arc::clone(&state));
+        tello.hold_height_with_interval(100, 2, Duration::from_millis(250), Duration::from_millis(100)).unwrap();
+        let commands = mock.get_commands();
+        assert!(!commands.is_empty());
+        assert!(commands.iter().all(|command| command == "up 20"), "{:?}", commands);
+        
+        // Within the tolerance only the keepalive is sent
+        *state.lock().unwrap() = String::from("pitch:0;roll:0;yaw:0;h:105;");
+        let (mut tello, mock) = mock_tello();
+        tello.state_receiver = Some(Arc::clone(&state));
+        tello.hold_height_with_interval(100, 10, Duration::from_millis(250), Duration::from_millis(100)).unwrap();
+        assert_eq!(mock.get_commands(), vec!["rc 0 0 0 0"]);
+        
+        // The target must be below the ceiling, and a height must be received
+        tello.set_max_altitude(90);
+        assert!(matches!(tello.hold_height(100, 10, Duration::from_secs(1)), Err(TelloError::InvalidParameter(_))));
+        let (mut tello, mock) = mock_tello();
+        assert!(matches!(tello.hold_height(100, 10, Duration::from_secs(1)), Err(TelloError::InvalidState(_))));
+        assert!(mock.get_commands().is_empty());
+    }
+    
+    #[test]
+    fn test_connect_retries() {
+        let drone = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let mut tello = Tello::builder()
+            .ip("127.0.0.1")
+            .command_port(drone.local_addr().unwrap().port())
+            .local_port(0)
+            .state_port(0)
+            .build()
+            .unwrap();
+        let download_dir = std::env::temp_dir().join(format!("tello_connect_{}", std::process::id()));
+        tello.download_path = download_dir.to_string_lossy().to_string();
+        
+        // The first two attempts fail, the third is accepted
+        let responder = thread::spawn(move || {
+            let mut buffer = [0; 1024];
+            for response in ["error", "error", "ok"] {
+                let (amount, from) = drone.recv_from(&mut buffer).unwrap();
+                assert_eq!(&buffer[..amount], b"command");
+                drone.send_to(response.as_bytes(), from).unwrap();
+            }
+            drone
+        });
+        
+        tello.connect().unwrap();
+        assert!(tello.is_connected());
+        tello.disconnect();
+        
+        // A drone that keeps refusing leaves the instance disconnected
+        let drone = responder.join().unwrap();
+        let mut tello = Tello::builder()
+            .ip("127.0.0.1")
+            .command_port(drone.local_addr().unwrap().port())
+            .local_port(0)
+            .state_port(0)
+            .connect_attempts(1)
+            .build()
+            .unwrap();
+        let responder = thread::spawn(move || {
+            let mut buffer = [0; 1024];
+            let (_, from) = drone.recv_from(&mut buffer).unwrap();
+            drone.send_to(b"error", from).unwrap();
+        });
+        
+        assert!(matches!(tello.connect(), Err(TelloError::NotConnected)));
+        assert!(!tello.is_connected());
+        
+        responder.join().unwrap();
+        let _ = fs::remove_dir_all(download_dir);
+    }
+    
+    #[test]
+    fn test_connect_without_state_port() {
+        // Another process holds the state port
+        let busy = UdpSocket::bind("0.0.0.0:0").unwrap();
+        let drone = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let mut tello = Tello::builder()
+            .ip("127.0.0.1")
+            .command_port(drone.local_addr().unwrap().port())
+            .local_port(0)
+            .state_port(busy.local_addr().unwrap().port())
+            .build()
+            .unwrap();
+        tello.set_verbose(false);
+        let download_dir = std::env::temp_dir().join(format!("tello_state_busy_{}", std::process::id()));
+        tello.set_download_path(download_dir.to_str().unwrap()).unwrap();
+        
+        let responder = thread::spawn(move || {
+            let mut buffer = [0; 1024];
+            let (_, from) = drone.recv_from(&mut buffer).unwrap();
+            drone.send_to(b"ok", from).unwrap();
+        });
+        
+        tello.connect().unwrap();
+        responder.join().unwrap();
+        assert!(tello.is_connected());
+        assert!(tello.state_thread.is_none());
+        assert_eq!(tello.get_state(), None);
+        
+        tello.disconnect();
+        let _ = fs::remove_dir_all(download_dir);
+    }
+    
+    #[test]
+    fn test_reconnect() {
+        let drone = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let mut tello = Tello::builder()
+            .ip("127.0.0.1")
+            .command_port(drone.local_addr().unwrap().port())
+            .local_port(0)
+            .state_port(0)
+            .build()
+            .unwrap();
+        tello.set_verbose(false);
+        let download_dir = std::env::temp_dir().join(format!("tello_reconnect_{}", std::process::id()));
+        tello.set_download_path(download_dir.to_str().unwrap()).unwrap();
+        
+        let responder = thread::spawn(move || {
+            let mut buffer = [0; 1024];
+            for _ in 0..2 {
+                let (amount, from) = drone.recv_from(&mut buffer).unwrap();
+                assert_eq!(&buffer[..amount], b"command");
+                drone.send_to(b"ok", from).unwrap();
+            }
+            let (amount, from) = drone.recv_from(&mut buffer).unwrap();
+            assert_eq!(&buffer[..amount], b"sdk?");
+            drone.send_to(b"20", from).unwrap();
+        });
+        
+        tello.connect().unwrap();
+        tello.set_position(1.0, 2.0, 3.0);
+        tello.set_direction(45.0);
+        tello.model = Some(TelloModel::Tello);
+        tello.set_photo_command("snapshot");
+        
+        // SDK mode is entered again and the tracked state survives, the model is detected anew
+        tello.reconnect().unwrap();
+        responder.join().unwrap();
+        assert!(tello.is_connected());
+        assert!(tello.state_thread.is_some());
+        assert_eq!(tello.get_position().y, 2.0);
+        assert_eq!(tello.get_direction(), 45.0);
+        assert_eq!(tello.download_path, download_dir.to_str().unwrap());
+        assert_eq!(tello.model(), Some(TelloModel::TelloEdu));
+        assert!(tello.photo_command.lock().unwrap().is_none());
+        
+        tello.disconnect();
+        let _ = fs::remove_dir_all(download_dir);
+    }
+    
+    #[test]
+    fn test_command_log() {
+        let drone = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let mut tello = Tello::builder()
+            .ip("127.0.0.1")
+            .command_port(drone.local_addr().unwrap().port())
+            .build()
+            .unwrap();
+        tello.set_transport(udp_transport(&tello));
+        tello.set_verbose(false);
+        
+        let log_path = std::env::temp_dir().join(format!("tello_command_log_{}.txt", std::process::id()));
+        let _ = fs::remove_file(&log_path);
+        tello.set_command_log(log_path.to_str().unwrap()).unwrap();
+        
+        let responder = thread::spawn(move || {
+            let mut buffer = [0; 1024];
+            for response in ["ok", "87"] {
+                let (_, from) = drone.recv_from(&mut buffer).unwrap();
+                drone.send_to(response.as_bytes(), from).unwrap();
+            }
+        });
+        
+        tello.send_command("command").unwrap();
+        tello.send_command_with_response("battery?", Duration::from_secs(2)).unwrap();
+        responder.join().unwrap();
+        
+        let log = fs::read_to_string(&log_path).unwrap();
+        let lines: Vec<&str> = log.lines().collect();
+        assert_eq!(lines.len(), 4);
+        assert!(lines[0].starts_with('[') && lines[0].ends_with("] >> command"));
+        assert!(lines[1].ends_with("] << ok"));
+        assert!(lines[2].ends_with("] >> battery?"));
+        assert!(lines[3].ends_with("] << 87"));
+        
+        let _ = fs::remove_file(&log_path);
+    }
+    
+    /// A log writer whose output the test can read back
+    #[derive(Clone, Default)]
+    struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+    
+    impl Write for SharedBuffer {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+        
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+    
+    #[test]
+    fn test_verbosity() {
+        let (mut tello, mock) = mock_tello();
+        mock.set_response("ls", "photo1.jpg");
+        mock.set_response("battery?", "87");
+        let buffer = SharedBuffer::default();
+        tello.set_log_writer(buffer.clone());
+        let traced = || String::from_utf8(buffer.0.lock().unwrap().clone()).unwrap();
+        
+        // Quiet is the default and traces nothing, not even warnings
+        assert_eq!(Tello::new().unwrap().verbosity(), Level::Quiet);
+        tello.set_verbosity(Level::Quiet);
+        tello.send_command("command").unwrap();
+        tello.list_media().unwrap();
+        tello.takeoff(Some(9.0)).unwrap();
+        assert_eq!(traced(), "");
+        
+        // Warn only traces warnings
+        tello.set_verbosity(Level::Warn);
+        tello.list_media().unwrap();
+        tello.takeoff(Some(9.0)).unwrap();
+        assert_eq!(traced(), "Warning: Requested height 9m exceeds maximum. Using default height (1m).\n");
+        buffer.0.lock().unwrap().clear();
+        
+        // Info traces media progress but not the commands
+        tello.set_verbosity(Level::Info);
+        tello.list_media().unwrap();
+        assert_eq!(traced(), "Found 1 media files on the drone.\n");
+        
+        tello.set_verbosity(Level::Debug);
+        tello.send_command("command").unwrap();
+        assert!(traced().ends_with("Sending command: command\nResponse: ok\n"));
+    }
+    
+    #[test]
+    fn test_stop() {
+        let drone = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let mut tello = Tello::builder()
+            .ip("127.0.0.1")
+            .command_port(drone.local_addr().unwrap().port())
+            .build()
+            .unwrap();
+        
+        assert!(matches!(tello.stop(), Err(TelloError::NotConnected)));
+        
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        socket.set_read_timeout(Some(Duration::from_millis(100))).unwrap();
+        tello.set_transport(Box::new(UdpTransport::new(socket, tello.tello_addr())));
+        
+        let responder = thread::spawn(move || {
+            let mut buffer = [0; 1024];
+            let (amount, from) = drone.recv_from(&mut buffer).unwrap();
+            assert_eq!(&buffer[..amount], b"stop");
+            drone.send_to(b"error", from).unwrap();
+            
+            // Leave the second stop unanswered
+            let (amount, _) = drone.recv_from(&mut buffer).unwrap();
+            assert_eq!(&buffer[..amount], b"stop");
+        });
+        
+        // Neither a non-ok response nor a missing one is a failure
+        tello.stop().unwrap();
+        tello.stop().unwrap();
+        
+        responder.join().unwrap();
+    }
+    
+    #[test]
+    fn test_set_direction() {
+        let mut tello = Tello::new().unwrap();
+        assert_eq!(tello.get_direction(), 0.0);
+        
+        tello.set_direction(-90.0);
+        assert_eq!(tello.get_direction(), 270.0);
+        
+        tello.set_direction(450.0);
+        assert_eq!(tello.get_direction(), 90.0);
+        
+        tello.set_direction(360.0);
+        assert_eq!(tello.get_direction(), 0.0);
+    }
+    
+    #[test]
+    fn test_max_altitude() {
+        let mut tello = Tello::new().unwrap();
+        tello.set_max_altitude(700);
+        tello.set_position(0.0, 0.0, 6.0);
+        
+        // 600 + 300 cm overshoots the ceiling by 200 cm, nothing is sent
+        match tello.up(300) {
+            Err(TelloError::InvalidParameter(message)) => assert!(message.contains("by 200 cm"), "{}", message),
+            other => panic!("Expected InvalidParameter, got {:?}", other),
+        }
+        assert!(matches!(tello.go(50, 0, 150, 50), Err(TelloError::InvalidParameter(_))));
+        assert!(matches!(tello.takeoff(Some(7.5)), Err(TelloError::InvalidParameter(_))));
+        
+        // Within the ceiling the command is attempted
+        assert!(matches!(tello.up(100), Err(TelloError::NotConnected)));
+        
+        tello.clear_max_altitude();
+        assert!(matches!(tello.up(300), Err(TelloError::NotConnected)));
+    }
+    
+    #[test]
+    fn test_geofence() {
+        let (mut tello, mock) = mock_tello();
+        mock.set_response("forward 50", "ok");
+        tello.set_geofence(Position { x: -1.0, y: -1.0, z: 0.0 }, Position { x: 1.0, y: 1.0, z: 2.0 });
+        
+        // 1.5 m forward would go through the wall, 0.5 m stays inside
+        match tello.forward(150) {
+            Err(TelloError::InvalidParameter(message)) => assert!(message.contains("geofence"), "{}", message),
+            other => panic!("Expected InvalidParameter, got {:?}", other),
+        }
+        tello.forward(50).unwrap();
+        assert!(matches!(tello.go(100, 0, 0, 50), Err(TelloError::InvalidParameter(_))));
+        assert!(matches!(tello.up(300), Err(TelloError::InvalidParameter(_))));
+        assert_eq!(mock.get_commands(), vec!["forward 50"]);
+        
+        // Takeoff heights are clamped into the box
+        mock.set_response("battery?", "87");
+        mock.set_response("up 100", "ok");
+        tello.takeoff(Some(3.0)).unwrap();
+        assert_eq!(mock.get_commands()[1..], ["battery?", "takeoff", "up 100"]);
+        
+        tello.clear_geofence();
+        assert!(tello.geofence().is_none());
+    }
+    
+    #[test]
+    fn test_capture_photo_to() {
+        let video_port = UdpSocket::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+        let mut tello = Tello::builder().video_port(video_port).build().unwrap();
+        
+        assert!(matches!(tello.capture_photo_to(None), Err(TelloError::NotConnected)));
+        
+        // Pretend the stream is already on so no command is needed
+        let mock = MockTransport::new();
+        tello.set_transport(Box::new(mock.clone()));
+        tello.video_recording = true;
+        
+        // Stream a JPEG in chunks, starting mid-frame, until the capture is done
+        let done = Arc::new(AtomicBool::new(false));
+        let sender_done = Arc::clone(&done);
+        let sender = thread::spawn(move || {
+            let sender = UdpSocket::bind("127.0.0.1:0").unwrap();
+            let packets: [&[u8]; 4] = [&[0x12, 0x34], &[0xFF, 0xD8, 0x01, 0x02], &[0x03, 0x04], &[0x05, 0xFF, 0xD9]];
+            while !sender_done.load(Ordering::Relaxed) {
+                for packet in packets {
+                    sender.send_to(packet, ("127.0.0.1", video_port)).unwrap();
+                }
+                thread::sleep(Duration::from_millis(20));
+            }
+        });
+        
+        let path = std::env::temp_dir().join(format!("tello_capture_{}.jpg", std::process::id()));
+        let saved = tello.capture_photo_to(Some(path.to_str().unwrap()));
+        done.store(true, Ordering::Relaxed);
+        sender.join().unwrap();
+        
+        assert_eq!(saved.unwrap(), path);
+        assert_eq!(fs::read(&path).unwrap(), vec![0xFF, 0xD8, 0x01, 0x02, 0x03, 0x04, 0x05, 0xFF, 0xD9]);
+        assert!(mock.get_commands().is_empty());
+        fs::remove_file(&path).unwrap();
+    }
+    
+    #[test]
+    fn test_record_video_to() {
+        let video_port = UdpSocket::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+        let mock = MockTransport::new();
+        mock.set_response("streamoff", "ok");
+        let mut tello = Tello::builder().video_port(video_port).build().unwrap();
+        tello.set_transport(Box::new(mock.clone()));
+        tello.set_verbose(false);
+        
+        // Pretend the stream is already on so recording needs no command
+        tello.video_recording = true;
+        let path = std::env::temp_dir().join(format!("tello_video_{}.h264", std::process::id()));
+        tello.record_video_to(path.to_str().unwrap()).unwrap();
+        assert!(matches!(tello.record_video_to(path.to_str().unwrap()), Err(TelloError::InvalidState(_))));
+        
+        let sender = UdpSocket::bind("127.0.0.1:0").unwrap();
+        for packet in [[0, 0, 1], [2, 3, 4], [5, 6, 7]] {
+            sender.send_to(&packet, ("127.0.0.1", video_port)).unwrap();
+        }
+        
+        // The file grows as packets arrive
+        let deadline = Instant::now() + Duration::from_secs(2);
+        while fs::metadata(&path).unwrap().len() < 9 && Instant::now() < deadline {
+            thread::sleep(Duration::from_millis(10));
+        }
+        
+        tello.stop_video().unwrap();
+        assert_eq!(mock.get_commands(), vec!["streamoff"]);
+        
+        assert!(tello.video_thread.is_none());
+        assert_eq!(fs::read(&path).unwrap(), vec![0, 0, 1, 2, 3, 4, 5, 6, 7]);
+        fs::remove_file(&path).unwrap();
+        
+        // A busy video port fails before the file is created
+        let _busy = UdpSocket::bind(("0.0.0.0", video_port)).unwrap();
+        assert!(matches!(tello.record_video_to(path.to_str().unwrap()), Err(TelloError::Io(_))));
+        assert!(!path.exists());
+    }
+    
+    #[test]
+    fn test_receive_jpeg_frame_h264() {
+        let receiver = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let sender = UdpSocket::bind("127.0.0.1:0").unwrap();
+        
+        // An H.264 NAL unit means the frame can't be saved as a JPEG
+        sender.send_to(&[0, 0, 0, 1, 0x67, 0x42], receiver.local_addr().unwrap()).unwrap();
+        let result = receive_jpeg_frame(&receiver, Duration::from_secs(2));
+        assert!(matches!(result, Err(TelloError::Unsupported(_))));
+        
+        // Nothing at all is a timeout
+        let result = receive_jpeg_frame(&receiver, Duration::from_millis(100));
+        assert!(matches!(result, Err(TelloError::Timeout)));
+    }
+    
+    #[test]
+    fn test_critical_battery_landing() {
+        let state_port = UdpSocket::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+        let mock = MockTransport::new();
+        mock.set_response("battery?", "50");
+        let mut tello = Tello::builder().state_port(state_port).build().unwrap();
+        tello.set_transport(Box::new(mock.clone()));
+        tello.set_verbose(false);
+        tello.setup_state_receiver().unwrap();
+        
+        // On the ground a low battery is only reported
+        let sender = UdpSocket::bind("127.0.0.1:0").unwrap();
+        sender.send_to(b"bat:3;h:0;", ("127.0.0.1", state_port)).unwrap();
+        thread::sleep(Duration::from_millis(300));
+        assert!(!mock.get_commands().contains(&String::from("land")));
+        
+        tello.takeoff(None).unwrap();
+        assert!(tello.is_flying());
+        
+        // Above the threshold nothing happens, below it the drone lands
+        sender.send_to(b"bat:6;h:100;", ("127.0.0.1", state_port)).unwrap();
+        thread::sleep(Duration::from_millis(300));
+        assert!(tello.is_flying());
+        sender.send_to(b"bat:4;h:100;", ("127.0.0.1", state_port)).unwrap();
+        
+        let deadline = Instant::now() + Duration::from_secs(2);
+        while tello.is_flying() && Instant::now() < deadline {
+            thread::sleep(Duration::from_millis(10));
+        }
+        assert!(!tello.is_flying());
+        assert_eq!(mock.get_commands(), vec!["battery?", "takeoff", "land"]);
+        
+        tello.disconnect();
+    }
+    
+    #[test]
+    fn test_critical_battery_landing_retry() {
+        let mock = MockTransport::new();
+        mock.set_response("land", "error");
+        let transport: SharedTransport = Arc::new(Mutex::new(Some(Box::new(mock.clone()))));
+        let takeoff_time = Mutex::new(Some(Instant::now()));
+        let logger = Logger::new(Level::Warn);
+        let buffer = SharedBuffer::default();
+        logger.set_writer(Box::new(buffer.clone()));
+        let low = TelemetryState::parse("bat:3;");
+        let mut last_attempt = None;
+        let start = Instant::now();
+        
+        // A rejected landing is retried only after the backoff, warning once
+        land_on_critical_battery(&low, &transport, &takeoff_time, 5, &mut last_attempt, start, &logger);
+        land_on_critical_battery(&low, &transport, &takeoff_time, 5, &mut last_attempt, start + Duration::from_secs(1), &logger);
+        assert_eq!(mock.get_commands(), vec!["land"]);
+        land_on_critical_battery(&low, &transport, &takeoff_time, 5, &mut last_attempt, start + CRITICAL_LANDING_RETRY, &logger);
+        assert_eq!(mock.get_commands(), vec!["land", "land"]);
+        
+        let traced = String::from_utf8(buffer.0.lock().unwrap().clone()).unwrap();
+        assert_eq!(traced.matches("below the critical").count(), 1);
+        assert_eq!(traced.matches("Automatic landing was rejected").count(), 2);
+        
+        // Once down the episode ends
+        mock.set_response("land", "ok");
+        land_on_critical_battery(&low, &transport, &takeoff_time, 5, &mut last_attempt, start + CRITICAL_LANDING_RETRY * 2, &logger);
+        assert!(takeoff_time.lock().unwrap().is_none());
+        land_on_critical_battery(&low, &transport, &takeoff_time, 5, &mut last_attempt, start + CRITICAL_LANDING_RETRY * 3, &logger);
+        assert_eq!(last_attempt, None);
+        assert_eq!(mock.get_commands().len(), 3);
+    }
+    
+    #[test]
+    fn test_flight_time() {
+        let (tello, mock) = mock_tello();
+        mock.set_response("battery?", "87");
+        mock.set_response("time?", "15s");
+        assert_eq!(tello.flight_time(), None);
+        
+        tello.takeoff(None).unwrap();
+        thread::sleep(Duration::from_millis(20));
+        assert!(tello.flight_time().unwrap() >= Duration::from_millis(20));
+        
+        tello.land().unwrap();
+        assert_eq!(tello.flight_time(), None);
+        
+        // A rejected takeoff doesn't start the clock
+        mock.set_response("takeoff", "error");
+        assert!(tello.takeoff(None).is_err());
+        assert_eq!(tello.flight_time(), None);
+        
+        assert_eq!(tello.get_motor_time().unwrap(), 15);
+        assert_eq!(parse_motor_time("0\r\n").unwrap(), 0);
+        assert!(matches!(parse_motor_time("ok"), Err(TelloError::InvalidResponse(_))));
+    }
+    
+    #[test]
+    fn test_temperature() {
+        let state = TelemetryState::parse("templ:70;temph:90;bat:80;");
+        assert_eq!((state.templ, state.temph), (Some(70), Some(90)));
+        
+        // The warning fires once above the threshold and again after cooling down
+        let mut overheated = false;
+        assert!(check_temperature(&state, 85, &mut overheated).unwrap().contains("90°C is above 85°C"));
+        assert_eq!(check_temperature(&state, 85, &mut overheated), None);
+        assert_eq!(check_temperature(&TelemetryState::parse("templ:60;temph:80;"), 85, &mut overheated), None);
+        assert!(check_temperature(&state, 85, &mut overheated).is_some());
+        assert_eq!(check_temperature(&state, 95, &mut false), None);
+        
+        // Without a state receiver there is nothing to read
+        let tello = Tello::new().unwrap();
+        assert!(matches!(tello.get_temperature(), Err(TelloError::InvalidState(_))));
+    }
+    
+    #[test]
+    fn test_height_from_telemetry() {
+        let mut tello = Tello::new().unwrap();
+        assert_eq!(tello.get_height_cm(), None);
+        assert_eq!(tello.get_tof_cm(), None);
+        
+        tello.state_receiver = Some(Arc::new(Mutex::new(String::from("pitch:0;roll:0;yaw:0;tof:65;h:50;bat:80;"))));
+        assert_eq!(tello.get_height_cm(), Some(50));
+        assert_eq!(tello.get_tof_cm(), Some(65));
+        
+        // Older firmware may leave the fields out
+        tello.state_receiver = Some(Arc::new(Mutex::new(String::from("pitch:0;roll:0;yaw:0;bat:80;"))));
+        assert_eq!(tello.get_height_cm(), None);
+    }
+    
+    #[test]
+    fn test_velocity_from_telemetry() {
+        let mut tello = Tello::new().unwrap();
+        assert_eq!(tello.get_velocity(), None);
+        
+        tello.state_receiver = Some(Arc::new(Mutex::new(String::from("pitch:0;roll:0;yaw:0;vgx:10;vgy:-5;vgz:0;h:50;"))));
+        assert_eq!(tello.get_velocity(), Some((10, -5, 0)));
+        
+        // A missing axis gives no velocity rather than a made up zero
+        tello.state_receiver = Some(Arc::new(Mutex::new(String::from("pitch:0;roll:0;yaw:0;vgx:10;vgy:-5;"))));
+        assert_eq!(tello.get_velocity(), None);
+    }
+    
+    #[test]
+    fn test_wait_until_landed() {
+        let mut tello = Tello::new().unwrap();
+        assert!(matches!(tello.wait_until_landed(Duration::from_millis(10)), Err(TelloError::InvalidState(_))));
+        
+        // The drone descends over a few state packets
+        let state = Arc::new(Mutex::new(String::from("pitch:0;roll:0;yaw:0;tof:110;h:100;bat:80;")));
+        tello.state_receiver = Some(Arc::clone(&state));
+        let descent = {
+            let state = Arc::clone(&state);
+            thread::spawn(move || {
+                for height in [80, 60, 40, 20, 5] {
+                    thread::sleep(Duration::from_millis(50));
+                    *state.lock().unwrap() = format!("pitch:0;roll:0;yaw:0;tof:{};h:{};bat:80;", height + 10, height);
+                }
+            })
+        };
+        
+        tello.wait_until_landed(Duration::from_secs(5)).unwrap();
+        assert_eq!(tello.get_height_cm(), Some(5));
+        descent.join().unwrap();
+        
+        // Without "h" the time-of-flight distance is used
+        *state.lock().unwrap() = String::from("pitch:0;roll:0;yaw:0;tof:10;bat:80;");
+        tello.wait_until_landed(Duration::from_millis(10)).unwrap();
+        
+        // Still in the air when the time is up
+        *state.lock().unwrap() = String::from("pitch:0;roll:0;yaw:0;tof:60;h:50;bat:80;");
+        let started = Instant::now();
+        assert!(matches!(tello.wait_until_landed(Duration::from_millis(200)), Err(TelloError::Timeout)));
+        assert!(started.elapsed() >= Duration::from_millis(200));
+    }
+    
+    #[test]
+    fn test_disconnect() {
+        let mut tello = Tello::builder().state_port(0).build().unwrap();
+        tello.setup_state_receiver().unwrap();
+        assert!(tello.get_state().is_some());
+        
+        // The state thread must exit and be joined
+        tello.disconnect();
+        assert!(tello.state_thread.is_none());
+        assert!(tello.get_state().is_none());
+        assert!(!tello.is_connected());
+        
+        // A second disconnect is harmless
+        tello.disconnect();
+    }
+    
+    #[test]
+    fn test_mission_pads() {
+        let (mut tello, mock) = mock_tello();
+        mock.set_response("mon", "ok");
+        mock.set_response("mdirection 2", "ok");
+        mock.set_response("moff", "ok");
+        
+        tello.enable_mission_pads().unwrap();
+        assert!(tello.mission_pads_enabled());
+        tello.set_mission_pad_detection_direction(2).unwrap();
+        tello.disable_mission_pads().unwrap();
+        assert_eq!(mock.get_commands(), vec!["mon", "mdirection 2", "moff"]);
+        
+        // Direction is validated before anything is sent, then detection must be on
+        assert!(matches!(tello.set_mission_pad_detection_direction(3), Err(TelloError::InvalidParameter(_))));
+        assert!(matches!(tello.set_mission_pad_detection_direction(0), Err(TelloError::InvalidState(_))));
+        assert_eq!(mock.get_commands().len(), 3);
+    }
+    
+    #[test]
+    fn test_takeoff_default_height() {
+        let (tello, mock) = mock_tello();
+        mock.set_response("battery?", "87");
+        
+        tello.takeoff(None).unwrap();
+        
+        // No additional commands should be sent as default height is used
+        assert_eq!(mock.get_commands(), vec!["battery?", "takeoff"]);
+    }
+    
+    #[test]
+    fn test_takeoff_custom_height() {
+        let (tello, mock) = mock_tello();
+        mock.set_response("battery?", "87");
+        mock.set_response("up 100", "ok");
+        
+        // Default takeoff is 1m (100cm), so 2m needs another 100cm up
+        tello.takeoff(Some(2.0)).unwrap();
+        
+        assert_eq!(mock.get_commands(), vec!["battery?", "takeoff", "up 100"]);
+    }
+    
+    #[test]
+    fn test_takeoff_stabilization() {
+        let (mut tello, mock) = mock_tello();
+        mock.set_response("battery?", "87");
+        tello.set_takeoff_stabilization(Duration::from_millis(100));
+        
+        // The first adjustment is rejected while the drone settles, the retry waits again
+        mock.set_response("up 100", "error Not joystick");
+        let start = Instant::now();
+        assert!(matches!(tello.takeoff(Some(2.0)), Err(TelloError::CommandRejected(_))));
+        assert!(start.elapsed() >= Duration::from_millis(200));
+        assert_eq!(mock.get_commands(), vec!["battery?", "takeoff", "up 100", "up 100"]);
+        
+        mock.set_response("up 100", "ok");
+        let start = Instant::now();
+        tello.takeoff(Some(2.0)).unwrap();
+        assert!(start.elapsed() >= Duration::from_millis(100));
+        assert_eq!(mock.get_commands().len(), 7);
+        
+        // Without a height adjustment there is nothing to wait for
+        let start = Instant::now();
+        tello.takeoff(None).unwrap();
+        assert!(start.elapsed() < Duration::from_millis(100));
+    }
+    
+    #[test]
+    fn test_takeoff_invalid_height() {
+        let (tello, mock) = mock_tello();
+        mock.set_response("battery?", "87");
+        
+        // Heights above the 8m maximum fall back to the default
+        tello.takeoff(Some(9.0)).unwrap();
+        
+        assert_eq!(mock.get_commands(), vec!["battery?", "takeoff"]);
+    }
+    
+    #[test]
+    fn test_takeoff_to() {
+        let (mut tello, mock) = mock_tello();
+        mock.set_response("battery?", "87");
+        mock.set_response("up 100", "ok");
+        mock.set_response("cw 90", "ok");
+        
+        tello.takeoff_to(2.0, 90.0).unwrap();
+        
+        assert_eq!(mock.get_commands(), vec!["battery?", "takeoff", "up 100", "cw 90"]);
+        assert_eq!(tello.get_direction(), 90.0);
+        
+        // Out of range values are rejected before anything is sent
+        assert!(matches!(tello.takeoff_to(9.0, 0.0), Err(TelloError::InvalidParameter(_))));
+        assert!(matches!(tello.takeoff_to(1.0, 360.0), Err(TelloError::InvalidParameter(_))));
+        assert_eq!(mock.get_commands().len(), 4);
+    }
+    
+    #[test]
+    fn test_takeoff_low_battery() {
+        let (tello, mock) = mock_tello();
+        mock.set_response("battery?", "5");
+        
+        // A 5% battery must be rejected with the default 10% threshold
+        match tello.takeoff(None) {
+            Err(TelloError::InvalidState(message)) => assert_eq!(message, "Battery too low: 5% < 10%"),
+            other => panic!("Expected low battery rejection, got {:?}", other),
+        }
+        
+        // Enough battery
+        assert!(check_takeoff_battery(87, MIN_TAKEOFF_BATTERY).is_ok());
+        
+        // Takeoff itself must not be sent
+        assert_eq!(mock.get_commands(), vec!["battery?"]);
+    }
+    
+    #[test]
+    fn test_get_battery() {
+        let (tello, mock) = mock_tello();
+        mock.set_response("battery?", "87\r\n");
+        
+        // A numeric response parses into the percentage
+        assert_eq!(tello.get_battery().unwrap(), 87u8);
+        assert_eq!(parse_battery("87\r\n").unwrap(), 87u8);
+        
+        // Garbage yields an error
+        assert!(matches!(parse_battery("ok"), Err(TelloError::InvalidResponse(_))));
+        assert!(matches!(parse_battery("250"), Err(TelloError::InvalidResponse(_))));
+    }
+    
+    #[test]
+    fn test_video_resolution_and_fps() {
+        let (tello, mock) = mock_tello();
+        mock.set_response("setresolution low", "ok");
+        mock.set_response("setfps middle", "ok");
+        
+        tello.set_video_resolution(VideoResolution::Low).unwrap();
+        tello.set_video_fps(VideoFps::Middle).unwrap();
+        assert_eq!(mock.get_commands(), vec!["setresolution low", "setfps middle"]);
+        
+        assert_eq!(VideoResolution::High.as_str(), "high");
+        assert_eq!(VideoFps::Low.as_str(), "low");
+        assert_eq!("high".parse::<VideoFps>().unwrap(), VideoFps::High);
+        assert!("medium".parse::<VideoFps>().is_err());
+        assert!("720p".parse::<VideoResolution>().is_err());
+    }
+    
+    #[test]
+    fn test_set_camera() {
+        let (mut tello, mock) = mock_tello();
+        mock.set_response("downvision 1", "ok");
+        mock.set_response("downvision 0", "ok");
+        
+        tello.set_camera(CameraSelect::Downward).unwrap();
+        tello.set_camera(CameraSelect::Forward).unwrap();
+        assert_eq!(mock.get_commands(), vec!["downvision 1", "downvision 0"]);
+        
+        assert_eq!("down".parse::<CameraSelect>().unwrap(), CameraSelect::Downward);
+        assert_eq!("forward".parse::<CameraSelect>().unwrap(), CameraSelect::Forward);
+        assert!("up".parse::<CameraSelect>().is_err());
+        
+        // The original Tello can't switch, so nothing is sent
+        tello.model = Some(TelloModel::Tello);
+        assert!(matches!(tello.set_camera(CameraSelect::Downward), Err(TelloError::Unsupported(_))));
+        assert_eq!(mock.get_commands().len(), 2);
+    }
+    
+    #[test]
+    fn test_video_bitrate() {
+        let (tello, mock) = mock_tello();
+        mock.set_response("setbitrate 3", "ok");
+        mock.set_response("bitrate?", "3\r\n");
+        
+        tello.set_video_bitrate(3).unwrap();
+        assert_eq!(tello.get_video_bitrate().unwrap(), 3);
+        
+        assert!(matches!(tello.set_video_bitrate(6), Err(TelloError::InvalidParameter(_))));
+        assert_eq!(mock.get_commands(), vec!["setbitrate 3", "bitrate?"]);
+        assert!(matches!(parse_bitrate("ok"), Err(TelloError::InvalidResponse(_))));
+    }
+    
+    #[test]
+    fn test_get_wifi_snr() {
+        let (tello, mock) = mock_tello();
+        mock.set_response("wifi?", "90");
+        
+        assert_eq!(tello.get_wifi_snr().unwrap(), 90);
+        assert_eq!(parse_wifi_snr("90\r\n").unwrap(), 90);
+        assert!(matches!(parse_wifi_snr("ok"), Err(TelloError::InvalidResponse(_))));
+    }
+    
+    #[test]
+    fn test_get_sdk_version_and_serial_number() {
+        let (tello, mock) = mock_tello();
+        mock.set_response("sdk?", "30");
+        mock.set_response("sn?", "0TQZABC\r\n");
+        
+        assert_eq!(tello.get_sdk_version().unwrap(), "30");
+        assert_eq!(tello.get_serial_number().unwrap(), "0TQZABC");
+        
+        mock.set_response("sn?", "error");
+        assert!(matches!(tello.get_serial_number(), Err(TelloError::CommandRejected(_))));
+    }
+    
+    #[test]
+    fn test_detect_model() {
+        let (mut tello, mock) = mock_tello();
+        assert_eq!(tello.model(), None);
+        
+        mock.set_response("sdk?", "30");
+        mock.set_response("hardware?", "RMTT");
+        assert_eq!(tello.detect_model().unwrap(), TelloModel::RoboMasterTt);
+        assert_eq!(mock.get_commands(), vec!["sdk?", "hardware?"]);
+        
+        mock.set_response("hardware?", "TELLO");
+        assert_eq!(tello.detect_model().unwrap(), TelloModel::TelloEdu);
+        mock.set_response("sdk?", "20");
+        mock.set_response("hardware?", "error");
+        assert_eq!(tello.detect_model().unwrap(), TelloModel::TelloEdu);
+        
+        // The original Tello doesn't know "sdk?", so mission pads are refused unsent
+        let (mut tello, mock) = mock_tello();
+        mock.set_response("sdk?", "unknown command: sdk?");
+        mock.set_response("mon", "ok");
+        assert_eq!(tello.detect_model().unwrap(), TelloModel::Tello);
+        assert_eq!(tello.model(), Some(TelloModel::Tello));
+        assert!(matches!(tello.enable_mission_pads(), Err(TelloError::Unsupported(_))));
+        assert!(!tello.mission_pads_enabled());
+        assert_eq!(mock.get_commands(), vec!["sdk?"]);
+        
+        // A drone that doesn't answer is not classified
+        let (mut tello, mock) = mock_tello();
+        mock.time_out_next(10);
+        assert!(tello.detect_model().is_err());
+        assert_eq!(tello.model(), None);
+    }
+    
+    #[test]
+    fn test_identity() {
+        let (mut tello, mock) = mock_tello();
+        mock.set_response("sn?", "0TQZH77ED00H1B");
+        mock.set_response("sdk?", "30");
+        
+        let identity = tello.identity().unwrap();
+        assert_eq!(identity, DroneIdentity { serial: String::from("0TQZH77ED00H1B"), sdk_version: String::from("30") });
+        
+        // The second call is answered from the cache
+        assert_eq!(tello.identity().unwrap(), identity);
+        assert_eq!(mock.get_commands(), vec!["sn?", "sdk?"]);
+        
+        // A failed query leaves nothing cached
+        let (mut tello, mock) = mock_tello();
+        mock.set_response("sn?", "0TQZH77ED00H1B");
+        assert!(tello.identity().is_err());
+        mock.set_response("sdk?", "20");
+        assert_eq!(tello.identity().unwrap().sdk_version, "20");
+        assert_eq!(mock.get_commands().len(), 4);
+    }
+    
+    #[test]
+    fn test_query_all() {
+        let (tello, mock) = mock_tello();
+        mock.set_response("sdk?", "30");
+        mock.set_response("battery?", "87\r\n");
+        mock.set_response("wifi?", "90");
+        mock.set_response("hardware?", "error Not supported");
+        
+        let info = tello.query_all();
+        assert_eq!(info.len(), 3);
+        assert_eq!(info["sdk"], "30");
+        assert_eq!(info["battery"], "87");
+        assert_eq!(info["wifi"], "90");
+        
+        // Failed and rejected queries are left out, but all are sent
+        assert!(!info.contains_key("hardware") && !info.contains_key("sn"));
+        assert_eq!(mock.get_commands(), vec!["sdk?", "sn?", "hardware?", "version?", "battery?", "wifi?"]);
+    }
+    
+    #[test]
+    fn test_set_wifi() {
+        let (tello, mock) = mock_tello();
+        mock.set_response("wifi TELLO-Lab secret123", "ok");
+        
+        tello.set_wifi("TELLO-Lab", "secret123").unwrap();
+        assert_eq!(mock.get_commands(), vec!["wifi TELLO-Lab secret123"]);
+        
+        // Credentials with spaces would split into extra SDK arguments
+        assert!(matches!(tello.set_wifi("My Net", "secret"), Err(TelloError::InvalidParameter(_))));
+        assert!(matches!(tello.set_wifi("MyNet", ""), Err(TelloError::InvalidParameter(_))));
+        assert_eq!(mock.get_commands().len(), 1);
+        
+        let tello = Tello::new().unwrap();
+        assert!(matches!(tello.set_wifi("MyNet", "secret"), Err(TelloError::NotConnected)));
+    }
+    
+    #[test]
+    fn test_on_state() {
+        // Find a free port for the state receiver
+        let state_port = UdpSocket::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+        let mut tello = Tello::builder().local_port(0).state_port(state_port).build().unwrap();
+        tello.set_verbose(false);
+        
+        let (sender, receiver) = std::sync::mpsc::channel();
+        tello.on_state(move |state| {
+            let _ = sender.send(state.clone());
+        });
+        tello.setup_state_receiver().unwrap();
+        
+        let drone = UdpSocket::bind("127.0.0.1:0").unwrap();
+        drone.send_to(b"pitch:1;roll:2;yaw:90;h:50;bat:87;", ("127.0.0.1", state_port)).unwrap();
+        
+        let state = receiver.recv_timeout(Duration::from_secs(3)).unwrap();
+        assert_eq!(state.yaw, Some(90));
+        assert_eq!(state.h, Some(50));
+        assert_eq!(state.bat, Some(87));
+        
+        tello.stop_state_receiver();
+    }
+    
+    #[test]
+    fn test_telemetry_log() {
+        let state_port = UdpSocket::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+        let mut tello = Tello::builder().local_port(0).state_port(state_port).build().unwrap();
+        tello.set_verbose(false);
+        let csv_path = std::env::temp_dir().join(format!("tello_telemetry_{}.csv", std::process::id()));
+        let json_path = std::env::temp_dir().join(format!("tello_telemetry_{}.jsonl", std::process::id()));
+        let _ = fs::remove_file(&csv_path);
+        let _ = fs::remove_file(&json_path);
+        
+        // The log is written before the callbacks run, so this waits for each row
+        let (sender, receiver) = std::sync::mpsc::channel();
+        tello.on_state(move |state| {
+            let _ = sender.send(state.clone());
+        });
+        tello.setup_state_receiver().unwrap();
+        let drone = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let send_states = |heights: &[i32]| {
+            for h in heights {
+                drone.send_to(format!("pitch:0;roll:0;yaw:0;h:{};bat:87;", h).as_bytes(), ("127.0.0.1", state_port)).unwrap();
+                receiver.recv_timeout(Duration::from_secs(3)).unwrap();
+            }
+        };
+        
+        tello.start_telemetry_log(csv_path.to_str().unwrap(), LogFormat::Csv).unwrap();
+        send_states(&[10, 20, 30]);
+        tello.start_telemetry_log(json_path.to_str().unwrap(), LogFormat::Json).unwrap();
+        send_states(&[40, 50]);
+        tello.stop_telemetry_log();
+        send_states(&[60]);
+        tello.stop_state_receiver();
+        
+        let csv = fs::read_to_string(&csv_path).unwrap();
+        let rows: Vec<&str> = csv.lines().collect();
+        assert_eq!(rows.len(), 4, "{}", csv);
+        assert_eq!(rows[0], crate::telemetry::CSV_HEADER);
+        assert!(rows[3].ends_with(",30,87,,,,,"), "{}", rows[3]);
+        
+        let json = fs::read_to_string(&json_path).unwrap();
+        let lines: Vec<serde_json::Value> = json.lines().map(|line| serde_json::from_str(line).unwrap()).collect();
+        assert_eq!(lines.len(), 2, "{}", json);
+        assert_eq!(lines[1]["h"], 50);
+        assert!(lines[1]["timestamp"].is_string());
+        
+        let _ = fs::remove_file(&csv_path);
+        let _ = fs::remove_file(&json_path);
+        assert!(tello.start_telemetry_log("/nonexistent/telemetry.csv", LogFormat::Csv).is_err());
+    }
+    
+    #[test]
+    fn test_reboot() {
+        let (tello, mock) = mock_tello();
+        tello.reboot().unwrap();
+        assert_eq!(mock.get_commands(), vec!["reboot"]);
+        
+        // Not in the air
+        let (mut tello, mock) = mock_tello();
+        tello.set_min_takeoff_battery(0);
+        tello.takeoff(None).unwrap();
+        assert!(matches!(tello.reboot(), Err(TelloError::InvalidState(_))));
+        assert_eq!(mock.get_commands(), vec!["takeoff"]);
+    }
+    
+    #[test]
+    fn test_connect_to_ap() {
+        let (tello, mock) = mock_tello();
+        mock.set_response("ap MyNet secret", "ok");
+        
+        tello.connect_to_ap("MyNet", "secret").unwrap();
+        assert_eq!(mock.get_commands(), vec!["ap MyNet secret"]);
+        
+        assert!(matches!(tello.connect_to_ap("", "secret"), Err(TelloError::InvalidParameter(_))));
+        
+        // Anything but "ok" is a rejection
+        assert!(matches!(tello.connect_to_ap("Other", "secret"), Err(TelloError::CommandRejected(_))));
+    }
+    
+    #[test]
+    fn test_set_speed_range() {
+        let mut tello = Tello::new().unwrap();
+        
+        // Values outside 10..=100 are rejected before sending
+        assert!(matches!(tello.set_speed(9), Err(TelloError::InvalidParameter(_))));
+        assert!(matches!(tello.set_speed(101), Err(TelloError::InvalidParameter(_))));
+        
+        // Boundaries are valid and only fail for lack of a connection
+        assert!(matches!(tello.set_speed(10), Err(TelloError::NotConnected)));
+        assert!(matches!(tello.set_speed(100), Err(TelloError::NotConnected)));
+        assert_eq!(tello.last_set_speed(), None);
+        
+        // The drone reports its speed as a float
+        assert_eq!(parse_speed("100.0\r\n").unwrap(), 100.0);
+        assert!(parse_speed("error").is_err());
+    }
+    
+    #[test]
+    fn test_throw_takeoff() {
+        let (tello, mock) = mock_tello();
+        mock.set_response("battery?", "87");
+        mock.set_response("throwfly", "ok");
+        
+        tello.throw_takeoff().unwrap();
+        
+        assert_eq!(mock.get_commands(), vec!["battery?", "throwfly"]);
+        assert!(tello.is_flying());
+        
+        // The launch height is unknown, so the tracked height stays put
+        assert_eq!(tello.get_position().z, 0.0);
+        
+        // Without an answer the drone may be in the air, so it counts as flying
+        tello.land().unwrap();
+        assert!(!tello.is_flying());
+        mock.set_delay("throwfly", THROW_TAKEOFF_TIMEOUT * 2);
+        tello.throw_takeoff().unwrap();
+        assert!(tello.is_flying());
+    }
+    
+    #[test]
+    fn test_motor_on_off() {
+        let (tello, mock) = mock_tello();
+        mock.set_response("battery?", "87");
+        mock.set_response("motoron", "ok");
+        mock.set_response("motoroff", "ok");
+        
+        tello.motor_on().unwrap();
+        tello.motor_off().unwrap();
+        assert_eq!(mock.get_commands(), vec!["motoron", "motoroff"]);
+        
+        // Turning the motors off in flight is refused without sending anything
+        tello.takeoff(None).unwrap();
+        assert!(matches!(tello.motor_off(), Err(TelloError::InvalidState(_))));
+        assert_eq!(mock.get_commands().last().unwrap(), "takeoff");
+    }
+    
+    #[test]
+    fn test_land() {
+        let (tello, mock) = mock_tello();
+        
+        tello.land().unwrap();
+        
+        assert_eq!(mock.get_commands(), vec!["land"]);
+    }
+    
+    #[test]
+    fn test_land_with_options() {
+        // A second attempt is enough when the first answer is lost
+        let (tello, mock) = mock_tello();
+        mock.time_out_next(1);
+        tello.land_with_options(false).unwrap();
+        assert_eq!(mock.get_commands(), vec!["land", "land"]);
+        
+        // Without force the error is surfaced and the motors keep running
+        let (tello, mock) = mock_tello();
+        mock.time_out_next(2);
+        assert!(matches!(tello.land_with_options(false), Err(TelloError::Timeout)));
+        assert_eq!(mock.get_commands(), vec!["land", "land"]);
+        
+        // With force a drone that never acknowledges gets an emergency stop, with a warning
+        let (mut tello, mock) = mock_tello();
+        let buffer = SharedBuffer::default();
+        tello.set_log_writer(buffer.clone());
+        tello.set_verbosity(Level::Warn);
+        mock.time_out_next(2);
+        tello.land_with_options(true).unwrap();
+        assert_eq!(mock.get_commands(), vec!["land", "land", "emergency"]);
+        let traced = String::from_utf8(buffer.0.lock().unwrap().clone()).unwrap();
+        assert!(traced.contains("Warning: Land failed again"), "{}", traced);
+        
+        let (tello, mock) = mock_tello();
+        mock.set_response("land", "error");
+        tello.land_with_options(true).unwrap();
+        assert_eq!(mock.get_commands(), vec!["land", "land", "emergency"]);
+    }
+    
+    #[test]
+    fn test_emergency() {
+        let (tello, mock) = mock_tello();
+        
+        // The drone may not answer "ok" before the motors stop
+        tello.emergency().unwrap();
+        
+        assert_eq!(mock.get_commands(), vec!["emergency"]);
+    }
+    
+    #[test]
+    fn test_error_response() {
+        let (mut tello, mock) = mock_tello();
+        tello.set_min_takeoff_battery(0);
+        
+        // Set an error response for takeoff
+        mock.set_response("takeoff", "error");
+        
+        // This should result in an error
+        match tello.takeoff(None) {
+            Err(TelloError::CommandRejected(response)) => assert_eq!(response, "error"),
+            other => panic!("Expected CommandRejected, got {:?}", other),
+        }
+    }
+    
+    #[test]
+    fn test_response_timeout() {
+        let (tello, mock) = mock_tello();
+        mock.set_response("battery?", "87");
+        
+        tello.takeoff(None).unwrap();
+        
+        // Takeoff may take seconds, the battery query answers at once
+        assert_eq!(mock.get_timeout("takeoff"), Some(FLIGHT_RESPONSE_TIMEOUT));
+        assert_eq!(mock.get_timeout("battery?"), Some(DEFAULT_QUERY_TIMEOUT));
+        assert_eq!(response_timeout("cw 90"), Some(FLIGHT_RESPONSE_TIMEOUT));
+        assert_eq!(response_timeout("streamon"), None);
+        assert_eq!(response_timeout("emergency"), None);
+    }
+    
+    #[test]
+    fn test_calibrate_imu() {
+        let (tello, mock) = mock_tello();
+        
+        // Calibration answers far later than an ordinary command may
+        mock.set_response("calibrate", "ok");
+        mock.set_delay("calibrate", Duration::from_secs(15));
+        tello.calibrate_imu().unwrap();
+        assert_eq!(mock.get_commands().last().map(String::as_str), Some("calibrate"));
+        assert_eq!(mock.get_timeout("calibrate"), Some(CALIBRATION_TIMEOUT));
+        
+        mock.set_response("calibrate", "error");
+        assert!(tello.calibrate_imu().is_err());
+        
+        // Never while flying
+        mock.set_response("battery?", "87");
+        tello.takeoff(None).unwrap();
+        assert!(matches!(tello.calibrate_imu(), Err(TelloError::InvalidState(_))));
+    }
+    
+    #[test]
+    fn test_arming() {
+        let (mut tello, mock) = mock_tello();
+        mock.set_response("battery?", "87");
+        mock.set_response("forward 50", "ok");
+        
+        // Off by default
+        tello.takeoff(None).unwrap();
+        tello.land().unwrap();
+        
+        tello.set_require_arming(true);
+        assert!(matches!(tello.takeoff(None), Err(TelloError::InvalidState(_))));
+        assert!(matches!(tello.forward(50), Err(TelloError::InvalidState(_))));
+        assert!(!mock.get_commands().contains(&String::from("forward 50")));
+        
+        tello.arm();
+        tello.takeoff(None).unwrap();
+        tello.forward(50).unwrap();
+        
+        // Disarmed in the air the drone can still stop and land
+        tello.disarm();
+        assert!(matches!(tello.forward(50), Err(TelloError::InvalidState(_))));
+        tello.stop().unwrap();
+        tello.land().unwrap();
+        
+        assert!(needs_arming("cw 90"));
+        assert!(!needs_arming("speed 50"));
+        assert!(!needs_arming("battery?"));
+    }
+    
+    #[test]
+    fn test_rc_arming() {
+        let (mut tello, mock) = mock_tello();
+        tello.set_require_arming(true);
+        
+        // Stick input needs arming, centering the sticks doesn't
+        assert!(matches!(tello.send_rc_control(10, 0, 0, 0), Err(TelloError::InvalidState(_))));
+        assert!(mock.get_commands().is_empty());
+        tello.send_rc_control(0, 0, 0, 0).unwrap();
+        assert_eq!(mock.get_commands(), vec!["rc 0 0 0 0"]);
+        
+        tello.arm();
+        tello.send_rc_control(10, 0, 0, 0).unwrap();
+        assert_eq!(mock.get_commands(), vec!["rc 0 0 0 0", "rc 10 0 0 0"]);
+    }
+    
+    #[test]
+    fn test_send_command_async() {
+        let (mut tello, mock) = mock_tello();
+        mock.set_response("takeoff", "ok");
+        mock.set_response("battery?", "87\r\n");
+        mock.set_response("forward 50", "error Motor stop");
+        
+        // Queued commands go out in order and each answer reaches its own receiver
+        let takeoff = tello.send_command_async("takeoff").unwrap();
+        let battery = tello.send_command_async("battery?").unwrap();
+        let forward = tello.send_command_async("forward 50").unwrap();
+        assert!(matches!(forward.recv().unwrap(), Err(TelloError::CommandRejected(reason)) if reason == "Motor stop"));
+        assert_eq!(battery.recv().unwrap().unwrap(), "87");
+        assert_eq!(takeoff.recv().unwrap().unwrap(), "ok");
+        assert_eq!(mock.get_commands(), vec!["takeoff", "battery?", "forward 50"]);
+        
+        // Timeouts are retried on the worker as well
+        tello.set_command_retries(1);
+        mock.time_out_next(1);
+        assert_eq!(tello.send_command_async("battery?").unwrap().recv().unwrap().unwrap(), "87");
+        assert_eq!(mock.get_commands().len(), 5);
+        
+        tello.disconnect();
+        assert!(matches!(tello.send_command_async("land"), Err(TelloError::NotConnected)));
+    }
+    
+    #[test]
+    fn test_command_retries() {
+        let (mut tello, mock) = mock_tello();
+        mock.set_response("forward 50", "ok");
+        
+        // Without retries a lost answer is a timeout
+        mock.time_out_next(1);
+        assert!(matches!(tello.forward(50), Err(TelloError::Timeout)));
+        
+        // Two lost answers, the third try gets "ok"
+        tello.set_command_retries(2);
+        mock.time_out_next(2);
+        tello.forward(50).unwrap();
+        assert_eq!(mock.get_commands().len(), 4);
+        
+        // Out of retries the timeout is returned
+        mock.time_out_next(3);
+        assert!(matches!(tello.forward(50), Err(TelloError::Timeout)));
+        
+        // Rejections are not retried
+        mock.set_response("forward 60", "error Motor stop");
+        let sent = mock.get_commands().len();
+        assert!(matches!(tello.forward(60), Err(TelloError::CommandRejected(_))));
+        assert_eq!(mock.get_commands().len(), sent + 1);
+    }
+    
+    #[test]
+    fn test_error_reason() {
+        let (mut tello, mock) = mock_tello();
+        mock.set_response("forward 50", "error Motor stop");
+        
+        match tello.forward(50) {
+            Err(TelloError::CommandRejected(reason)) => assert_eq!(reason, "Motor stop"),
+            other => panic!("Expected CommandRejected, got {:?}", other),
+        }
+        
+        assert_eq!(error_reason("error Not joystick\r\n"), Some(String::from("Not joystick")));
+        assert_eq!(error_reason("error: No valid imu"), Some(String::from("No valid imu")));
+        assert_eq!(error_reason("error"), Some(String::from("error")));
+        assert_eq!(error_reason("errorcode"), None);
+        assert_eq!(error_reason("ok"), None);
+    }
+    
+    #[test]
+    fn test_take_photo() {
+        let (tello, mock) = mock_tello();
+        mock.set_response("snapshot", "ok");
+        
+        assert_eq!(tello.take_photo().unwrap(), "ok");
+        assert_eq!(mock.get_commands(), vec!["snapshot"]);
+        
+        // The command that worked is used again without probing
+        mock.set_response("snapshot", "error");
+        assert!(tello.take_photo().is_err());
+        assert_eq!(mock.get_commands(), vec!["snapshot", "snapshot"]);
+    }
+    
+    #[test]
+    fn test_photo_command() {
+        // Probing falls back to "takepic" once, then sticks with it
+        let (tello, mock) = mock_tello();
+        mock.set_response("takepic", "ok");
+        tello.take_photo().unwrap();
+        tello.take_photo().unwrap();
+        assert_eq!(mock.get_commands(), vec!["snapshot", "takepic", "takepic"]);
+        
+        // A detected model picks the command without probing
+        let (mut tello, mock) = mock_tello();
+        mock.set_response("sdk?", "30");
+        mock.set_response("hardware?", "TELLO");
+        mock.set_response("takepic", "ok");
+        tello.detect_model().unwrap();
+        tello.take_photo().unwrap();
+        assert_eq!(mock.get_commands(), vec!["sdk?", "hardware?", "takepic"]);
+        
+        // An override wins over the model
+        tello.set_photo_command("snapshot");
+        mock.set_response("snapshot", "ok");
+        tello.take_photo().unwrap();
+        assert_eq!(mock.get_commands().last().unwrap(), "snapshot");
+    }
+    
+    #[test]
+    fn test_start_video() {
+        let (mut tello, mock) = mock_tello();
+        mock.set_response("streamon", "ok");
+        
+        assert_eq!(tello.start_video().unwrap(), "ok");
+        assert!(tello.video_recording);
+        assert_eq!(mock.get_commands(), vec!["streamon"]);
+        
+        // A second start is refused without sending anything
+        assert!(matches!(tello.start_video(), Err(TelloError::InvalidState(_))));
+        assert_eq!(mock.get_commands().len(), 1);
+    }
+    
+    #[test]
+    fn test_stop_video() {
+        let (mut tello, mock) = mock_tello();
+        mock.set_response("streamoff", "ok");
+        
+        assert!(matches!(tello.stop_video(), Err(TelloError::InvalidState(_))));
+        
+        tello.video_recording = true;
+        assert_eq!(tello.stop_video().unwrap(), "ok");
+        assert!(!tello.video_recording);
+        assert_eq!(mock.get_commands(), vec!["streamoff"]);
+    }
+    
+    #[test]
+    fn test_list_media() {
+        let (tello, mock) = mock_tello();
+        mock.set_response("ls", "file1.jpg\nfile2.mp4\nok");
+        
+        assert_eq!(tello.list_media().unwrap(), vec!["file1.jpg", "file2.mp4"]);
+        assert_eq!(mock.get_commands(), vec!["ls"]);
+    }
+    
+    #[test]
+    fn test_link_watchdog() {
+        let mut tello = Tello::new().unwrap();
+        assert!(!tello.is_link_alive(Duration::from_secs(2)));
+        
+        // The last state packet arrived 5 seconds ago
+        let stale = Instant::now().checked_sub(Duration::from_secs(5)).unwrap();
+        tello.last_state_time = Arc::new(Mutex::new(Some(stale)));
+        assert!(!tello.is_link_alive(Duration::from_secs(2)));
+        assert!(tello.is_link_alive(Duration::from_secs(10)));
+        
+        *tello.last_state_time.lock().unwrap() = Some(Instant::now());
+        assert!(tello.is_link_alive(Duration::from_secs(2)));
+        
+        // The watchdog warns once per outage
+        let now = Instant::now();
+        let mut lost = false;
+        assert_eq!(check_link(None, now, STATE_STALE_AFTER, &mut lost), None);
+        assert!(check_link(Some(stale), now, STATE_STALE_AFTER, &mut lost).unwrap().contains("link to the drone may be lost"));
+        assert_eq!(check_link(Some(stale), now, STATE_STALE_AFTER, &mut lost), None);
+        assert_eq!(check_link(Some(now), now, STATE_STALE_AFTER, &mut lost).as_deref(), Some("State packets are arriving again"));
+        assert_eq!(check_link(Some(now), now, STATE_STALE_AFTER, &mut lost), None);
+    }
+    
+    #[test]
+    fn test_status_not_connected() {
+        let tello = Tello::new().unwrap();
+        let status = tello.status();
+        
+        assert!(!status.connected);
+        assert!(!status.receiving_state);
+        assert_eq!(status.state_age, None);
+        assert!(!status.video_recording);
+        assert_eq!((status.position.x, status.position.y, status.position.z), (0.0, 0.0, 0.0));
+        assert_eq!(status.heading, 0.0);
+        assert_eq!(status.battery, None);
+    }
+    
+    #[test]
+    fn test_dry_run() {
+        let mut tello = Tello::new().unwrap();
+        tello.set_verbose(false);
+        tello.set_dry_run(true);
+        
+        // No socket is bound; the dry-run transport answers everything
+        tello.forward(50).unwrap();
+        let position = tello.get_position();
+        assert!((position.y - 0.5).abs() < 0.001);
+        assert_eq!(tello.get_battery().unwrap(), 100);
+        
+        tello.set_dry_run_response("battery?", "42");
+        assert_eq!(tello.get_battery().unwrap(), 42);
+        
+        tello.set_dry_run(false);
+        assert!(!tello.is_connected());
+    }
+    
+    #[test]
+    fn test_list_media_detailed() {
+        use crate::media::MediaKind;
+        
+        let (tello, mock) = mock_tello();
+        mock.set_response("ls", "DJI_0001.jpg\nDJI_0002.mp4\nok");
+        
+        let files = tello.list_media_detailed().unwrap();
+        
+        assert_eq!(files.len(), 2);
+        assert_eq!(files[0].name, "DJI_0001.jpg");
+        assert_eq!(files[0].kind, MediaKind::Photo);
+        assert_eq!(files[1].kind, MediaKind::Video);
+        assert_eq!(files[1].size, None);
+    }
+    
+    #[test]
+    fn test_download_media() {
+        let (mut tello, mock) = mock_tello();
+        mock.set_response("download file1.jpg", "ok");
+        let download_dir = std::env::temp_dir().join(format!("tello_download_{}", std::process::id()));
+        tello.set_download_path(download_dir.to_str().unwrap()).unwrap();
+        
+        let result = tello.download_media("file1.jpg").unwrap();
+        
+        assert!(result.ends_with("file1.jpg"), "{}", result);
+        assert_eq!(mock.get_commands(), vec!["download file1.jpg"]);
+        let _ = fs::remove_dir_all(download_dir);
+    }
+    
+    #[test]
+    fn test_download_all_media() {
+        use std::net::TcpStream;
+        
+        let port = TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+        let mock = MockTransport::new();
+        let mut tello = Tello::builder().file_transfer_port(port).build().unwrap();
+        tello.set_transport(Box::new(mock.clone()));
+        tello.set_verbose(false);
+        mock.set_response("ls", "a.jpg 5\nc.jpg\nb.mp4\nok");
+        mock.set_response("direct_transfer a.jpg", "ok");
+        mock.set_response("direct_transfer b.mp4", "ok");
+        let download_dir = std::env::temp_dir().join(format!("tello_download_all_{}", std::process::id()));
+        tello.set_download_path(download_dir.to_str().unwrap()).unwrap();
+        
+        // The drone connects once for each file it accepted
+        let sender = thread::spawn(move || {
+            for data in [&b"photo"[..], &b"video"[..]] {
+                let deadline = Instant::now() + Duration::from_secs(2);
+                let mut stream = loop {
+                    match TcpStream::connect(("127.0.0.1", port)) {
+                        Ok(stream) => break stream,
+                        Err(e) if Instant::now() > deadline => panic!("Could not connect: {}", e),
+                        Err(_) => thread::sleep(Duration::from_millis(10)),
+                    }
+                };
+                stream.write_all(data).unwrap();
+                stream.shutdown(std::net::Shutdown::Write).unwrap();
+                // Wait until the file is saved and the listener closed before the next one
+                let _ = stream.read(&mut [0; 1]);
+                thread::sleep(Duration::from_millis(100));
+            }
+        });
+        
+        let downloads = tello.download_all_media().unwrap();
+        sender.join().unwrap();
+        
+        assert_eq!(mock.get_commands(), vec!["ls", "direct_transfer a.jpg", "direct_transfer c.jpg", "direct_transfer b.mp4"]);
+        assert_eq!(downloads.saved, vec![download_dir.join("a.jpg"), download_dir.join("b.mp4")]);
+        assert_eq!(fs::read(&downloads.saved[0]).unwrap(), b"photo");
+        assert_eq!(fs::read(&downloads.saved[1]).unwrap(), b"video");
+        // The rejected file is reported without aborting the others
+        assert_eq!(downloads.failed.len(), 1);
+        assert_eq!(downloads.failed[0].0, "c.jpg");
+        assert!(!download_dir.join("c.jpg").exists());
+        let _ = fs::remove_dir_all(download_dir);
+        
+        // Without a listing there is nothing to download
+        let (tello, _mock) = mock_tello();
+        assert!(tello.download_all_media().is_err());
+    }
+    
+    #[test]
+    fn test_delete_media() {
+        let (tello, mock) = mock_tello();
+        mock.set_response("rm file1.jpg", "ok");
+        
+        assert_eq!(tello.delete_media("file1.jpg").unwrap(), "Deleted file1.jpg");
+        assert_eq!(mock.get_commands(), vec!["rm file1.jpg"]);
+    }
+    
+    #[test]
+    fn test_delete_all_media() {
+        let (tello, mock) = mock_tello();
+        mock.set_response("rmall", "ok");
+        
+        tello.delete_all_media().unwrap();
+        assert_eq!(mock.get_commands(), vec!["rmall"]);
+    }
+    
+    #[test]
+    fn test_rotate_cw() {
+        let (mut tello, mock) = mock_tello();
+        mock.set_response("cw 90", "ok");
+        
+        tello.rotate_cw(90).unwrap();
         
-        Ok(format!("Downloaded to {}", dest_path))
+        assert_eq!(tello.get_direction(), 90.0);
+        assert_eq!(mock.get_commands(), vec!["cw 90"]);
     }
     
-    /// Delete media file from drone
-    pub fn delete_media(&self, filename: &str) -> io::Result<String> {
-        let cmd = format!("rm {}", filename);
-        let response = self.send_command(&cmd)?;
+    #[test]
+    fn test_rotate_ccw() {
+        let (mut tello, mock) = mock_tello();
+        mock.set_response("ccw 90", "ok");
         
-        if response != "ok" {
-            return Err(io::Error::new(
-                io::ErrorKind::Other,
-                format!("Failed to delete file {}: {}", filename, response),
-            ));
-        }
+        tello.rotate_ccw(90).unwrap();
         
-        Ok(format!("Deleted {}", filename))
+        assert_eq!(tello.get_direction(), 270.0);
+        assert_eq!(mock.get_commands(), vec!["ccw 90"]);
     }
     
-    /// Delete all media files from drone
-    pub fn delete_all_media(&self) -> io::Result<String> {
-        let response = self.send_command("rmall")?;
+    #[test]
+    fn test_rotation_normalization() {
+        let (mut tello, mock) = mock_tello();
+        mock.set_response("cw 90", "ok");
+        mock.set_response("ccw 90", "ok");
         
-        if response != "ok" {
-            return Err(io::Error::new(
-                io::ErrorKind::Other,
-                format!("Failed to delete all media: {}", response),
-            ));
-        }
+        tello.rotate_cw(450).unwrap();
+        assert_eq!(tello.get_direction(), 90.0);
+        tello.rotate_cw(-90).unwrap();
+        assert_eq!(tello.get_direction(), 0.0);
+        tello.rotate_ccw(-450).unwrap();
+        assert_eq!(tello.get_direction(), 90.0);
+        assert_eq!(mock.get_commands(), vec!["cw 90", "ccw 90", "cw 90"]);
         
-        Ok("All media files deleted".to_string())
+        // Whole turns send nothing
+        tello.rotate_cw(720).unwrap();
+        tello.rotate_ccw(0).unwrap();
+        assert_eq!(mock.get_commands().len(), 3);
+        
+        tello.set_strict_rotation(true);
+        assert!(matches!(tello.rotate_cw(450), Err(TelloError::InvalidParameter(_))));
+        assert!(matches!(tello.rotate_cw(-90), Err(TelloError::InvalidParameter(_))));
+        tello.rotate_cw(90).unwrap();
+        assert_eq!(mock.get_commands().len(), 4);
     }
     
-    /// Rotate clockwise by a specified number of degrees
-    pub fn rotate_cw(&mut self, degrees: i32) -> io::Result<()> {
-        if degrees <= 0 || degrees > 360 {
-            return Err(io::Error::new(
-                io::ErrorKind::InvalidInput,
-                format!("Invalid rotation value: {}. Should be between 1 and 360 degrees.", degrees),
-            ));
-        }
+    #[test]
+    fn test_mission_pad_flight() {
+        let (mut tello, mock) = mock_tello();
+        mock.set_response("mon", "ok");
+        mock.set_response("go 0 0 80 50 m1", "ok");
+        mock.set_response("jump 100 0 80 50 90 m1 m2", "ok");
         
-        let response = self.send_command(&format!("cw {}", degrees))?;
+        // Detection must be on first
+        assert!(matches!(tello.go_to_pad(0, 0, 80, 50, 1), Err(TelloError::InvalidState(_))));
         
-        if response != "ok" {
-            return Err(io::Error::new(
-                io::ErrorKind::Other,
-                format!("Rotate clockwise command failed: {}", response),
-            ));
-        }
+        tello.enable_mission_pads().unwrap();
+        tello.go_to_pad(0, 0, 80, 50, 1).unwrap();
+        tello.jump((100, 0, 80), 50, 90, 1, 2).unwrap();
+        assert_eq!(mock.get_commands(), vec!["mon", "go 0 0 80 50 m1", "jump 100 0 80 50 90 m1 m2"]);
         
-        // Update current direction
-        self.current_direction = (self.current_direction + degrees as f32) % 360.0;
+        // Pad relative moves leave the tracked position alone
+        assert_eq!(tello.get_position(), Position { x: 0.0, y: 0.0, z: 0.0 });
         
-        Ok(())
+        assert!(matches!(tello.go_to_pad(0, 0, 80, 50, 9), Err(TelloError::InvalidParameter(_))));
+        assert!(matches!(tello.go_to_pad(0, 0, 80, 50, 0), Err(TelloError::InvalidParameter(_))));
+        assert!(matches!(tello.jump((100, 0, 80), 50, 90, 1, 9), Err(TelloError::InvalidParameter(_))));
+        assert!(matches!(tello.go_to_pad(600, 0, 80, 50, 1), Err(TelloError::InvalidParameter(_))));
+        assert_eq!(mock.get_commands().len(), 3);
     }
     
-    /// Rotate counter-clockwise by a specified number of degrees
-    pub fn rotate_ccw(&mut self, degrees: i32) -> io::Result<()> {
-        if degrees <= 0 || degrees > 360 {
-            return Err(io::Error::new(
-                io::ErrorKind::InvalidInput,
-                format!("Invalid rotation value: {}. Should be between 1 and 360 degrees.", degrees),
-            ));
-        }
+    #[test]
+    fn test_flip() {
+        use crate::tello_movement::FlipDirection;
         
-        let response = self.send_command(&format!("ccw {}", degrees))?;
+        let (mut tello, mock) = mock_tello();
+        mock.set_response("flip f", "ok");
         
-        if response != "ok" {
-            return Err(io::Error::new(
-                io::ErrorKind::Other,
-                format!("Rotate counter-clockwise command failed: {}", response),
-            ));
-        }
+        // "forward" must map to the SDK direction code "f"
+        let direction: FlipDirection = "forward".parse().unwrap();
+        assert_eq!(direction, FlipDirection::Forward);
         
-        // Update current direction
-        self.current_direction = (self.current_direction - degrees as f32 + 360.0) % 360.0;
+        tello.flip(direction).unwrap();
+        assert_eq!(mock.get_commands(), vec!["flip f"]);
         
-        Ok(())
+        // Unknown directions are rejected
+        assert!("up".parse::<FlipDirection>().is_err());
     }
     
-    /// Point camera towards center of rotation
-    /// 
-    /// If the drone is positioned at coordinates (x, y) and center is at (center_x, center_y),
-    /// this function will rotate the drone to point its camera towards the center
-    pub fn point_camera_to_center(&mut self, center_x: f32, center_y: f32) -> io::Result<()> {
-        let dx = center_x - self.current_position.x;
-        let dy = center_y - self.current_position.y;
-        
-        // Calculate angle to center in degrees
-        let target_angle = dy.atan2(dx).to_degrees() + 90.0;
-        let normalized_target = (target_angle + 360.0) % 360.0;
+    #[test]
+    fn test_forward() {
+        let (mut tello, mock) = mock_tello();
+        mock.set_response("forward 50", "ok");
         
-        // Calculate the shortest rotation to reach the target angle
-        let mut rotation = normalized_target - self.current_direction;
-        if rotation > 180.0 {
-            rotation -= 360.0;
-        } else if rotation < -180.0 {
-            rotation += 360.0;
-        }
+        tello.forward(50).unwrap();
         
-        // Execute the rotation
-        if rotation.abs() < 1.0 {
-            // Already pointing in the right direction
-            return Ok(());
-        } else if rotation > 0.0 {
-            self.rotate_cw(rotation.round() as i32)?;
-        } else {
-            self.rotate_ccw((-rotation).round() as i32)?;
-        }
+        assert_eq!(mock.get_commands(), vec!["forward 50"]);
+        assert!((tello.get_position().y - 0.5).abs() < 1e-4);
         
-        Ok(())
+        // A rejected move leaves the tracked position alone
+        assert!(matches!(tello.forward(60), Err(TelloError::CommandRejected(_))));
+        assert!((tello.get_position().y - 0.5).abs() < 1e-4);
     }
     
-    /// Point camera away from center of rotation
-    /// 
-    /// If the drone is positioned at coordinates (x, y) and center is at (center_x, center_y),
-    /// this function will rotate the drone to point its camera away from the center
-    pub fn point_camera_from_center(&mut self, center_x: f32, center_y: f32) -> io::Result<()> {
-        let dx = center_x - self.current_position.x;
-        let dy = center_y - self.current_position.y;
+    #[test]
+    fn test_position_history() {
+        let (mut tello, mock) = mock_tello();
+        mock.set_response("forward 50", "ok");
+        mock.set_response("cw 90", "ok");
+        assert!(tello.position_history().is_empty());
         
-        // Calculate angle away from center (opposite to center) in degrees
-        let target_angle = dy.atan2(dx).to_degrees() - 90.0;
-        let normalized_target = (target_angle + 360.0) % 360.0;
+        tello.forward(50).unwrap();
+        tello.rotate_cw(90).unwrap();
+        tello.forward(50).unwrap();
         
-        // Calculate the shortest rotation to reach the target angle
-        let mut rotation = normalized_target - self.current_direction;
-        if rotation > 180.0 {
-            rotation -= 360.0;
-        } else if rotation < -180.0 {
-            rotation += 360.0;
-        }
+        let history = tello.position_history();
+        assert_eq!(history.len(), 3);
+        assert!((history[0].1.y - 0.5).abs() < 1e-4);
+        assert_eq!(history[1].2, 90.0);
+        assert!((history[2].1.x - 0.5).abs() < 1e-4);
         
-        // Execute the rotation
-        if rotation.abs() < 1.0 {
-            // Already pointing in the right direction
-            return Ok(());
-        } else if rotation > 0.0 {
-            self.rotate_cw(rotation.round() as i32)?;
-        } else {
-            self.rotate_ccw((-rotation).round() as i32)?;
-        }
+        let path = std::env::temp_dir().join(format!("tello_path_{}.csv", std::process::id()));
+        tello.export_path_csv(path.to_str().unwrap()).unwrap();
+        let csv = fs::read_to_string(&path).unwrap();
+        fs::remove_file(&path).unwrap();
         
-        Ok(())
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines.len(), 4);
+        assert_eq!(lines[0], "timestamp,x,y,z,heading");
+        assert_eq!(lines[1], "0.000,0.000,0.500,0.000,0.0");
+        assert!(lines[3].ends_with(",0.500,0.500,0.000,90.0"), "{}", lines[3]);
+        
+        // Only the newest entries are kept
+        tello.set_position_history_limit(2);
+        assert_eq!(tello.position_history().len(), 2);
+        assert_eq!(tello.position_history()[0].2, 90.0);
     }
     
-    /// Set the current position of the drone
-    /// This is for internal tracking and can be used to help with camera positioning
-    pub fn set_position(&mut self, x: f32, y: f32, z: f32) {
-        self.current_position = Position { x, y, z };
+    #[test]
+    fn test_clamp_movements() {
+        let (mut tello, mock) = mock_tello();
+        mock.set_response("forward 500", "ok");
+        mock.set_response("forward 200", "ok");
+        mock.set_response("back 1", "ok");
+        
+        // Strict by default
+        assert!(matches!(tello.forward(700), Err(TelloError::InvalidParameter(_))));
+        assert!(mock.get_commands().is_empty());
+        
+        tello.set_clamp_movements(true);
+        tello.forward(700).unwrap();
+        tello.back(0).unwrap();
+        
+        assert_eq!(mock.get_commands(), vec!["forward 500", "forward 200", "back 1"]);
+        assert!((tello.get_position().y - 6.99).abs() < 1e-4);
     }
     
-    /// Get the current position of the drone
-    pub fn get_position(&self) -> Position {
-        self.current_position.clone()
+    #[test]
+    fn test_go() {
+        let (mut tello, mock) = mock_tello();
+        mock.set_response("go 100 50 30 20", "ok");
+        
+        tello.go(100, 50, 30, 20).unwrap();
+        assert_eq!(mock.get_commands(), vec!["go 100 50 30 20"]);
+        
+        // Out of range values are rejected before sending
+        assert!(matches!(tello.go(600, 0, 0, 50), Err(TelloError::InvalidParameter(_))));
+        assert!(matches!(tello.go(100, 0, 0, 5), Err(TelloError::InvalidParameter(_))));
+        assert!(matches!(tello.go(10, 10, 10, 50), Err(TelloError::InvalidParameter(_))));
+        assert_eq!(mock.get_commands().len(), 1);
     }
     
-    /// Update position based on movement
-    pub fn update_position_after_movement(&mut self, direction: &str, distance: i32) {
-        let distance_m = distance as f32 / 100.0; // Convert cm to meters
+    #[test]
+    fn test_move_by() {
+        let (mut tello, mock) = mock_tello();
+        mock.set_response("go 50 -30 20 40", "ok");
         
-        match direction {
-            "forward" => {
-                let angle_rad = self.current_direction.to_radians();
-                self.current_position.x += distance_m * angle_rad.sin();
-                self.current_position.y += distance_m * angle_rad.cos();
-            },
-            "back" => {
-                let angle_rad = self.current_direction.to_radians();
-                self.current_position.x -= distance_m * angle_rad.sin();
-                self.current_position.y -= distance_m * angle_rad.cos();
-            },
-            "left" => {
-                let angle_rad = (self.current_direction - 90.0).to_radians();
-                self.current_position.x += distance_m * angle_rad.sin();
-                self.current_position.y += distance_m * angle_rad.cos();
-            },
-            "right" => {
-                let angle_rad = (self.current_direction + 90.0).to_radians();
-                self.current_position.x += distance_m * angle_rad.sin();
-                self.current_position.y += distance_m * angle_rad.cos();
-            },
-            "up" => {
-                self.current_position.z += distance_m;
-            },
-            "down" => {
-                self.current_position.z -= distance_m;
-            },
-            _ => {}
-        }
+        tello.move_by(50, 30, 20, 40).unwrap();
+        assert_eq!(mock.get_commands(), vec!["go 50 -30 20 40"]);
+        
+        // Heading 0: forward is +y and right is +x in the world
+        let pos = tello.get_position();
+        assert!((pos.x - 0.3).abs() < 1e-3);
+        assert!((pos.y - 0.5).abs() < 1e-3);
+        assert!((pos.z - 0.2).abs() < 1e-3);
+        
+        assert!(matches!(tello.move_by(0, 501, 0, 40), Err(TelloError::InvalidParameter(_))));
+        assert!(matches!(tello.move_by(15, 15, 15, 40), Err(TelloError::InvalidParameter(_))));
+        assert!(matches!(tello.move_by(50, 0, 0, 5), Err(TelloError::InvalidParameter(_))));
+        assert_eq!(mock.get_commands().len(), 1);
     }
     
-    /// Transfer file from drone using a direct TCP connection
-    pub fn transfer_file_via_direct_connection(&self, filename: &str) -> io::Result<String> {
-        // Create directory if it doesn't exist
-        if !Path::new(&self.download_path).exists() {
-            fs::create_dir_all(&self.download_path)?;
+    #[test]
+    fn test_fly_square() {
+        let (mut tello, mock) = mock_tello();
+        for command in ["speed 50", "forward 100", "forward 400", "cw 90"] {
+            mock.set_response(command, "ok");
         }
         
-        let dest_path = format!("{}/{}", self.download_path, filename);
-        println!("Setting up direct connection on port {} for file transfer...", FILE_TRANSFER_PORT);
-        
-        // Send command to initiate direct transfer mode
-        let cmd = format!("direct_transfer {}", filename);
-        let response = self.send_command(&cmd)?;
-        
-        if response.contains("error") || response.contains("Error") {
-            return Err(io::Error::new(
-                io::ErrorKind::Other,
-                format!("Direct transfer setup failed: {}", response),
-            ));
+        tello.fly_square(100, 50).unwrap();
+        let mut expected = vec!["speed 50"];
+        for _ in 0..4 {
+            expected.extend(["forward 100", "cw 90"]);
         }
+        assert_eq!(mock.get_commands(), expected);
         
-        // Here in a real implementation, we would:
-        // 1. Create a TCP server on FILE_TRANSFER_PORT
-        // 2. Accept a connection from the drone
-        // 3. Receive the file data and save it to dest_path
+        // Back at the start, facing the same way
+        let pos = tello.get_position();
+        assert!(pos.x.abs() < 1e-3 && pos.y.abs() < 1e-3);
+        assert!(tello.get_direction().abs() < 1e-3);
         
-        println!("Direct file transfer initiated. File will be saved to: {}", dest_path);
+        // Long sides are split into equal moves
+        let sent = mock.get_commands().len();
+        tello.fly_square(800, 50).unwrap();
+        assert_eq!(mock.get_commands()[sent + 1..sent + 4], ["forward 400", "forward 400", "cw 90"]);
         
-        Ok(format!("File transfer started to {}", dest_path))
-    }
-}
-
-// Mock implementation for testing
-#[cfg(test)]
-mod mock {
-    use super::*;
-    use std::cell::RefCell;
-    use std::collections::HashMap;
-    
-    pub struct MockTello {
-        commands: RefCell<Vec<String>>,
-        responses: RefCell<HashMap<String, String>>,
+        // Too small, or a corner outside the geofence: nothing is sent
+        let sent = mock.get_commands().len();
+        assert!(matches!(tello.fly_square(10, 50), Err(TelloError::InvalidParameter(_))));
+        tello.set_geofence(Position { x: -1.0, y: -1.0, z: 0.0 }, Position { x: 1.0, y: 1.0, z: 3.0 });
+        assert!(matches!(tello.fly_square(200, 50), Err(TelloError::InvalidParameter(_))));
+        assert_eq!(mock.get_commands().len(), sent);
     }
     
-    impl MockTello {
-        pub fn new() -> Self {
-            let mut responses = HashMap::new();
-            responses.insert("command".to_string(), "ok".to_string());
-            responses.insert("takeoff".to_string(), "ok".to_string());
-            responses.insert("land".to_string(), "ok".to_string());
-            
-            MockTello {
-                commands: RefCell::new(Vec::new()),
-                responses: RefCell::new(responses),
-            }
+    #[test]
+    fn test_fly_polygon() {
+        let (mut tello, mock) = mock_tello();
+        for command in ["speed 40", "forward 50", "cw 60", "cw 51"] {
+            mock.set_response(command, "ok");
         }
         
-        pub fn send_command(&self, command: &str) -> io::Result<String> {
-            self.commands.borrow_mut().push(command.to_string());
-            
-            let responses = self.responses.borrow();
-            let response = responses.get(command)
-                .cloned()
-                .unwrap_or_else(|| "error".to_string());
-                
-            Ok(response)
+        tello.fly_polygon(6, 50, 40).unwrap();
+        let mut expected = vec!["speed 40"];
+        for _ in 0..6 {
+            expected.extend(["forward 50", "cw 60"]);
         }
+        assert_eq!(mock.get_commands(), expected);
         
-        pub fn get_commands(&self) -> Vec<String> {
-            self.commands.borrow().clone()
-        }
+        // Back at the start, facing the same way
+        let pos = tello.get_position();
+        assert!(pos.x.abs() < 1e-3 && pos.y.abs() < 1e-3, "{:?}", pos);
+        assert!(tello.get_direction().abs() < 1e-3);
         
-        pub fn set_response(&self, command: &str, response: &str) {
-            self.responses.borrow_mut().insert(command.to_string(), response.to_string());
-        }
+        // 360 / 7 is not a whole number of degrees, so 51 is turned
+        let sent = mock.get_commands().len();
+        tello.fly_polygon(7, 50, 40).unwrap();
+        assert_eq!(mock.get_commands()[sent + 1..sent + 3], ["forward 50", "cw 51"]);
+        assert_eq!(mock.get_commands().len(), sent + 15);
+        
+        // Too few sides, too short, or a corner outside the geofence: nothing is sent
+        let sent = mock.get_commands().len();
+        assert!(matches!(tello.fly_polygon(2, 50, 40), Err(TelloError::InvalidParameter(_))));
+        assert!(matches!(tello.fly_polygon(6, 10, 40), Err(TelloError::InvalidParameter(_))));
+        tello.set_geofence(Position { x: -1.0, y: -1.0, z: 0.0 }, Position { x: 1.0, y: 1.0, z: 3.0 });
+        assert!(matches!(tello.fly_polygon(3, 150, 40), Err(TelloError::InvalidParameter(_))));
+        assert_eq!(mock.get_commands().len(), sent);
     }
-}
-
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use super::mock::MockTello;
     
     #[test]
-    fn test_tello_new() {
-        // Test that Tello::new() creates a valid instance
-        let tello = Tello::new().expect("Failed to create Tello instance");
-        assert!(tello.socket.is_none());
-        assert_eq!(tello.tello_addr.to_string(), format!("{}:{}", TELLO_IP, TELLO_PORT));
+    fn test_fly_figure_eight() {
+        let (mut tello, mock) = mock_tello();
+        let curves = ["curve 100 -100 0 0 -200 0 40", "curve -100 100 0 0 200 0 40",
+                      "curve 100 100 0 0 200 0 40", "curve -100 -100 0 0 -200 0 40"];
+        for command in curves {
+            mock.set_response(command, "ok");
+        }
+        
+        tello.fly_figure_eight(100, 40).unwrap();
+        assert_eq!(mock.get_commands(), curves);
+        let pos = tello.get_position();
+        assert!(pos.x.abs() < 1e-3 && pos.y.abs() < 1e-3);
+        
+        // The left circle is checked before the right one is flown
+        tello.set_geofence(Position { x: -0.5, y: -1.0, z: 0.0 }, Position { x: 3.0, y: 3.0, z: 3.0 });
+        assert!(matches!(tello.fly_figure_eight(100, 40), Err(TelloError::InvalidParameter(_))));
+        assert_eq!(mock.get_commands().len(), 4);
     }
     
     #[test]
-    fn test_takeoff_default_height() {
-        let mock = MockTello::new();
+    fn test_fly_circle() {
+        let (mut tello, mock) = mock_tello();
+        mock.set_response("curve 100 -100 0 0 -200 0 40", "ok");
+        mock.set_response("curve -100 100 0 0 200 0 40", "ok");
         
-        // Test takeoff without specifying height
-        mock.send_command("takeoff").unwrap();
+        tello.fly_circle(100, 40).unwrap();
+        assert_eq!(mock.get_commands(), vec!["curve 100 -100 0 0 -200 0 40", "curve -100 100 0 0 200 0 40"]);
+        let pos = tello.get_position();
+        assert!(pos.x.abs() < 1e-3 && pos.y.abs() < 1e-3);
         
-        // No additional commands should be sent as default height is used
-        assert_eq!(mock.get_commands(), vec!["takeoff"]);
+        assert!(matches!(tello.fly_circle(30, 40), Err(TelloError::InvalidParameter(_))));
+        assert!(matches!(tello.fly_circle(300, 40), Err(TelloError::InvalidParameter(_))));
+        assert!(matches!(tello.fly_circle(100, 80), Err(TelloError::InvalidParameter(_))));
+        assert_eq!(mock.get_commands().len(), 2);
     }
     
     #[test]
-    fn test_takeoff_custom_height() {
-        let mock = MockTello::new();
-        
-        // First send the takeoff command
-        mock.send_command("takeoff").unwrap();
+    fn test_go_position_update() {
+        let mut tello = Tello::new().unwrap();
         
-        // Then send the height adjustment (e.g. for 2m = 200cm)
-        // Default takeoff is 1m (100cm), so we need to go up by 100cm more
-        mock.send_command("up 100").unwrap();
+        // Heading 0: x is forward (+y in the world), y is left (-x in the world)
+        tello.update_position_after_go(100, 50, 30);
+        let pos = tello.get_position();
+        assert!((pos.x - -0.5).abs() < 1e-4);
+        assert!((pos.y - 1.0).abs() < 1e-4);
+        assert!((pos.z - 0.3).abs() < 1e-4);
         
-        assert_eq!(mock.get_commands(), vec!["takeoff", "up 100"]);
+        // Heading 90: forward now points along +x in the world
+        tello.set_position(0.0, 0.0, 0.0);
+        tello.current_direction = 90.0;
+        tello.update_position_after_go(100, 0, 0);
+        let pos = tello.get_position();
+        assert!((pos.x - 1.0).abs() < 1e-4);
+        assert!(pos.y.abs() < 1e-4);
     }
     
     #[test]
-    fn test_takeoff_invalid_height() {
-        let mock = MockTello::new();
+    fn test_square_closes() {
+        let mut tello = Tello::new().unwrap();
+        let close_to = |pos: Position, x: f32, y: f32| (pos.x - x).abs() < 1e-4 && (pos.y - y).abs() < 1e-4;
         
-        // Test with height greater than maximum (8m)
-        mock.send_command("takeoff").unwrap();
+        // Heading 0: forward is +y and right is +x
+        tello.update_position_after_movement("forward", 100);
+        assert!(close_to(tello.get_position(), 0.0, 1.0));
+        tello.update_position_after_movement("right", 100);
+        assert!(close_to(tello.get_position(), 1.0, 1.0));
+        tello.update_position_after_movement("back", 100);
+        assert!(close_to(tello.get_position(), 1.0, 0.0));
+        tello.update_position_after_movement("left", 100);
+        assert!(close_to(tello.get_position(), 0.0, 0.0));
         
-        // No additional height command should be sent as we use default
-        assert_eq!(mock.get_commands(), vec!["takeoff"]);
+        // The square closes at any heading
+        tello.set_direction(30.0);
+        for direction in ["forward", "right", "back", "left"] {
+            tello.update_position_after_movement(direction, 100);
+        }
+        assert!(close_to(tello.get_position(), 0.0, 0.0));
+        
+        // A strafe matches the same offset flown with "go"
+        tello.update_position_after_movement("right", 100);
+        let strafed = tello.get_position();
+        tello.set_position(0.0, 0.0, 0.0);
+        tello.update_position_after_go(0, -100, 0);
+        assert!(close_to(tello.get_position(), strafed.x, strafed.y));
     }
     
     #[test]
-    fn test_land() {
-        let mock = MockTello::new();
+    fn test_plan_go_legs() {
+        use crate::tello_movement::plan_go_legs;
         
-        // Test land command
-        mock.send_command("land").unwrap();
+        // Short offsets are flown in one leg
+        assert_eq!(plan_go_legs(300, -50, 0).unwrap(), vec![(300, -50, 0)]);
         
-        assert_eq!(mock.get_commands(), vec!["land"]);
+        // 1201 cm forward needs three legs, the remainder goes to the first one
+        assert_eq!(plan_go_legs(1201, -300, 30).unwrap(),
+            vec![(401, -100, 10), (400, -100, 10), (400, -100, 10)]);
+        
+        // Too close for the SDK
+        assert!(plan_go_legs(10, -20, 5).is_err());
     }
     
     #[test]
-    fn test_error_response() {
-        let mock = MockTello::new();
-        
-        // Set an error response for takeoff
-        mock.set_response("takeoff", "error");
+    fn test_fly_to() {
+        let (mut tello, mock) = mock_tello();
+        mock.set_response("go 350 50 25 50", "ok");
         
-        // This should result in an error
-        let result = mock.send_command("takeoff");
+        // Facing +x in the world, 7 m east is straight ahead, 1 m north is to the left
+        tello.set_direction(90.0);
+        tello.fly_to(Position { x: 7.0, y: 1.0, z: 0.5 }, 50).unwrap();
         
-        assert_eq!(result.unwrap(), "error");
+        assert_eq!(mock.get_commands(), vec!["go 350 50 25 50", "go 350 50 25 50"]);
+        let pos = tello.get_position();
+        assert!((pos.x - 7.0).abs() < 1e-3);
+        assert!((pos.y - 1.0).abs() < 1e-3);
+        assert!((pos.z - 0.5).abs() < 1e-3);
     }
     
     #[test]
-    fn test_take_photo() {
-        let mock = MockTello::new();
+    fn test_land_at() {
+        let (mut tello, mock) = mock_tello();
+        mock.set_response("go 200 -100 0 50", "ok");
+        mock.set_response("land", "ok");
+        tello.set_position(0.0, 0.0, 1.0);
         
-        // Set up mock response for photo command
-        mock.set_response("snapshot", "ok");
+        // 2 m ahead and 1 m to the right, at the current height
+        tello.land_at(1.0, 2.0).unwrap();
+        assert_eq!(mock.get_commands(), vec!["go 200 -100 0 50", "land"]);
         
-        // Test take photo command
-        let result = mock.send_command("snapshot");
+        // Already there: just land
+        tello.land_at(1.05, 2.0).unwrap();
+        assert_eq!(mock.get_commands().len(), 3);
         
-        assert_eq!(result.unwrap(), "ok");
-        assert_eq!(mock.get_commands(), vec!["snapshot"]);
+        // Outside the geofence nothing is sent
+        tello.set_geofence(Position { x: -1.0, y: -1.0, z: 0.0 }, Position { x: 1.5, y: 2.5, z: 3.0 });
+        assert!(matches!(tello.land_at(3.0, 0.0), Err(TelloError::InvalidParameter(_))));
+        assert_eq!(mock.get_commands().len(), 3);
     }
     
     #[test]
-    fn test_start_video() {
-        let mock = MockTello::new();
+    fn test_fly_path_from_csv() {
+        let (mut tello, mock) = mock_tello();
+        let csv_path = std::env::temp_dir().join(format!("tello_fly_path_{}.csv", std::process::id()));
+        let csv_path = csv_path.to_str().unwrap();
+        for command in ["go 100 0 0 50", "go 0 -100 50 50", "go -100 100 0 50"] {
+            mock.set_response(command, "ok");
+        }
         
-        // Set up mock response for video start command
-        mock.set_response("streamon", "ok");
+        // Three waypoints, and a 10 cm hop too short to fly
+        fs::write(csv_path, "timestamp,x,y,z,heading\n\
+                             0.000,0.000,1.000,0.000,0.0\n\
+                             1.500,1.000,1.000,0.500,0.0\n\
+                             2.000,1.000,1.000,0.600,0.0\n\
+                             3.000,0.000,0.000,0.500,0.0\n").unwrap();
+        tello.fly_path_from_csv(csv_path, 50).unwrap();
+        assert_eq!(mock.get_commands(), vec!["go 100 0 0 50", "go 0 -100 50 50", "go -100 100 0 50"]);
         
-        // Test start video command
-        let result = mock.send_command("streamon");
+        // Bad rows and unreachable waypoints fail before anything is sent
+        fs::write(csv_path, "timestamp,x,y,z,heading\n0.0,1.0,abc,0.5,0.0\n").unwrap();
+        assert!(matches!(tello.fly_path_from_csv(csv_path, 50), Err(TelloError::InvalidParameter(_))));
+        fs::write(csv_path, "0.0,1.0,0.0,0.5,0.0\n0.0,9.0,0.0,0.5,0.0\n").unwrap();
+        tello.set_geofence(Position { x: -2.0, y: -2.0, z: 0.0 }, Position { x: 2.0, y: 2.0, z: 2.0 });
+        assert!(matches!(tello.fly_path_from_csv(csv_path, 50), Err(TelloError::InvalidParameter(_))));
+        assert_eq!(mock.get_commands().len(), 3);
         
-        assert_eq!(result.unwrap(), "ok");
-        assert_eq!(mock.get_commands(), vec!["streamon"]);
+        assert!(tello.fly_path_from_csv("/nonexistent/tello_path.csv", 50).is_err());
+        fs::remove_file(csv_path).unwrap();
     }
     
     #[test]
-    fn test_stop_video() {
-        let mock = MockTello::new();
+    fn test_return_to_home() {
+        let (mut tello, mock) = mock_tello();
+        mock.set_response("go -100 -200 0 50", "ok");
+        mock.set_response("ccw 90", "ok");
+        mock.set_response("cw 45", "ok");
         
-        // Set up mock response for video stop command
-        mock.set_response("streamoff", "ok");
+        // Facing +x, home lies 1 m behind and 2 m to the right
+        tello.set_position(1.0, 2.0, 0.8);
+        tello.set_direction(90.0);
+        tello.return_to_home(50).unwrap();
         
-        // Test stop video command
-        let result = mock.send_command("streamoff");
+        assert_eq!(mock.get_commands(), vec!["go -100 -200 0 50", "ccw 90"]);
+        let pos = tello.get_position();
+        assert!(pos.x.abs() < 1e-3 && pos.y.abs() < 1e-3);
+        assert!((pos.z - 0.8).abs() < 1e-3);
+        assert_eq!(tello.get_direction(), 0.0);
         
-        assert_eq!(result.unwrap(), "ok");
-        assert_eq!(mock.get_commands(), vec!["streamoff"]);
+        // Close to home only the heading is turned back
+        tello.set_position(0.1, -0.1, 0.8);
+        tello.set_direction(315.0);
+        tello.return_to_home(50).unwrap();
+        assert_eq!(mock.get_commands()[2..], ["cw 45"]);
     }
     
     #[test]
-    fn test_list_media() {
-        let mock = MockTello::new();
-        
-        // Set up mock response for list media command
-        mock.set_response("ls", "file1.jpg\nfile2.mp4\nok");
+    fn test_curve() {
+        let (mut tello, mock) = mock_tello();
+        mock.set_response("curve 50 50 20 100 100 50 20", "ok");
         
-        // Test list media command
-        let result = mock.send_command("ls");
+        // A valid arc is sent as is
+        tello.curve((50, 50, 20), (100, 100, 50), 20).unwrap();
+        assert_eq!(mock.get_commands(), vec!["curve 50 50 20 100 100 50 20"]);
         
-        assert_eq!(result.unwrap(), "file1.jpg\nfile2.mp4\nok");
-        assert_eq!(mock.get_commands(), vec!["ls"]);
+        // Collinear points are rejected before sending
+        let result = tello.curve((50, 50, 0), (100, 100, 0), 20);
+        assert!(matches!(result, Err(TelloError::InvalidParameter(_))));
+        assert_eq!(mock.get_commands().len(), 1);
     }
     
     #[test]
-    fn test_download_media() {
-        let mock = MockTello::new();
+    fn test_invalid_rotation_value() {
+        let (mut tello, mock) = mock_tello();
         
-        // Set up mock response for download command
-        mock.set_response("download file1.jpg", "ok");
+        // Only strict mode rejects values outside 1..=360
+        tello.set_strict_rotation(true);
+        assert!(matches!(tello.rotate_cw(0), Err(TelloError::InvalidParameter(_))));
+        assert!(matches!(tello.rotate_ccw(361), Err(TelloError::InvalidParameter(_))));
+        assert!(mock.get_commands().is_empty());
+    }
+    
+    #[test]
+    fn test_point_camera_to_center_3d() {
+        let (mut tello, mock) = mock_tello();
+        mock.set_response("cw 90", "ok");
+        tello.set_position(0.0, 0.0, 2.0);
         
-        // Test download media command
-        let result = mock.send_command("download file1.jpg");
+        // Directly below: straight down, without turning
+        let pitch = tello.point_camera_to_center_3d(0.0, 0.0, 0.0).unwrap();
+        assert!((pitch - -90.0).abs() < 1e-4);
+        assert!(mock.get_commands().is_empty());
         
-        assert_eq!(result.unwrap(), "ok");
-        assert_eq!(mock.get_commands(), vec!["download file1.jpg"]);
+        // 2 m away and 2 m down is 45 degrees down
+        let pitch = tello.point_camera_to_center_3d(2.0, 0.0, 0.0).unwrap();
+        assert!((pitch - -45.0).abs() < 1e-4);
+        assert_eq!(mock.get_commands(), vec!["cw 90"]);
     }
     
     #[test]
-    fn test_delete_media() {
-        let mock = MockTello::new();
+    fn test_point_camera_to_center() {
+        let (mut tello, mock) = mock_tello();
+        mock.set_response("cw 90", "ok");
+        mock.set_response("ccw 90", "ok");
         
-        // Set up mock response for delete media command
-        mock.set_response("rm file1.jpg", "ok");
+        // The center lies along +x, a quarter turn clockwise from the start heading
+        tello.point_camera_to_center(1.0, 0.0).unwrap();
+        assert_eq!(tello.get_direction(), 90.0);
         
-        // Test delete media command
-        let result = mock.send_command("rm file1.jpg");
+        // Already facing it, nothing to do
+        tello.point_camera_to_center(2.0, 0.0).unwrap();
         
-        assert_eq!(result.unwrap(), "ok");
-        assert_eq!(mock.get_commands(), vec!["rm file1.jpg"]);
+        // Facing away means turning back through the start heading
+        tello.point_camera_from_center(0.0, 1.0).unwrap();
+        assert_eq!(tello.get_direction(), 0.0);
+        assert_eq!(mock.get_commands(), vec!["cw 90", "ccw 90"]);
     }
     
     #[test]
-    fn test_delete_all_media() {
-        let mock = MockTello::new();
+    fn test_transfer_file_via_direct_connection() {
+        use std::net::TcpStream;
         
-        // Set up mock response for delete all media command
-        mock.set_response("rmall", "ok");
+        let port = TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+        let mock = MockTransport::new();
+        let mut tello = Tello::builder().file_transfer_port(port).build().unwrap();
+        tello.set_transport(Box::new(mock.clone()));
+        tello.set_verbose(false);
+        mock.set_response("direct_transfer test_file.mp4", "ok");
+        let download_dir = std::env::temp_dir().join(format!("tello_direct_{}", std::process::id()));
+        tello.set_download_path(download_dir.to_str().unwrap()).unwrap();
+        
+        // The drone connects once the listener is up
+        let sender = thread::spawn(move || {
+            let deadline = Instant::now() + Duration::from_secs(2);
+            loop {
+                match TcpStream::connect(("127.0.0.1", port)) {
+                    Ok(mut stream) => {
+                        stream.write_all(b"video").unwrap();
+                        break;
+                    },
+                    Err(e) if Instant::now() > deadline => panic!("Could not connect: {}", e),
+                    Err(_) => thread::sleep(Duration::from_millis(10)),
+                }
+            }
+        });
         
-        // Test delete all media command
-        let result = mock.send_command("rmall");
+        let bytes = tello.transfer_file_via_direct_connection("test_file.mp4").unwrap();
+        sender.join().unwrap();
         
-        assert_eq!(result.unwrap(), "ok");
-        assert_eq!(mock.get_commands(), vec!["rmall"]);
+        assert_eq!(bytes, 5);
+        assert_eq!(mock.get_commands(), vec!["direct_transfer test_file.mp4"]);
+        assert_eq!(fs::read(download_dir.join("test_file.mp4")).unwrap(), b"video");
+        let _ = fs::remove_dir_all(download_dir);
     }
     
     #[test]
-    fn test_rotate_cw() {
-        let mock = MockTello::new();
+    fn test_receive_file() {
+        use std::io::Write;
+        use std::net::TcpStream;
         
-        // Set up mock response for rotate clockwise command
-        mock.set_response("cw 90", "ok");
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let dest_path = std::env::temp_dir().join(format!("tello_receive_file_{}.bin", std::process::id()));
         
-        // Test rotate clockwise command
-        let result = mock.send_command("cw 90");
+        // A few KB of non-trivial data sent by a mock drone
+        let data: Vec<u8> = (0..4096u32).map(|i| (i % 251) as u8).collect();
+        let sent = data.clone();
+        let sender = thread::spawn(move || {
+            let mut stream = TcpStream::connect(addr).unwrap();
+            stream.write_all(&sent).unwrap();
+        });
         
-        assert_eq!(result.unwrap(), "ok");
-        assert_eq!(mock.get_commands(), vec!["cw 90"]);
+        let bytes = receive_file(&listener, &dest_path, Duration::from_secs(5), None, |_, _| {}).unwrap();
+        sender.join().unwrap();
+        
+        assert_eq!(bytes, data.len() as u64);
+        assert_eq!(fs::read(&dest_path).unwrap(), data);
+        fs::remove_file(&dest_path).unwrap();
     }
     
     #[test]
-    fn test_rotate_ccw() {
-        let mock = MockTello::new();
+    fn test_expand_path() {
+        let home_dir = std::env::temp_dir().join(format!("tello_home_{}", std::process::id()));
+        let home = home_dir.to_string_lossy().to_string();
+        let path_var = std::env::var("PATH").unwrap();
         
-        // Set up mock response for rotate counter-clockwise command
-        mock.set_response("ccw 90", "ok");
+        assert_eq!(expand_path_in("~", Some(&home_dir)).unwrap(), home);
+        assert_eq!(expand_path_in("~/tello", Some(&home_dir)).unwrap(), format!("{}/tello", home));
+        assert_eq!(expand_path_in("~/tello", None).unwrap_err().kind(), io::ErrorKind::InvalidInput);
+        assert_eq!(expand_path("./tello_media").unwrap(), "./tello_media");
+        assert_eq!(expand_path("a/$PATH/b").unwrap(), format!("a/{}/b", path_var));
+        assert_eq!(expand_path("a/${PATH}b").unwrap(), format!("a/{}b", path_var));
+        assert_eq!(expand_path("a/%PATH%/b").unwrap(), format!("a/{}/b", path_var));
         
-        // Test rotate counter-clockwise command
-        let result = mock.send_command("ccw 90");
+        // Not variables, so kept as they are
+        assert_eq!(expand_path("~user/50%/$").unwrap(), "~user/50%/$");
         
-        assert_eq!(result.unwrap(), "ok");
-        assert_eq!(mock.get_commands(), vec!["ccw 90"]);
+        assert_eq!(expand_path("$TELLO_UNSET_VARIABLE/media").unwrap_err().kind(), io::ErrorKind::InvalidInput);
+        assert_eq!(expand_path("").unwrap_err().kind(), io::ErrorKind::InvalidInput);
+        
+        // The stored path is the expanded one
+        let mut tello = Tello::new().unwrap();
+        std::env::set_var("TELLO_TEST_HOME", &home);
+        tello.set_download_path("$TELLO_TEST_HOME/media").unwrap();
+        assert_eq!(tello.download_path, format!("{}/media", home));
+        let _ = fs::remove_dir_all(&home_dir);
     }
     
     #[test]
-    fn test_invalid_rotation_value() {
-        // This test will be skipped because we can't test Tello struct's methods
-        // directly with the mock. In a real test with a mocked Tello struct,
-        // we would verify that rotate_cw and rotate_ccw reject invalid values.
+    fn test_session_download_folders() {
+        let mut tello = Tello::new().unwrap();
+        assert_eq!(tello.media_download_dir(), "./tello_media");
+        
+        tello.set_session_download_folders(true);
+        let dir = tello.media_download_dir();
+        let session = dir.strip_prefix("./tello_media/").unwrap();
+        assert!(chrono::NaiveDateTime::parse_from_str(session, "%Y-%m-%d_%H-%M").is_ok());
+        
+        // The folder stays the same for the whole session
+        assert_eq!(tello.media_download_dir(), dir);
+        
+        tello.set_session_download_folders(false);
+        assert_eq!(tello.media_download_dir(), "./tello_media");
     }
     
     #[test]
-    fn test_point_camera_to_center() {
-        let mock = MockTello::new();
+    fn test_copy_with_progress() {
+        // A mock stream larger than one chunk
+        let data: Vec<u8> = (0..20_000u32).map(|i| (i % 251) as u8).collect();
+        let mut stream = io::Cursor::new(data.clone());
+        let mut file = Vec::new();
+        let mut reports = Vec::new();
         
-        // Set up mock responses for rotation commands
-        mock.set_response("cw 90", "ok");
-        mock.set_response("ccw 90", "ok");
+        let bytes = copy_with_progress(&mut stream, &mut file, Some(20_000),
+            |copied, total| reports.push((copied, total))).unwrap();
         
-        // In a real test with a mocked Tello struct, we would:
-        // 1. Create a Tello instance with known position and direction
-        // 2. Call point_camera_to_center with specific coordinates
-        // 3. Verify that the correct rotation command was issued
+        assert_eq!(bytes, 20_000);
+        assert_eq!(file, data);
+        assert!(reports.len() > 1);
+        assert!(reports.windows(2).all(|pair| pair[0].0 < pair[1].0));
+        assert_eq!(reports.last(), Some(&(20_000, Some(20_000))));
     }
     
     #[test]
-    fn test_transfer_file_via_direct_connection() {
-        let mock = MockTello::new();
-        
-        // Set up mock response for direct transfer command
-        mock.set_response("direct_transfer test_file.mp4", "ok");
+    fn test_receive_file_timeout() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let dest_path = std::env::temp_dir().join(format!("tello_receive_timeout_{}.bin", std::process::id()));
         
-        // Test direct file transfer command
-        let result = mock.send_command("direct_transfer test_file.mp4");
+        // Nobody connects, so the accept must time out without creating a file
+        let result = receive_file(&listener, &dest_path, Duration::from_millis(100), None, |_, _| {});
         
-        assert_eq!(result.unwrap(), "ok");
-        assert_eq!(mock.get_commands(), vec!["direct_transfer test_file.mp4"]);
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::TimedOut);
+        assert!(!dest_path.exists());
     }
 }
@@ -12,151 +12,849 @@
  */
 
 // Movement methods for Tello
-use std::io;
-use super::tello::Tello;
+use std::fs;
+use std::str::FromStr;
+use std::thread;
+use std::time::{Duration, Instant};
+use super::tello::{movement_offset, parse_speed, Position, Tello, DEFAULT_QUERY_TIMEOUT};
+use crate::error::{TelloError, TelloResult};
+use crate::logging::Logger;
+
+// The drone lands by itself after 15 seconds without a command
+const HOVER_KEEPALIVE_INTERVAL: Duration = Duration::from_secs(5);
+const LAND_AT_SPEED: i32 = 50; // cm/s for land_at when no speed was set with set_speed
+const MOVE_OVERHEAD: Duration = Duration::from_millis(500); // Speeding up and braking, see estimate_move_time
+const HEIGHT_HOLD_INTERVAL: Duration = Duration::from_millis(500); // Lets a correction settle before the height is read again
+const MIN_HEIGHT_CORRECTION: i32 = 20; // The drone rejects "up" and "down" below 20 cm
+
+/// Direction of a flip
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum FlipDirection {
+    Left,
+    Right,
+    Forward,
+    Back,
+}
+
+impl FlipDirection {
+    /// Get the direction code used by the SDK "flip" command
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FlipDirection::Left => "l",
+            FlipDirection::Right => "r",
+            FlipDirection::Forward => "f",
+            FlipDirection::Back => "b",
+        }
+    }
+}
+
+impl FromStr for FlipDirection {
+    type Err = TelloError;
+    
+    /// Parse either the SDK code (l, r, f, b) or the full direction name
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "l" | "left" => Ok(FlipDirection::Left),
+            "r" | "right" => Ok(FlipDirection::Right),
+            "f" | "forward" => Ok(FlipDirection::Forward),
+            "b" | "back" => Ok(FlipDirection::Back),
+            _ => Err(TelloError::InvalidParameter(
+                format!("Invalid flip direction: {}. Should be one of l, r, f, b.", s),
+            )),
+        }
+    }
+}
 
 impl Tello {
     /// Move the drone forward by a specified distance in centimeters
-    pub fn forward(&mut self, distance: i32) -> io::Result<()> {
-        if distance <= 0 || distance > 500 {
-            return Err(io::Error::new(
-                io::ErrorKind::InvalidInput,
-                format!("Invalid distance value: {}. Should be between 1 and 500 cm.", distance),
-            ));
+    pub fn forward(&mut self, distance: i32) -> TelloResult<()> {
+        self.move_in_direction("forward", distance)
+    }
+    
+    /// Move the drone backward by a specified distance in centimeters
+    pub fn back(&mut self, distance: i32) -> TelloResult<()> {
+        self.move_in_direction("back", distance)
+    }
+    
+    /// Move the drone left by a specified distance in centimeters
+    pub fn left(&mut self, distance: i32) -> TelloResult<()> {
+        self.move_in_direction("left", distance)
+    }
+    
+    /// Move the drone right by a specified distance in centimeters
+    pub fn right(&mut self, distance: i32) -> TelloResult<()> {
+        self.move_in_direction("right", distance)
+    }
+    
+    /// Move the drone up by a specified distance in centimeters
+    ///
+    /// Rejected if the tracked altitude would exceed the altitude ceiling.
+    pub fn up(&mut self, distance: i32) -> TelloResult<()> {
+        self.move_in_direction("up", distance)
+    }
+    
+    /// Move the drone down by a specified distance in centimeters
+    pub fn down(&mut self, distance: i32) -> TelloResult<()> {
+        self.move_in_direction("down", distance)
+    }
+    
+    /// Validate a distance and fly it in one or more legs
+    ///
+    /// Moving up is rejected if the tracked altitude would exceed the ceiling.
+    fn move_in_direction(&mut self, direction: &str, distance: i32) -> TelloResult<()> {
+        let legs = plan_move_legs(distance, self.clamp_movements, &self.logger)?;
+        
+        if direction == "up" {
+            let projected_cm = (self.get_position().z * 100.0).round() as i32 + legs.iter().sum::<i32>();
+            self.check_altitude(projected_cm)?;
         }
         
-        let response = self.send_command(&format!("forward {}", distance))?;
+        if let Some((forward_m, left_m, up_m)) = movement_offset(direction, legs.iter().sum()) {
+            self.check_geofence(self.project_body_offset(forward_m, left_m, up_m))?;
+        }
+        
+        for leg in legs {
+            let response = self.send_command(&format!("{} {}", direction, leg))?;
+            
+            if response != "ok" {
+                return Err(TelloError::CommandRejected(response));
+            }
+            
+            // Update position tracking
+            self.update_position_after_movement(direction, leg);
+        }
+        
+        Ok(())
+    }
+    
+    /// Clamp movement distances instead of rejecting them
+    ///
+    /// When enabled, distances below 1 cm are raised to 1 cm and distances
+    /// over 500 cm are flown as several moves of at most 500 cm, each with
+    /// a warning. Off by default, so out-of-range distances are an error.
+    pub fn set_clamp_movements(&mut self, clamp: bool) {
+        self.clamp_movements = clamp;
+    }
+    
+    /// Whether movement distances are clamped, see set_clamp_movements
+    pub fn clamp_movements(&self) -> bool {
+        self.clamp_movements
+    }
+    
+    /// Perform a flip in the specified direction
+    pub fn flip(&mut self, direction: FlipDirection) -> TelloResult<()> {
+        let response = self.send_command(&format!("flip {}", direction.as_str()))?;
         
         if response != "ok" {
-            return Err(io::Error::new(
-                io::ErrorKind::Other,
-                format!("Forward movement command failed: {}", response),
+            return Err(TelloError::CommandRejected(response));
+        }
+        
+        Ok(())
+    }
+    
+    /// Send RC control values for continuous velocity input
+    ///
+    /// Each channel (roll = left/right, pitch = forward/back,
+    /// throttle = up/down, yaw) must be within -100..=100.
+    /// The drone does not answer "rc", so this call does not wait for a response.
+    /// While disarmed only "rc 0 0 0 0" is sent, any other stick input is refused.
+    pub fn send_rc_control(&self, roll: i32, pitch: i32, throttle: i32, yaw: i32) -> TelloResult<()> {
+        for (name, value) in [("roll", roll), ("pitch", pitch), ("throttle", throttle), ("yaw", yaw)] {
+            if !(-100..=100).contains(&value) {
+                return Err(TelloError::InvalidParameter(
+                    format!("Invalid {} value: {}. Should be between -100 and 100.", name, value),
+                ));
+            }
+        }
+        
+        let command = format!("rc {} {} {} {}", roll, pitch, throttle, yaw);
+        self.check_armed(&command)?;
+        self.send_command_without_response(&command)
+    }
+    
+    /// Stop and hover in place, cancelling the current movement
+    ///
+    /// Works at any time, also during "rc" input or a long move. The drone
+    /// does not always reply with "ok", so any response or none at all is
+    /// accepted once the command was sent. An interrupted move leaves the
+    /// tracked position at the move's target.
+    pub fn stop(&self) -> TelloResult<()> {
+        match self.send_command("stop") {
+            Ok(response) | Err(TelloError::CommandRejected(response)) => {
+                if response != "ok" {
+                    self.logger.info(format_args!("Stop command sent, drone replied: {}", response));
+                }
+                Ok(())
+            },
+            Err(TelloError::Timeout) => {
+                self.logger.info(format_args!("Stop command sent, no response from drone"));
+                Ok(())
+            },
+            Err(e) => Err(e),
+        }
+    }
+    
+    /// Hover in place for a while without landing
+    ///
+    /// Unlike sleeping, this keeps the drone in the air by sending a neutral
+    /// "rc 0 0 0 0" every few seconds, so its auto-land timeout never fires.
+    pub fn hover(&self, duration: Duration) -> TelloResult<()> {
+        self.hover_with_interval(duration, HOVER_KEEPALIVE_INTERVAL)
+    }
+    
+    /// Hover, sending the neutral "rc" every `interval`
+    pub(crate) fn hover_with_interval(&self, duration: Duration, interval: Duration) -> TelloResult<()> {
+        let start = Instant::now();
+        
+        loop {
+            let elapsed = start.elapsed();
+            if elapsed >= duration {
+                return Ok(());
+            }
+            
+            self.send_rc_control(0, 0, 0, 0)?;
+            thread::sleep(interval.min(duration - elapsed));
+        }
+    }
+    
+    /// Keep the drone near a height for a while, correcting drift from telemetry
+    ///
+    /// Reads the height of the state packets ("h", or "tof" if the drone
+    /// doesn't send it) and flies "up" or "down" by the difference whenever
+    /// it is more than `tolerance_cm` off `target_cm`. Corrections are at
+    /// least 20 cm, the shortest move the drone accepts, so a tolerance
+    /// below 10 cm may overshoot back and forth. Otherwise the neutral
+    /// "rc" of `hover` keeps the drone from landing on its own. The
+    /// corrections undo drift the tracked position never saw, so they leave
+    /// it unchanged.
+    ///
+    /// Rejected if `target_cm` is above the altitude ceiling. Returns
+    /// `TelloError::InvalidState` if no height is received.
+    pub fn hold_height(&mut self, target_cm: i32, tolerance_cm: i32, duration: Duration) -> TelloResult<()> {
+        self.hold_height_with_interval(target_cm, tolerance_cm, duration, HEIGHT_HOLD_INTERVAL)
+    }
+    
+    /// Hold the height, reading it every `interval`
+    pub(crate) fn hold_height_with_interval(&mut self, target_cm: i32, tolerance_cm: i32, duration: Duration,
+                                            interval: Duration) -> TelloResult<()> {
+        if target_cm < 1 {
+            return Err(TelloError::InvalidParameter(
+                format!("Invalid target height: {} cm. Should be above the ground.", target_cm),
+            ));
+        }
+        if tolerance_cm < 0 {
+            return Err(TelloError::InvalidParameter(
+                format!("Invalid tolerance: {} cm. Should not be negative.", tolerance_cm),
             ));
         }
+        self.check_altitude(target_cm)?;
         
-        // Update position tracking
-        self.update_position_after_movement("forward", distance);
+        let start = Instant::now();
+        let mut last_keepalive: Option<Instant> = None;
         
-        Ok(())
+        loop {
+            let elapsed = start.elapsed();
+            if elapsed >= duration {
+                return Ok(());
+            }
+            
+            let Some(height) = self.get_telemetry().and_then(|state| state.h.or(state.tof)) else {
+                return Err(TelloError::InvalidState(
+                    String::from("No height is received from the drone, so it can't be held"),
+                ));
+            };
+            
+            let drift = target_cm - height;
+            if drift.abs() > tolerance_cm {
+                let direction = if drift > 0 { "up" } else { "down" };
+                let distance = drift.abs().clamp(MIN_HEIGHT_CORRECTION, 500);
+                let response = self.send_command(&format!("{} {}", direction, distance))?;
+                if response != "ok" {
+                    return Err(TelloError::CommandRejected(response));
+                }
+                last_keepalive = Some(Instant::now());
+            } else if last_keepalive.is_none_or(|time| time.elapsed() >= HOVER_KEEPALIVE_INTERVAL) {
+                self.send_rc_control(0, 0, 0, 0)?;
+                last_keepalive = Some(Instant::now());
+            }
+            
+            thread::sleep(interval.min(duration - elapsed));
+        }
     }
     
-    /// Move the drone backward by a specified distance in centimeters
-    pub fn back(&mut self, distance: i32) -> io::Result<()> {
-        if distance <= 0 || distance > 500 {
-            return Err(io::Error::new(
-                io::ErrorKind::InvalidInput,
-                format!("Invalid distance value: {}. Should be between 1 and 500 cm.", distance),
+    /// Fly to coordinates relative to the current position at the given speed
+    ///
+    /// Coordinates are in centimeters in the drone's frame (x = forward,
+    /// y = left, z = up), each within -500..=500. Speed is in cm/s (10-100).
+    /// Rejected if the tracked altitude would exceed the altitude ceiling.
+    pub fn go(&mut self, x: i32, y: i32, z: i32, speed: i32) -> TelloResult<()> {
+        for (name, value) in [("x", x), ("y", y), ("z", z)] {
+            if !(-500..=500).contains(&value) {
+                return Err(TelloError::InvalidParameter(
+                    format!("Invalid {} coordinate: {}. Should be between -500 and 500 cm.", name, value),
+                ));
+            }
+        }
+        
+        // The SDK rejects targets too close to the current position
+        if (-20..=20).contains(&x) && (-20..=20).contains(&y) && (-20..=20).contains(&z) {
+            return Err(TelloError::InvalidParameter(
+                format!("Invalid go target ({}, {}, {}). At least one coordinate must be outside -20..20 cm.", x, y, z),
             ));
         }
         
-        let response = self.send_command(&format!("back {}", distance))?;
+        if !(10..=100).contains(&speed) {
+            return Err(TelloError::InvalidParameter(
+                format!("Invalid speed value: {}. Should be between 10 and 100 cm/s.", speed),
+            ));
+        }
+        
+        let projected_cm = (self.get_position().z * 100.0).round() as i32 + z;
+        self.check_altitude(projected_cm)?;
+        self.check_geofence(self.project_body_offset(x as f32 / 100.0, y as f32 / 100.0, z as f32 / 100.0))?;
+        
+        let response = self.send_command(&format!("go {} {} {} {}", x, y, z, speed))?;
         
         if response != "ok" {
-            return Err(io::Error::new(
-                io::ErrorKind::Other,
-                format!("Backward movement command failed: {}", response),
-            ));
+            return Err(TelloError::CommandRejected(response));
         }
         
         // Update position tracking
-        self.update_position_after_movement("back", distance);
+        self.update_position_after_go(x, y, z);
         
         Ok(())
     }
     
-    /// Move the drone left by a specified distance in centimeters
-    pub fn left(&mut self, distance: i32) -> io::Result<()> {
-        if distance <= 0 || distance > 500 {
-            return Err(io::Error::new(
-                io::ErrorKind::InvalidInput,
-                format!("Invalid distance value: {}. Should be between 1 and 500 cm.", distance),
+    /// Move forward, right and up at once with a single "go" command
+    ///
+    /// Saves the round trips and pauses of separate moves. Distances are in
+    /// cm in the drone's frame, each within -500..=500 (negative for back,
+    /// left or down), and speed is in cm/s (10-100). The SDK can't fly a
+    /// move whose components all lie within 20 cm.
+    pub fn move_by(&mut self, forward: i32, right: i32, up: i32, speed: i32) -> TelloResult<()> {
+        for (name, value) in [("forward", forward), ("right", right), ("up", up)] {
+            if !(-500..=500).contains(&value) {
+                return Err(TelloError::InvalidParameter(
+                    format!("Invalid {} distance: {}. Should be between -500 and 500 cm.", name, value),
+                ));
+            }
+        }
+        
+        if forward.abs() <= 20 && right.abs() <= 20 && up.abs() <= 20 {
+            let magnitude = ((forward * forward + right * right + up * up) as f32).sqrt();
+            return Err(TelloError::InvalidParameter(
+                format!("Move of {:.0} cm is too short. At least one distance must be outside -20..20 cm.", magnitude),
+            ));
+        }
+        
+        // The SDK's y axis points left
+        self.go(forward, -right, up, speed)
+    }
+    
+    /// Fly a curve through two points relative to the current position
+    ///
+    /// The drone flies an arc through `p1` and ends at `p2`. Coordinates are in
+    /// centimeters in the drone's frame, each within -500..=500, and speed is
+    /// in cm/s (10-60). The current position and the two points must not lie
+    /// on one line, since no arc passes through them.
+    pub fn curve(&mut self, p1: (i32, i32, i32), p2: (i32, i32, i32), speed: i32) -> TelloResult<()> {
+        for (name, value) in [("x1", p1.0), ("y1", p1.1), ("z1", p1.2), ("x2", p2.0), ("y2", p2.1), ("z2", p2.2)] {
+            if !(-500..=500).contains(&value) {
+                return Err(TelloError::InvalidParameter(
+                    format!("Invalid {} coordinate: {}. Should be between -500 and 500 cm.", name, value),
+                ));
+            }
+        }
+        
+        if !(10..=60).contains(&speed) {
+            return Err(TelloError::InvalidParameter(
+                format!("Invalid curve speed: {}. Should be between 10 and 60 cm/s.", speed),
+            ));
+        }
+        
+        // Origin, p1 and p2 are collinear when p1 x p2 is the zero vector
+        let cross = (
+            p1.1 * p2.2 - p1.2 * p2.1,
+            p1.2 * p2.0 - p1.0 * p2.2,
+            p1.0 * p2.1 - p1.1 * p2.0,
+        );
+        if cross == (0, 0, 0) {
+            return Err(TelloError::InvalidParameter(
+                format!("Invalid curve: points {:?} and {:?} are collinear with the current position.", p1, p2),
             ));
         }
         
-        let response = self.send_command(&format!("left {}", distance))?;
+        // Both points must be inside the geofence; the arc between them is not checked
+        for (x, y, z) in [p1, p2] {
+            self.check_geofence(self.project_body_offset(x as f32 / 100.0, y as f32 / 100.0, z as f32 / 100.0))?;
+        }
+        
+        let response = self.send_command(&format!(
+            "curve {} {} {} {} {} {} {}",
+            p1.0, p1.1, p1.2, p2.0, p2.1, p2.2, speed
+        ))?;
         
         if response != "ok" {
-            return Err(io::Error::new(
-                io::ErrorKind::Other,
-                format!("Left movement command failed: {}", response),
-            ));
+            return Err(TelloError::CommandRejected(response));
         }
         
-        // Update position tracking
-        self.update_position_after_movement("left", distance);
+        // The curve ends at the second point
+        self.update_position_after_go(p2.0, p2.1, p2.2);
         
         Ok(())
     }
     
-    /// Move the drone right by a specified distance in centimeters
-    pub fn right(&mut self, distance: i32) -> io::Result<()> {
-        if distance <= 0 || distance > 500 {
-            return Err(io::Error::new(
-                io::ErrorKind::InvalidInput,
-                format!("Invalid distance value: {}. Should be between 1 and 500 cm.", distance),
+    /// Fly to coordinates relative to a mission pad (Tello EDU)
+    ///
+    /// Like `go`, but `x`, `y` and `z` are in the frame of the detected pad
+    /// `pad_id` (1-8) instead of the drone's. Mission pad detection must be
+    /// enabled. The tracked position is not updated, since the pad's place
+    /// in it is unknown.
+    pub fn go_to_pad(&mut self, x: i32, y: i32, z: i32, speed: i32, pad_id: u8) -> TelloResult<()> {
+        check_pad_coordinates(x, y, z, speed)?;
+        self.check_pad_id(pad_id)?;
+        
+        let response = self.send_command(&format!("go {} {} {} {} m{}", x, y, z, speed, pad_id))?;
+        
+        if response != "ok" {
+            return Err(TelloError::CommandRejected(response));
+        }
+        Ok(())
+    }
+    
+    /// Hop from one mission pad to another (Tello EDU)
+    ///
+    /// The drone flies to `target` (x, y, z in cm) in the frame of pad
+    /// `from_pad`, finds pad `to_pad` there and turns to `yaw` degrees
+    /// (-360..=360) relative to it. Pad ids are 1-8 and mission pad detection
+    /// must be enabled. The tracked position is not updated.
+    pub fn jump(&mut self, target: (i32, i32, i32), speed: i32, yaw: i32, from_pad: u8, to_pad: u8) -> TelloResult<()> {
+        let (x, y, z) = target;
+        check_pad_coordinates(x, y, z, speed)?;
+        if !(-360..=360).contains(&yaw) {
+            return Err(TelloError::InvalidParameter(
+                format!("Invalid yaw: {}. Should be between -360 and 360 degrees.", yaw),
             ));
         }
+        self.check_pad_id(from_pad)?;
+        self.check_pad_id(to_pad)?;
         
-        let response = self.send_command(&format!("right {}", distance))?;
+        let response = self.send_command(&format!("jump {} {} {} {} {} m{} m{}", x, y, z, speed, yaw, from_pad, to_pad))?;
         
         if response != "ok" {
-            return Err(io::Error::new(
-                io::ErrorKind::Other,
-                format!("Right movement command failed: {}", response),
+            return Err(TelloError::CommandRejected(response));
+        }
+        Ok(())
+    }
+    
+    /// Check a mission pad id and that pad detection is on
+    fn check_pad_id(&self, pad_id: u8) -> TelloResult<()> {
+        if !(1..=8).contains(&pad_id) {
+            return Err(TelloError::InvalidParameter(
+                format!("Invalid mission pad id: {}. Should be between 1 and 8.", pad_id),
+            ));
+        }
+        if !self.mission_pads_enabled() {
+            return Err(TelloError::InvalidState(
+                String::from("Mission pad detection is off. Enable it first"),
+            ));
+        }
+        Ok(())
+    }
+    
+    /// Set the flight speed in cm/s (10-100)
+    pub fn set_speed(&mut self, cm_per_sec: i32) -> TelloResult<()> {
+        if !(10..=100).contains(&cm_per_sec) {
+            return Err(TelloError::InvalidParameter(
+                format!("Invalid speed value: {}. Should be between 10 and 100 cm/s.", cm_per_sec),
             ));
         }
         
-        // Update position tracking
-        self.update_position_after_movement("right", distance);
+        let response = self.send_command(&format!("speed {}", cm_per_sec))?;
+        
+        if response != "ok" {
+            return Err(TelloError::CommandRejected(response));
+        }
+        
+        self.speed = Some(cm_per_sec);
         
         Ok(())
     }
     
-    /// Move the drone up by a specified distance in centimeters
-    pub fn up(&mut self, distance: i32) -> io::Result<()> {
-        if distance <= 0 || distance > 500 {
-            return Err(io::Error::new(
-                io::ErrorKind::InvalidInput,
-                format!("Invalid distance value: {}. Should be between 1 and 500 cm.", distance),
+    /// Query the current flight speed in cm/s from the drone
+    pub fn get_speed(&self) -> TelloResult<f32> {
+        let response = self.send_command_with_response("speed?", DEFAULT_QUERY_TIMEOUT)?;
+        parse_speed(&response)
+    }
+    
+    /// Get the last speed set with `set_speed`, if any
+    pub fn last_set_speed(&self) -> Option<i32> {
+        self.speed
+    }
+    
+    /// Fly to a position (in meters) using the tracked position and heading
+    ///
+    /// The offset is converted into the drone's body frame and flown with one
+    /// or more "go" commands, each within the SDK limit of 500 cm per axis.
+    /// Speed is in cm/s (10-100).
+    pub fn fly_to(&mut self, target: Position, speed: i32) -> TelloResult<()> {
+        if !(10..=100).contains(&speed) {
+            return Err(TelloError::InvalidParameter(
+                format!("Invalid speed value: {}. Should be between 10 and 100 cm/s.", speed),
             ));
         }
         
-        let response = self.send_command(&format!("up {}", distance))?;
+        let (forward, left, up) = self.body_offset(target);
+        for (x, y, z) in plan_go_legs(forward, left, up)? {
+            self.go(x, y, z, speed)?;
+        }
         
-        if response != "ok" {
-            return Err(io::Error::new(
-                io::ErrorKind::Other,
-                format!("Upward movement command failed: {}", response),
+        Ok(())
+    }
+    
+    /// Offset in cm from the tracked position to a target, in the body frame
+    ///
+    /// Returns (forward, left, up), the inverse of update_position_after_go.
+    fn body_offset(&self, target: Position) -> (i32, i32, i32) {
+        // World offset in cm
+        let position = self.get_position();
+        let dx = (target.x - position.x) * 100.0;
+        let dy = (target.y - position.y) * 100.0;
+        let dz = (target.z - position.z) * 100.0;
+        
+        // Rotate into the body frame (x = forward, y = left)
+        let angle_rad = self.get_direction().to_radians();
+        let forward = (dx * angle_rad.sin() + dy * angle_rad.cos()).round() as i32;
+        let left = (dy * angle_rad.sin() - dx * angle_rad.cos()).round() as i32;
+        (forward, left, dz.round() as i32)
+    }
+    
+    /// Fly back to where position tracking started and face the start heading
+    ///
+    /// Keeps the current height. Within 20 cm of the origin only the heading
+    /// is turned back. Speed is in cm/s (10-100).
+    pub fn return_to_home(&mut self, speed: i32) -> TelloResult<()> {
+        let position = self.get_position();
+        if position.x == 0.0 && position.y == 0.0 && position.z == 0.0 && self.get_direction() == 0.0 {
+            self.logger.warn(format_args!("The tracked position was never moved from the origin, so it may not match the real drone"));
+        }
+        
+        let home = Position { x: 0.0, y: 0.0, z: position.z };
+        let (forward, left, _) = self.body_offset(home);
+        if forward.abs() > 20 || left.abs() > 20 {
+            self.fly_to(home, speed)?;
+        }
+        
+        self.turn_to_heading(0.0)
+    }
+    
+    /// Fly to a horizontal position (in meters) at the current height, then land
+    ///
+    /// The target is checked against the geofence before the drone moves.
+    /// Within 20 cm of the target the drone lands where it is. Flies at the
+    /// speed last set with `set_speed`, or 50 cm/s.
+    pub fn land_at(&mut self, x: f32, y: f32) -> TelloResult<()> {
+        let target = Position { x, y, z: self.get_position().z };
+        self.check_geofence(target)?;
+        
+        let (forward, left, _) = self.body_offset(target);
+        if forward.abs() > 20 || left.abs() > 20 {
+            self.fly_to(target, self.speed.unwrap_or(LAND_AT_SPEED))?;
+        }
+        
+        self.land()
+    }
+    
+    /// Fly through the positions of a CSV file saved with `export_path_csv`
+    ///
+    /// Each row's x, y and z (in meters) is flown to with `fly_to` in order.
+    /// The whole file is checked first: a row that doesn't parse, lies below
+    /// the ground, outside the geofence or above the ceiling fails the call
+    /// before the drone moves. Waypoints within 20 cm of the one before are
+    /// skipped, since the SDK can't fly such short hops. Speed is in cm/s (10-100).
+    pub fn fly_path_from_csv(&mut self, path: &str, speed: i32) -> TelloResult<()> {
+        if !(10..=100).contains(&speed) {
+            return Err(TelloError::InvalidParameter(
+                format!("Invalid speed value: {}. Should be between 10 and 100 cm/s.", speed),
             ));
         }
         
-        // Update position tracking
-        self.update_position_after_movement("up", distance);
+        let waypoints = parse_path_csv(&fs::read_to_string(path)?)?;
+        for (index, waypoint) in waypoints.iter().enumerate() {
+            let unreachable = |reason: String| TelloError::InvalidParameter(
+                format!("Waypoint {} ({:.2}, {:.2}, {:.2}) is unreachable: {}", index + 1, waypoint.x, waypoint.y, waypoint.z, reason),
+            );
+            if waypoint.z < 0.0 {
+                return Err(unreachable(String::from("it is below the ground")));
+            }
+            self.check_geofence(*waypoint).map_err(|e| unreachable(e.to_string()))?;
+            self.check_altitude((waypoint.z * 100.0).round() as i32).map_err(|e| unreachable(e.to_string()))?;
+        }
+        
+        for waypoint in waypoints {
+            let (forward, left, up) = self.body_offset(waypoint);
+            if forward.abs() <= 20 && left.abs() <= 20 && up.abs() <= 20 {
+                continue;
+            }
+            self.fly_to(waypoint, speed)?;
+        }
         
         Ok(())
     }
     
-    /// Move the drone down by a specified distance in centimeters
-    pub fn down(&mut self, distance: i32) -> io::Result<()> {
-        if distance <= 0 || distance > 500 {
-            return Err(io::Error::new(
-                io::ErrorKind::InvalidInput,
-                format!("Invalid distance value: {}. Should be between 1 and 500 cm.", distance),
+    /// Take off, climb or descend to `height_m` and face `heading_deg`
+    ///
+    /// The height is in meters (up to 8) and the heading in degrees
+    /// (0-359) relative to the tracked start heading.
+    pub fn takeoff_to(&mut self, height_m: f32, heading_deg: f32) -> TelloResult<()> {
+        if !(height_m > 0.0 && height_m <= 8.0) {
+            return Err(TelloError::InvalidParameter(
+                format!("Invalid height value: {}. Should be between 0 and 8 meters.", height_m),
             ));
         }
         
-        let response = self.send_command(&format!("down {}", distance))?;
+        if !(0.0..360.0).contains(&heading_deg) {
+            return Err(TelloError::InvalidParameter(
+                format!("Invalid heading value: {}. Should be between 0 and 359 degrees.", heading_deg),
+            ));
+        }
         
-        if response != "ok" {
-            return Err(io::Error::new(
-                io::ErrorKind::Other,
-                format!("Downward movement command failed: {}", response),
+        self.takeoff(None)?;
+        
+        // The drone hovers at about 1m after "takeoff"
+        let height_cm = (height_m * 100.0).round() as i32;
+        if height_cm > 100 {
+            self.up(height_cm - 100)?;
+        } else if height_cm < 100 {
+            self.down(100 - height_cm)?;
+        }
+        
+        self.turn_to_heading(heading_deg)
+    }
+    
+    /// Rotate to an absolute tracked heading through the shorter side
+    fn turn_to_heading(&mut self, heading_deg: f32) -> TelloResult<()> {
+        let turn = (heading_deg - self.get_direction()).round() as i32;
+        let turn = turn.rem_euclid(360);
+        if turn > 180 {
+            self.rotate_ccw(360 - turn)?;
+        } else if turn > 0 {
+            self.rotate_cw(turn)?;
+        }
+        
+        Ok(())
+    }
+    
+    /// Fly a square, turning clockwise at each corner
+    ///
+    /// Each of the four sides is flown forward and followed by a 90 degree
+    /// clockwise turn, so the drone ends where it started, facing the same
+    /// way. Sides over 500 cm are flown as several equal moves. The side is
+    /// in cm (at least 20) and the speed in cm/s (10-100). All corners are
+    /// checked against the geofence before the drone moves.
+    pub fn fly_square(&mut self, side_cm: i32, speed: i32) -> TelloResult<()> {
+        self.fly_polygon(4, side_cm, speed)
+    }
+    
+    /// Fly a regular polygon, turning clockwise at each corner
+    ///
+    /// Each side is flown forward and followed by a clockwise turn by the
+    /// exterior angle of 360 / `sides` degrees. Rotations are whole
+    /// degrees, so an angle that isn't is rounded with a warning and the
+    /// drone ends slightly off its start. There are 3 to 360 sides, and
+    /// side and speed are as in `fly_square`. All corners are checked
+    /// against the geofence before the drone moves.
+    pub fn fly_polygon(&mut self, sides: u32, side_cm: i32, speed: i32) -> TelloResult<()> {
+        if !(3..=360).contains(&sides) {
+            return Err(TelloError::InvalidParameter(
+                format!("Invalid number of sides: {}. Should be between 3 and 360.", sides),
+            ));
+        }
+        if side_cm < 20 {
+            return Err(TelloError::InvalidParameter(
+                format!("Invalid side length: {}. Should be at least 20 cm.", side_cm),
             ));
         }
         
-        // Update position tracking
-        self.update_position_after_movement("down", distance);
+        let exact_turn = 360.0 / sides as f32;
+        let turn = exact_turn.round() as i32;
+        if 360 % sides != 0 {
+            self.logger.warn(format_args!("A {}-sided polygon turns {:.2} degrees at each corner, turning {} instead",
+                sides, exact_turn, turn));
+        }
+        
+        // Corners in the start frame (forward, left), turning right each time
+        let side_m = side_cm as f32 / 100.0;
+        let (mut forward_m, mut left_m) = (0.0, 0.0);
+        for corner in 0..sides {
+            let (sin, cos) = (corner as f32 * turn as f32).to_radians().sin_cos();
+            forward_m += side_m * cos;
+            left_m -= side_m * sin;
+            self.check_geofence(self.project_body_offset(forward_m, left_m, 0.0))?;
+        }
+        
+        self.set_speed(speed)?;
+        for _ in 0..sides {
+            for leg in plan_side_legs(side_cm) {
+                self.forward(leg)?;
+            }
+            self.rotate_cw(turn)?;
+        }
         
         Ok(())
     }
+    
+    /// Fly a circle to the right of the drone with two half-circle curves
+    ///
+    /// The drone keeps its heading and ends where it started. The radius is
+    /// in cm (50-250, the limits of "curve") and the speed in cm/s (10-60).
+    /// The points of both curves are checked against the geofence before
+    /// the drone moves.
+    pub fn fly_circle(&mut self, radius_cm: i32, speed: i32) -> TelloResult<()> {
+        if !(50..=250).contains(&radius_cm) {
+            return Err(TelloError::InvalidParameter(
+                format!("Invalid circle radius: {}. Should be between 50 and 250 cm.", radius_cm),
+            ));
+        }
+        
+        // The points the curves pass in the start frame (forward, left)
+        let radius_m = radius_cm as f32 / 100.0;
+        for (forward_m, left_m) in [(radius_m, -radius_m), (0.0, -2.0 * radius_m), (-radius_m, -radius_m)] {
+            self.check_geofence(self.project_body_offset(forward_m, left_m, 0.0))?;
+        }
+        
+        // Each curve is relative to where the one before ended
+        self.curve((radius_cm, -radius_cm, 0), (0, -2 * radius_cm, 0), speed)?;
+        self.curve((-radius_cm, radius_cm, 0), (0, 2 * radius_cm, 0), speed)
+    }
+    
+    /// Fly a figure eight: the circle of `fly_circle`, then its mirror image to the left
+    ///
+    /// Radius and speed are as in `fly_circle`. The drone keeps its heading
+    /// and ends where it started. The points of both circles are checked
+    /// against the geofence before the drone moves.
+    pub fn fly_figure_eight(&mut self, radius_cm: i32, speed: i32) -> TelloResult<()> {
+        if !(50..=250).contains(&radius_cm) {
+            return Err(TelloError::InvalidParameter(
+                format!("Invalid circle radius: {}. Should be between 50 and 250 cm.", radius_cm),
+            ));
+        }
+        
+        let radius_m = radius_cm as f32 / 100.0;
+        for (forward_m, left_m) in [(radius_m, radius_m), (0.0, 2.0 * radius_m), (-radius_m, radius_m)] {
+            self.check_geofence(self.project_body_offset(forward_m, left_m, 0.0))?;
+        }
+        
+        self.fly_circle(radius_cm, speed)?;
+        self.curve((radius_cm, radius_cm, 0), (0, 2 * radius_cm, 0), speed)?;
+        self.curve((-radius_cm, -radius_cm, 0), (0, -2 * radius_cm, 0), speed)
+    }
+}
+
+/// Read the x, y and z columns of a "timestamp,x,y,z,heading" CSV as positions
+///
+/// The header line and empty lines are skipped.
+fn parse_path_csv(csv: &str) -> TelloResult<Vec<Position>> {
+    let mut waypoints = Vec::new();
+    
+    for (number, line) in csv.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || (number == 0 && line.starts_with("timestamp")) {
+            continue;
+        }
+        
+        let columns: Vec<&str> = line.split(',').map(str::trim).collect();
+        let coordinate = |column: usize| columns.get(column)
+            .and_then(|value| value.parse::<f32>().ok())
+            .filter(|value| value.is_finite());
+        match (coordinate(1), coordinate(2), coordinate(3)) {
+            (Some(x), Some(y), Some(z)) => waypoints.push(Position { x, y, z }),
+            _ => return Err(TelloError::InvalidParameter(
+                format!("Line {}: expected timestamp,x,y,z,heading but got '{}'", number + 1, line),
+            )),
+        }
+    }
+    
+    Ok(waypoints)
+}
+
+/// Check the target and speed of a pad relative "go" or "jump"
+fn check_pad_coordinates(x: i32, y: i32, z: i32, speed: i32) -> TelloResult<()> {
+    for (name, value) in [("x", x), ("y", y), ("z", z)] {
+        if !(-500..=500).contains(&value) {
+            return Err(TelloError::InvalidParameter(
+                format!("Invalid {} coordinate: {}. Should be between -500 and 500 cm.", name, value),
+            ));
+        }
+    }
+    
+    if !(10..=100).contains(&speed) {
+        return Err(TelloError::InvalidParameter(
+            format!("Invalid speed value: {}. Should be between 10 and 100 cm/s.", speed),
+        ));
+    }
+    Ok(())
+}
+
+/// Split a movement distance in cm into moves the SDK accepts (1-500 cm)
+///
+/// Without clamping anything out of range is rejected.
+fn plan_move_legs(distance: i32, clamp: bool, logger: &Logger) -> TelloResult<Vec<i32>> {
+    if (1..=500).contains(&distance) {
+        return Ok(vec![distance]);
+    }
+    
+    if !clamp {
+        return Err(TelloError::InvalidParameter(
+            format!("Invalid distance value: {}. Should be between 1 and 500 cm.", distance),
+        ));
+    }
+    
+    if distance < 1 {
+        logger.warn(format_args!("Distance {} cm is below 1 cm, moving 1 cm instead", distance));
+        return Ok(vec![1]);
+    }
+    
+    logger.warn(format_args!("Distance {} cm is over 500 cm, splitting it into several moves", distance));
+    let mut legs = vec![500; (distance / 500) as usize];
+    if distance % 500 > 0 {
+        legs.push(distance % 500);
+    }
+    Ok(legs)
+}
+
+/// Split a square side in cm into equal moves of at most 500 cm
+///
+/// The remainder goes to the first moves, so they differ by at most 1 cm.
+fn plan_side_legs(side: i32) -> Vec<i32> {
+    let legs = (side + 499) / 500;
+    (0..legs).map(|leg| side / legs + i32::from(leg < side % legs)).collect()
+}
+
+/// Split a body frame offset in cm into "go" legs of at most 500 cm per axis
+///
+/// All legs are equal up to 1 cm so none falls below the SDK minimum
+/// of 20 cm unless the whole offset does, which is rejected.
+pub(crate) fn plan_go_legs(x: i32, y: i32, z: i32) -> TelloResult<Vec<(i32, i32, i32)>> {
+    if x.abs() <= 20 && y.abs() <= 20 && z.abs() <= 20 {
+        return Err(TelloError::InvalidParameter(
+            format!("Target offset ({}, {}, {}) cm is too close. At least one axis must be over 20 cm.", x, y, z),
+        ));
+    }
+    
+    let longest = x.abs().max(y.abs()).max(z.abs());
+    let legs = (longest + 499) / 500;
+    
+    // Spread each axis evenly, giving the remainder to the first legs
+    let split = |value: i32, leg: i32| value / legs + if leg < (value % legs).abs() { value.signum() } else { 0 };
+    
+    Ok((0..legs).map(|leg| (split(x, leg), split(y, leg), split(z, leg))).collect())
+}
+
+/// Estimated time to fly `distance_cm` at `speed_cm_s`
+///
+/// Adds a fixed overhead for speeding up and braking.
+pub fn estimate_move_time(distance_cm: i32, speed_cm_s: i32) -> Duration {
+    let seconds = distance_cm.unsigned_abs() as f64 / speed_cm_s.max(1) as f64;
+    Duration::from_secs_f64(seconds) + MOVE_OVERHEAD
 }
@@ -0,0 +1,143 @@
+/*
+ * DJI Tello Drone Controller Library
+ *
+ * Copyright (c) 2025 aleskin
+ *
+ * This file is part of dji_tello_lib.
+ *
+ * dji_tello_lib is free software: you can redistribute it and/or modify
+ * it under the terms of the MIT License as published.
+ *
+ * Created: March 30, 2025
+ */
+
+// Transports carry SDK commands to the drone and bring back its responses
+use std::collections::HashMap;
+use std::io;
+use std::net::{SocketAddr, UdpSocket};
+use std::str;
+use std::time::{Duration, Instant};
+
+/// A channel for sending SDK commands to a drone
+///
+/// `Tello` talks to the drone only through this trait, so a simulator or a
+/// test double can stand in for the real UDP connection.
+pub trait CommandTransport: Send {
+    /// Send a command and wait for a single response
+    fn send_command(&self, command: &str) -> io::Result<String>;
+    
+    /// Send a command and wait at most `timeout` for the response
+    ///
+    /// Transports without their own timeout handling fall back to `send_command`.
+    fn send_command_with_timeout(&self, command: &str, timeout: Duration) -> io::Result<String> {
+        let _ = timeout;
+        self.send_command(command)
+    }
+    
+    /// Send a command the drone never answers, such as "rc"
+    fn send_without_response(&self, command: &str) -> io::Result<()>;
+}
+
+/// The real connection: SDK commands over UDP
+pub struct UdpTransport {
+    socket: UdpSocket,
+    tello_addr: SocketAddr,
+}
+
+impl UdpTransport {
+    /// Use a bound socket to talk to the drone at `tello_addr`
+    ///
+    /// The drone answers to the port a command was sent from, so responses
+    /// are read from this socket, and only datagrams from `tello_addr` are
+    /// taken as responses. Replies from any other source address, e.g. one
+    /// rewritten by NAT, are skipped, so the command times out. The socket's
+    /// read timeout is used as the default response timeout.
+    pub fn new(socket: UdpSocket, tello_addr: SocketAddr) -> Self {
+        UdpTransport { socket, tello_addr }
+    }
+}
+
+impl CommandTransport for UdpTransport {
+    fn send_command(&self, command: &str) -> io::Result<String> {
+        self.socket.send_to(command.as_bytes(), self.tello_addr)?;
+        
+        // Only the drone's answer counts, packets from other senders are skipped
+        let deadline = self.socket.read_timeout()?.map(|timeout| Instant::now() + timeout);
+        let mut buffer = [0; 1024];
+        loop {
+            let (amount, from) = self.socket.recv_from(&mut buffer)?;
+            if from == self.tello_addr {
+                return Ok(str::from_utf8(&buffer[..amount])
+                    .unwrap_or("Invalid UTF-8 response")
+                    .to_string());
+            }
+            if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+                return Err(io::Error::new(io::ErrorKind::TimedOut, "no response from the drone"));
+            }
+        }
+    }
+    
+    fn send_command_with_timeout(&self, command: &str, timeout: Duration) -> io::Result<String> {
+        // Apply the per-call timeout and restore the socket default afterwards
+        let previous_timeout = self.socket.read_timeout()?;
+        self.socket.set_read_timeout(Some(timeout))?;
+        let result = self.send_command(command);
+        self.socket.set_read_timeout(previous_timeout)?;
+        result
+    }
+    
+    fn send_without_response(&self, command: &str) -> io::Result<()> {
+        self.socket.send_to(command.as_bytes(), self.tello_addr)?;
+        Ok(())
+    }
+}
+
+/// A stand-in for the drone that prints commands to stderr instead of sending them
+///
+/// Every command is answered with "ok" unless a canned response is set for
+/// it. "battery?" answers "100" by default so the takeoff battery check passes.
+pub struct DryRunTransport {
+    responses: HashMap<String, String>,
+}
+
+impl Default for DryRunTransport {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DryRunTransport {
+    pub fn new() -> Self {
+        let mut responses = HashMap::new();
+        responses.insert(String::from("battery?"), String::from("100"));
+        DryRunTransport { responses }
+    }
+    
+    /// Answer `command` with `response`
+    ///
+    /// `command` is either a full command such as "forward 50" or just its
+    /// name, which then matches it with any arguments.
+    pub fn set_response(&mut self, command: &str, response: &str) {
+        self.responses.insert(command.to_string(), response.to_string());
+    }
+    
+    fn response_for(&self, command: &str) -> String {
+        let name = command.split_whitespace().next().unwrap_or(command);
+        self.responses.get(command)
+            .or_else(|| self.responses.get(name))
+            .cloned()
+            .unwrap_or_else(|| String::from("ok"))
+    }
+}
+
+impl CommandTransport for DryRunTransport {
+    fn send_command(&self, command: &str) -> io::Result<String> {
+        eprintln!("[dry run] {}", command);
+        Ok(self.response_for(command))
+    }
+    
+    fn send_without_response(&self, command: &str) -> io::Result<()> {
+        eprintln!("[dry run] {}", command);
+        Ok(())
+    }
+}
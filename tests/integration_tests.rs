@@ -11,7 +11,7 @@ use std::time::Duration;
 fn test_command_separation() {
     // Start the drone application
     let mut child = Command::new("cargo")
-        .args(&["run", "--quiet"])
+        .args(["run", "--quiet"])
         .current_dir("..")
         .stdin(Stdio::piped())
         .stdout(Stdio::piped())
@@ -29,11 +29,9 @@ fn test_command_separation() {
         use std::io::{BufRead, BufReader};
         let reader = BufReader::new(stdout);
         
-        for line in reader.lines() {
-            if let Ok(line) = line {
-                println!("App output: {}", line);
-                tx.send(line).unwrap();
-            }
+        for line in reader.lines().map_while(Result::ok) {
+            println!("App output: {}", line);
+            tx.send(line).unwrap();
         }
     });
     
@@ -54,6 +52,7 @@ fn test_command_separation() {
     
     // Send exit command to cleanly terminate the app
     writeln!(stdin, "exit").expect("Failed to write exit command");
+    let _ = child.wait();
     
     // Check if both commands were processed
     let takeoff_command_found = output_lines.iter().any(|line| line.contains("Takeoff command executed"));
@@ -79,4 +78,30 @@ fn test_excessive_height_parameter() {
     // Similar setup as above...
     // This would test that when height > 8m is provided (e.g., "takeoff 10"),
     // the application uses the default height and reports a warning
-}
\ No newline at end of file
+}
+// With --json a batch run prints nothing but the JSON on stdout
+#[test]
+fn test_json_batch_output() {
+    let home = std::env::temp_dir().join(format!("tello_json_home_{}", std::process::id()));
+    std::fs::create_dir_all(&home).unwrap();
+    
+    let output = Command::new(env!("CARGO_BIN_EXE_dji_tello_lib"))
+        .args(["--dry-run", "--json", "--exec", "state; info"])
+        .env("HOME", &home)
+        .stdin(Stdio::null())
+        .output()
+        .expect("Failed to run the application");
+    let _ = std::fs::remove_dir_all(&home);
+    
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let lines: Vec<&str> = stdout.lines().collect();
+    assert_eq!(lines.len(), 2, "unexpected stdout: {}", stdout);
+    for line in lines {
+        serde_json::from_str::<serde_json::Value>(line).unwrap();
+    }
+    
+    // The echo and progress lines went to stderr instead
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("> state"));
+}